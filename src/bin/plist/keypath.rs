@@ -0,0 +1,193 @@
+//! Key paths address values nested in arrays and dictionaries, e.g. `Lines.0`.
+//!
+//! Components are separated by `.`, which may be escaped as `\.` within a dictionary key. Array
+//! elements are addressed by their index.
+
+use plist::Value;
+
+fn parse(keypath: &str) -> Vec<String> {
+    let mut keys = vec![String::new()];
+    let mut chars = keypath.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('.') => keys.last_mut().unwrap().push('.'),
+                Some(c) => keys.last_mut().unwrap().extend(&['\\', c]),
+                None => keys.last_mut().unwrap().push('\\'),
+            },
+            '.' => keys.push(String::new()),
+            c => keys.last_mut().unwrap().push(c),
+        }
+    }
+    keys
+}
+
+fn child<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    match value {
+        Value::Dictionary(dict) => dict.get(key),
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+fn child_mut<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Dictionary(dict) => dict.get_mut(key),
+        Value::Array(array) => array.get_mut(key.parse::<usize>().ok()?),
+        _ => None,
+    }
+}
+
+fn lookup_mut<'a>(mut value: &'a mut Value, keys: &[String]) -> Option<&'a mut Value> {
+    for key in keys {
+        value = child_mut(value, key)?;
+    }
+    Some(value)
+}
+
+/// Returns the value at `keypath`.
+pub fn get<'a>(root: &'a Value, keypath: &str) -> Option<&'a Value> {
+    parse(keypath)
+        .iter()
+        .try_fold(root, |value, key| child(value, key))
+}
+
+/// Inserts `value` at `keypath`, which must not already hold a value. If `append` is set
+/// `keypath` must instead name an array to which `value` is appended.
+pub fn insert(root: &mut Value, keypath: &str, value: Value, append: bool) -> Result<(), String> {
+    let keys = parse(keypath);
+    if append {
+        return match lookup_mut(root, &keys) {
+            Some(Value::Array(array)) => {
+                array.push(value);
+                Ok(())
+            }
+            Some(_) => Err(format!("Value at [{}] is not an array", keypath)),
+            None => Err(not_found(keypath)),
+        };
+    }
+
+    let (last, parent) = keys.split_last().expect("a key path has at least one key");
+    match lookup_mut(root, parent) {
+        Some(Value::Dictionary(dict)) if dict.contains_key(last) => {
+            Err(format!("Value already exists at key path {}", keypath))
+        }
+        Some(Value::Dictionary(dict)) => {
+            dict.insert(last.clone(), value);
+            Ok(())
+        }
+        Some(Value::Array(array)) => match last.parse::<usize>() {
+            Ok(index) if index <= array.len() => {
+                array.insert(index, value);
+                Ok(())
+            }
+            _ => Err(not_found(keypath)),
+        },
+        _ => Err(not_found(keypath)),
+    }
+}
+
+/// Replaces the value at `keypath`. A missing dictionary entry is inserted.
+pub fn replace(root: &mut Value, keypath: &str, value: Value) -> Result<(), String> {
+    let keys = parse(keypath);
+    let (last, parent) = keys.split_last().expect("a key path has at least one key");
+    match lookup_mut(root, parent) {
+        Some(Value::Dictionary(dict)) => {
+            dict.insert(last.clone(), value);
+            Ok(())
+        }
+        Some(Value::Array(array)) => match last.parse::<usize>() {
+            Ok(index) if index < array.len() => {
+                array[index] = value;
+                Ok(())
+            }
+            _ => Err(not_found(keypath)),
+        },
+        _ => Err(not_found(keypath)),
+    }
+}
+
+/// Removes the value at `keypath`, preserving the order of the remaining values.
+pub fn remove(root: &mut Value, keypath: &str) -> Result<(), String> {
+    let keys = parse(keypath);
+    let (last, parent) = keys.split_last().expect("a key path has at least one key");
+    let removed = match lookup_mut(root, parent) {
+        Some(Value::Dictionary(dict)) => dict.shift_remove(last).is_some(),
+        Some(Value::Array(array)) => match last.parse::<usize>() {
+            Ok(index) if index < array.len() => {
+                array.remove(index);
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    };
+    if removed {
+        Ok(())
+    } else {
+        Err(format!("No value to remove at key path {}", keypath))
+    }
+}
+
+fn not_found(keypath: &str) -> String {
+    format!("Key path not found {}", keypath)
+}
+
+#[cfg(test)]
+mod tests {
+    use plist::Dictionary;
+
+    use super::*;
+
+    fn sample() -> Value {
+        let mut dict = Dictionary::new();
+        dict.insert("a.b".to_owned(), 1.into());
+        dict.insert(
+            "list".to_owned(),
+            Value::Array(vec!["x".into(), "y".into()]),
+        );
+        Value::Dictionary(dict)
+    }
+
+    #[test]
+    fn parse_escapes() {
+        assert_eq!(parse("a.0.b"), ["a", "0", "b"]);
+        assert_eq!(parse(r"a\.b.c\d"), ["a.b", r"c\d"]);
+        assert_eq!(parse(""), [""]);
+    }
+
+    #[test]
+    fn get_values() {
+        let value = sample();
+        assert_eq!(get(&value, r"a\.b"), Some(&1.into()));
+        assert_eq!(get(&value, "list.1"), Some(&"y".into()));
+        assert_eq!(get(&value, "list.2"), None);
+        assert_eq!(get(&value, "a"), None);
+    }
+
+    #[test]
+    fn modify_values() {
+        let mut value = sample();
+        insert(&mut value, "list.0", "w".into(), false).unwrap();
+        insert(&mut value, "list", "z".into(), true).unwrap();
+        insert(&mut value, "new", true.into(), false).unwrap();
+        assert!(insert(&mut value, "new", false.into(), false).is_err());
+        assert!(insert(&mut value, "missing.key", false.into(), false).is_err());
+        assert!(insert(&mut value, "new", false.into(), true).is_err());
+
+        replace(&mut value, "new", false.into()).unwrap();
+        replace(&mut value, "list.1", "X".into()).unwrap();
+        assert!(replace(&mut value, "list.4", "X".into()).is_err());
+
+        remove(&mut value, r"a\.b").unwrap();
+        assert!(remove(&mut value, r"a\.b").is_err());
+
+        let mut expected = Dictionary::new();
+        expected.insert(
+            "list".to_owned(),
+            Value::Array(vec!["w".into(), "X".into(), "y".into(), "z".into()]),
+        );
+        expected.insert("new".to_owned(), false.into());
+        assert_eq!(value, Value::Dictionary(expected));
+    }
+}
@@ -0,0 +1,483 @@
+//! A command line tool for checking, converting and editing plists, compatible with Apple's
+//! `plutil`.
+//!
+//! Built when the `cli` feature is enabled. Run `plist -help` for usage.
+
+mod keypath;
+
+use std::{
+    env,
+    fmt::Display,
+    fs,
+    io::{self, Cursor, Read, Write},
+    path::Path,
+    process, slice,
+};
+
+use chrono::{DateTime, Utc};
+use plist::{
+    json::JsonOptions,
+    stream::{AsciiWriter, BinaryWriteOptions, BinaryWriter, PrettyWriter, Writer},
+    Date, Dictionary, Integer, Value,
+};
+
+const USAGE: &str = "\
+plist: [command_option] [other_options] file...
+The file '-' means stdin
+Command options are (-lint is the default):
+ -help                         show this message and exit
+ -lint                         check the property list files for syntax errors
+ -convert fmt                  rewrite property list files in format
+                               fmt is one of: xml1 binary1 json ascii
+ -p                            print property list in a human-readable fashion
+                               (not for machine parsing! this 'format' is not stable)
+ -insert keypath -type value   insert a value into the property list before writing it out
+                               keypath is a key-value coding key path, with one extension:
+                               a numerical path component applied to an array will act on the
+                               object at that index in the array or insert it into the array if
+                               the numerical path component is the last one in the key path
+                               type is one of: bool, integer, float, date, string, data, xml,
+                               json, array, dictionary (array and dictionary take no value)
+                               -append appends value to the array at keypath
+ -replace keypath -type value  same as -insert, but it will replace an existing value
+ -remove keypath               removes the value at 'keypath' from the property list before
+                               writing it out
+ -extract keypath fmt          outputs a copy of the property list at 'keypath' in 'fmt'
+                               fmt is one of: xml1 binary1 json raw
+                               -n prevents printing a terminating newline if 'fmt' is raw
+Other options are (not all are applicable to all commands):
+ -s                            be silent on success
+ -o path                       specify alternate file path name for result;
+                               the -o option is used with -convert, and is only
+                               useful with one file argument (last file overwrites);
+                               the path '-' means stdout
+ -e extension                  specify alternate extension for converted files
+ -r                            if writing JSON, output in human-readable form
+ --                            specifies that all further arguments are file names
+";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Xml1,
+    Binary1,
+    Json,
+    Ascii,
+    Raw,
+}
+
+impl Format {
+    fn parse(name: &str, allow_raw: bool) -> Result<Format, String> {
+        match name {
+            "xml1" => Ok(Format::Xml1),
+            "binary1" => Ok(Format::Binary1),
+            "json" => Ok(Format::Json),
+            "ascii" if !allow_raw => Ok(Format::Ascii),
+            "raw" if allow_raw => Ok(Format::Raw),
+            _ => Err(format!("Unknown format specifier: {}", name)),
+        }
+    }
+}
+
+enum Command {
+    Help,
+    Lint,
+    Print,
+    Convert(Format),
+    Extract(String, Format),
+    Insert(String, Value),
+    Replace(String, Value),
+    Remove(String),
+}
+
+#[derive(Default)]
+struct Options {
+    output: Option<String>,
+    extension: Option<String>,
+    human_readable: bool,
+    silent: bool,
+    no_newline: bool,
+    append: bool,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let stdout = io::stdout();
+    process::exit(run(&args, &mut stdout.lock()));
+}
+
+/// Runs the command described by `args`, writing anything destined for stdout to `out` and
+/// returning the process exit status.
+fn run(args: &[String], out: &mut dyn Write) -> i32 {
+    let (command, options, files) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprint!("{}", USAGE);
+            return 1;
+        }
+    };
+    if let Command::Help = command {
+        return match out.write_all(USAGE.as_bytes()) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        };
+    }
+
+    let mut status = 0;
+    for file in &files {
+        if let Err(message) = run_file(&command, &options, file, out) {
+            eprintln!("{}: {}", file, message);
+            status = 1;
+        }
+    }
+    status
+}
+
+fn parse_args(all_args: &[String]) -> Result<(Command, Options, Vec<String>), String> {
+    let mut args = all_args.iter();
+    let command = match args.next().map(String::as_str) {
+        Some("-help") => return Ok((Command::Help, Options::default(), Vec::new())),
+        Some("-lint") => Command::Lint,
+        Some("-p") => Command::Print,
+        Some("-convert") => {
+            Command::Convert(Format::parse(next_arg(&mut args, "-convert")?, false)?)
+        }
+        Some("-extract") => {
+            let keypath = next_arg(&mut args, "-extract")?.to_owned();
+            let format = Format::parse(next_arg(&mut args, "-extract")?, true)?;
+            Command::Extract(keypath, format)
+        }
+        Some("-insert") => {
+            let keypath = next_arg(&mut args, "-insert")?.to_owned();
+            Command::Insert(keypath, parse_value(&mut args)?)
+        }
+        Some("-replace") => {
+            let keypath = next_arg(&mut args, "-replace")?.to_owned();
+            Command::Replace(keypath, parse_value(&mut args)?)
+        }
+        Some("-remove") => Command::Remove(next_arg(&mut args, "-remove")?.to_owned()),
+        // Like `plutil`, lint when no command is given.
+        _ => return parse_options(Command::Lint, all_args.iter()),
+    };
+    parse_options(command, args)
+}
+
+fn parse_options(
+    command: Command,
+    mut args: slice::Iter<'_, String>,
+) -> Result<(Command, Options, Vec<String>), String> {
+    let mut options = Options::default();
+    let mut files = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--" => {
+                files.extend(args.cloned());
+                break;
+            }
+            "-o" => options.output = Some(next_arg(&mut args, "-o")?.to_owned()),
+            "-e" => options.extension = Some(next_arg(&mut args, "-e")?.to_owned()),
+            "-r" => options.human_readable = true,
+            "-s" => options.silent = true,
+            "-n" => options.no_newline = true,
+            "-append" => options.append = true,
+            "-" => files.push(arg.clone()),
+            arg if arg.starts_with('-') => return Err(format!("unrecognized option: {}", arg)),
+            _ => files.push(arg.clone()),
+        }
+    }
+
+    if files.is_empty() {
+        return Err("No files specified.".to_owned());
+    }
+    Ok((command, options, files))
+}
+
+fn next_arg<'a>(args: &mut slice::Iter<'a, String>, option: &str) -> Result<&'a str, String> {
+    args.next()
+        .map(String::as_str)
+        .ok_or_else(|| format!("Missing argument for {}.", option))
+}
+
+/// Parses the `-type value` arguments of `-insert` and `-replace`.
+fn parse_value(args: &mut slice::Iter<'_, String>) -> Result<Value, String> {
+    let kind = next_arg(args, "-type")?;
+    match kind {
+        "-array" => return Ok(Value::Array(Vec::new())),
+        "-dictionary" => return Ok(Value::Dictionary(Dictionary::new())),
+        _ => (),
+    }
+
+    let text = next_arg(args, kind)?;
+    let invalid = || format!("Invalid value for {}: {}", kind, text);
+    Ok(match kind {
+        "-bool" => match text.to_ascii_lowercase().as_str() {
+            "yes" | "true" | "1" => Value::Boolean(true),
+            "no" | "false" | "0" => Value::Boolean(false),
+            _ => return Err(invalid()),
+        },
+        "-integer" => Value::Integer(match text.parse::<i64>() {
+            Ok(value) => Integer::from(value),
+            Err(_) => Integer::from(text.parse::<u64>().map_err(|_| invalid())?),
+        }),
+        "-float" => Value::Real(text.parse().map_err(|_| invalid())?),
+        "-string" => Value::String(text.to_owned()),
+        "-date" => {
+            let date = DateTime::parse_from_rfc3339(text).map_err(|_| invalid())?;
+            Value::Date(Date::from(date.with_timezone(&Utc)))
+        }
+        "-data" => Value::Data(base64::decode(text).map_err(|_| invalid())?),
+        "-xml" => Value::from_reader_xml(text.as_bytes()).map_err(|_| invalid())?,
+        "-json" => {
+            let json = serde_json::from_str(text).map_err(|_| invalid())?;
+            JsonOptions::new().from_json(json).map_err(|_| invalid())?
+        }
+        _ => return Err(format!("unrecognized type: {}", kind)),
+    })
+}
+
+fn run_file(
+    command: &Command,
+    options: &Options,
+    file: &str,
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let (mut value, format) = read_plist(file)?;
+    match command {
+        Command::Help => unreachable!("help is handled before reading files"),
+        Command::Lint => {
+            if !options.silent {
+                writeln!(out, "{}: OK", file).map_err(to_string)?;
+            }
+            Ok(())
+        }
+        Command::Print => {
+            let mut writer = PrettyWriter::new(out);
+            for event in value.events() {
+                writer.write(&event).map_err(|err| err.to_string())?;
+            }
+            Ok(())
+        }
+        Command::Convert(format) => {
+            write_output(file, options, &encode(&value, *format, options)?, out)
+        }
+        Command::Extract(keypath, format) => {
+            let value = keypath::get(&value, keypath).ok_or_else(|| {
+                format!(
+                    "Could not extract value, error: No value at that key path or invalid key \
+                     path: {}",
+                    keypath
+                )
+            })?;
+            write_output(file, options, &encode(value, *format, options)?, out)
+        }
+        Command::Insert(keypath, new) => {
+            keypath::insert(&mut value, keypath, new.clone(), options.append).map_err(modify)?;
+            write_output(file, options, &encode(&value, format, options)?, out)
+        }
+        Command::Replace(keypath, new) => {
+            keypath::replace(&mut value, keypath, new.clone()).map_err(modify)?;
+            write_output(file, options, &encode(&value, format, options)?, out)
+        }
+        Command::Remove(keypath) => {
+            keypath::remove(&mut value, keypath).map_err(modify)?;
+            write_output(file, options, &encode(&value, format, options)?, out)
+        }
+    }
+}
+
+fn modify(message: String) -> String {
+    format!("Could not modify plist, error: {}", message)
+}
+
+/// Reads a plist of any supported encoding, returning it with the format it was read from.
+fn read_plist(file: &str) -> Result<(Value, Format), String> {
+    let mut bytes = Vec::new();
+    let read = if file == "-" {
+        io::stdin().read_to_end(&mut bytes).map(drop)
+    } else {
+        fs::read(file).map(|contents| bytes = contents)
+    };
+    read.map_err(|_| "file does not exist or is not readable or is not a regular file")?;
+
+    let first = bytes.iter().find(|byte| !byte.is_ascii_whitespace());
+    if let Some(b'{') | Some(b'[') = first {
+        let json = serde_json::from_slice(&bytes).map_err(|err| err.to_string())?;
+        let value = JsonOptions::new()
+            .from_json(json)
+            .map_err(|err| err.to_string())?;
+        return Ok((value, Format::Json));
+    }
+
+    let format = if bytes.starts_with(b"bplist") {
+        Format::Binary1
+    } else {
+        Format::Xml1
+    };
+    let value = Value::from_reader(Cursor::new(bytes)).map_err(|err| err.to_string())?;
+    Ok((value, format))
+}
+
+fn encode(value: &Value, format: Format, options: &Options) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    match format {
+        Format::Xml1 => value.to_writer_xml(&mut bytes).map_err(to_string)?,
+        Format::Binary1 => {
+            // Number objects as `plutil` does so that converted files match its output.
+            let options = BinaryWriteOptions::new().apple_compatible(true);
+            let mut writer = BinaryWriter::new_with_options(&mut bytes, options);
+            for event in value.events() {
+                writer.write(&event).map_err(to_string)?;
+            }
+        }
+        Format::Json => {
+            if !is_json_compatible(value) {
+                return Err("invalid object in plist for destination format".to_owned());
+            }
+            let json = JsonOptions::new().to_json(value).map_err(to_string)?;
+            if options.human_readable {
+                serde_json::to_writer_pretty(&mut bytes, &json).map_err(to_string)?;
+            } else {
+                serde_json::to_writer(&mut bytes, &json).map_err(to_string)?;
+            }
+        }
+        Format::Ascii => {
+            let mut writer = AsciiWriter::new(&mut bytes);
+            for event in value.events() {
+                writer.write(&event).map_err(to_string)?;
+            }
+        }
+        Format::Raw => {
+            bytes = raw(value)?.into_bytes();
+            if !options.no_newline {
+                bytes.push(b'\n');
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+fn to_string<E: Display>(err: E) -> String {
+    err.to_string()
+}
+
+/// Returns whether `value` has a direct JSON equivalent. Like `plutil`, dates, data and uids are
+/// rejected rather than converted to strings or numbers.
+fn is_json_compatible(value: &Value) -> bool {
+    match value {
+        Value::Array(array) => array.iter().all(is_json_compatible),
+        Value::Dictionary(dict) => dict.values().all(is_json_compatible),
+        Value::Boolean(_) | Value::Integer(_) | Value::Real(_) | Value::String(_) => true,
+        _ => false,
+    }
+}
+
+/// Formats a value for `-extract keypath raw`. Arrays and dictionaries are written as their
+/// number of elements.
+fn raw(value: &Value) -> Result<String, String> {
+    Ok(match value {
+        Value::String(string) => string.clone(),
+        Value::Boolean(value) => value.to_string(),
+        Value::Integer(value) => value.to_string(),
+        Value::Real(value) => value.to_string(),
+        Value::Date(date) => format!("{:?}", date),
+        Value::Data(data) => base64::encode(data),
+        Value::Uid(uid) => uid.get().to_string(),
+        Value::Array(array) => array.len().to_string(),
+        Value::Dictionary(dict) => dict.len().to_string(),
+        _ => return Err("invalid object in plist for destination format".to_owned()),
+    })
+}
+
+/// Writes the result for `file` to the path given by `-o` or `-e`, `out`, or back to `file`.
+fn write_output(
+    file: &str,
+    options: &Options,
+    bytes: &[u8],
+    out: &mut dyn Write,
+) -> Result<(), String> {
+    let path = match (&options.output, &options.extension) {
+        (Some(path), _) if path == "-" => None,
+        (Some(path), _) => Some(Path::new(path).to_owned()),
+        (None, _) if file == "-" => None,
+        (None, Some(extension)) => Some(Path::new(file).with_extension(extension)),
+        (None, None) => Some(Path::new(file).to_owned()),
+    };
+
+    match path {
+        Some(path) => fs::write(&path, bytes)
+            .map_err(|err| format!("Could not write to {}: {}", path.display(), err)),
+        None => out
+            .write_all(bytes)
+            .and_then(|()| out.flush())
+            .map_err(to_string),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the command, returning its exit status and output.
+    fn run_args(args: &[&str]) -> (i32, String) {
+        let args: Vec<String> = args.iter().map(|&arg| arg.to_owned()).collect();
+        let mut out = Vec::new();
+        let status = run(&args, &mut out);
+        (status, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn lint() {
+        assert_eq!(
+            run_args(&["-lint", "tests/data/xml.plist"]),
+            (0, "tests/data/xml.plist: OK\n".to_owned())
+        );
+        assert_eq!(
+            run_args(&["-s", "tests/data/binary.plist"]),
+            (0, "".to_owned())
+        );
+        assert_eq!(
+            run_args(&["-lint", "tests/data/xml_error.plist"]),
+            (1, "".to_owned())
+        );
+    }
+
+    #[test]
+    fn missing_file() {
+        assert_eq!(
+            run_args(&["-lint", "tests/data/missing.plist"]),
+            (1, "".to_owned())
+        );
+    }
+
+    #[test]
+    fn unknown_option() {
+        assert_eq!(
+            run_args(&["-lint", "-q", "tests/data/xml.plist"]),
+            (1, "".to_owned())
+        );
+    }
+
+    #[test]
+    fn extract_raw_to_stdout() {
+        let args = [
+            "-extract",
+            "Author",
+            "raw",
+            "-o",
+            "-",
+            "tests/data/xml.plist",
+        ];
+        assert_eq!(run_args(&args), (0, "William Shakespeare\n".to_owned()));
+
+        let args = [
+            "-extract",
+            "Lines",
+            "raw",
+            "-n",
+            "-o",
+            "-",
+            "tests/data/xml.plist",
+        ];
+        assert_eq!(run_args(&args), (0, "2".to_owned()));
+    }
+}
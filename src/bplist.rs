@@ -0,0 +1,777 @@
+//! Structural inspection of binary plists.
+//!
+//! `BinaryReader` only checks what it needs to decode the objects reachable from the root.
+//! `inspect` decodes every object in the offset table and reports anything unusual about the
+//! layout of the file, which helps when debugging files produced by other encoders.
+//!
+//! ```
+//! let bytes = std::fs::read("tests/data/binary.plist").unwrap();
+//! let inspection = plist::bplist::inspect(&bytes).unwrap();
+//!
+//! let root = &inspection.objects[inspection.trailer.root_object as usize];
+//! assert_eq!(root.kind, Some(plist::bplist::ObjectKind::Dictionary));
+//! assert_eq!(root.children.len(), 26);
+//! assert!(inspection.issues.is_empty());
+//! ```
+
+use std::{char, str};
+
+use crate::{
+    error::{Error, ErrorKind},
+    stream, u64_to_usize,
+};
+
+const HEADER_LEN: u64 = 8;
+const TRAILER_LEN: usize = 32;
+
+/// The fields of the 32 byte trailer at the end of a binary plist.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Trailer {
+    /// Unused by CoreFoundation and normally zero.
+    pub sort_version: u8,
+    /// The size in bytes of each entry in the offset table.
+    pub offset_size: u8,
+    /// The size in bytes of each object reference in an array or dictionary.
+    pub object_ref_size: u8,
+    pub num_objects: u64,
+    pub root_object: u64,
+    pub offset_table_offset: u64,
+}
+
+/// The type of a binary plist object, as given by its marker byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ObjectKind {
+    Null,
+    Boolean,
+    Fill,
+    Integer,
+    Real,
+    Date,
+    Data,
+    AsciiString,
+    Utf16String,
+    Uid,
+    Array,
+    Dictionary,
+}
+
+/// An object in the object table.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Object {
+    /// The offset of the object's marker byte from the start of the file.
+    pub offset: u64,
+    /// The object's type, or `None` if it lies outside the object table or its marker byte is
+    /// unknown.
+    pub kind: Option<ObjectKind>,
+    /// The number of bytes occupied by the object, including its marker byte, length and any
+    /// object references, or `None` if it could not be decoded.
+    pub len: Option<u64>,
+    /// The objects referenced by an array, or the keys followed by the values of a dictionary.
+    pub children: Vec<u64>,
+    /// The arrays and dictionaries referencing this object, with one entry per reference.
+    pub parents: Vec<u64>,
+}
+
+/// A problem or irregularity found by `inspect`.
+///
+/// None of these prevent other objects from being inspected. `BinaryReader` rejects
+/// `InvalidObject`, `InvalidReference` and `RecursiveObject` if they are reachable from the root
+/// object and accepts the rest.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Issue {
+    /// The object lies in the header or at or after the offset table.
+    ObjectOutsideObjectTable { object: u64 },
+    /// The object could not be decoded.
+    InvalidObject { object: u64, error: Error },
+    /// An array or dictionary references an object not in the offset table.
+    InvalidReference { object: u64, reference: u64 },
+    /// The object is an array or dictionary which contains itself.
+    RecursiveObject { object: u64 },
+    /// The bytes of two objects overlap.
+    OverlappingObjects { first: u64, second: u64 },
+    /// The object's offset is not greater than the offset of the previous object in the offset
+    /// table.
+    OutOfOrderObject { object: u64 },
+    /// The object is neither the root object nor reachable from it.
+    UnreferencedObject { object: u64 },
+    /// An integer, uid or length in the object is encoded using `width` bytes where
+    /// CoreFoundation would use `canonical` bytes. A width of zero is a length stored in the
+    /// marker byte.
+    NonCanonicalInteger {
+        object: u64,
+        width: u8,
+        canonical: u8,
+    },
+    /// The trailer's object reference size differs from the size CoreFoundation would use.
+    NonCanonicalObjectRefSize { canonical: u8 },
+    /// The trailer's offset size differs from the size CoreFoundation would use.
+    NonCanonicalOffsetSize { canonical: u8 },
+    /// The children of the array or dictionary were not recorded as the objects in the offset
+    /// table already reference more objects in total than the object table has bytes. This only
+    /// happens if objects share offsets or overlap, and limits the memory used by `inspect`.
+    ReferenceLimitExceeded { object: u64 },
+}
+
+/// The structure of a binary plist.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Inspection {
+    pub trailer: Trailer,
+    /// The offset of each object, indexed by object reference.
+    pub offset_table: Vec<u64>,
+    /// Every object, indexed by object reference.
+    pub objects: Vec<Object>,
+    pub issues: Vec<Issue>,
+}
+
+impl Inspection {
+    /// Returns the objects referenced more than once, e.g. strings used as keys in many
+    /// dictionaries.
+    pub fn shared_objects(&self) -> impl Iterator<Item = u64> + '_ {
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|(_, object)| object.parents.len() > 1)
+            .map(|(object_ref, _)| object_ref as u64)
+    }
+}
+
+/// Inspects the structure of a binary plist.
+///
+/// Fails only if the header, trailer or offset table are invalid. Problems with individual
+/// objects are reported in `Inspection::issues`.
+pub fn inspect(bytes: &[u8]) -> Result<Inspection, Error> {
+    if !stream::is_binary_magic(bytes) {
+        return Err(ErrorKind::InvalidMagic.with_byte_offset(0));
+    }
+    if bytes.len() < HEADER_LEN as usize + TRAILER_LEN {
+        return Err(ErrorKind::UnexpectedEof.with_byte_offset(bytes.len() as u64));
+    }
+
+    let trailer_start = bytes.len() - TRAILER_LEN;
+    let trailer = read_trailer(&bytes[trailer_start..], trailer_start as u64)?;
+    let offset_table = read_offset_table(bytes, &trailer, trailer_start as u64)?;
+    let object_table = &bytes[..trailer.offset_table_offset as usize];
+
+    let mut issues = Vec::new();
+    let canonical = byte_count(trailer.num_objects);
+    if trailer.object_ref_size != canonical {
+        issues.push(Issue::NonCanonicalObjectRefSize { canonical });
+    }
+    let canonical = byte_count(trailer.offset_table_offset);
+    if trailer.offset_size != canonical {
+        issues.push(Issue::NonCanonicalOffsetSize { canonical });
+    }
+
+    // Every reference in a file without shared or overlapping objects occupies at least one byte
+    // of the object table.
+    let mut remaining_children = object_table.len() as u64;
+    let mut objects = Vec::with_capacity(offset_table.len());
+    for (object_ref, &offset) in offset_table.iter().enumerate() {
+        let object_ref = object_ref as u64;
+        let mut object = Object {
+            offset,
+            kind: None,
+            len: None,
+            children: Vec::new(),
+            parents: Vec::new(),
+        };
+
+        if offset < HEADER_LEN || offset >= trailer.offset_table_offset {
+            issues.push(Issue::ObjectOutsideObjectTable { object: object_ref });
+            objects.push(object);
+            continue;
+        }
+
+        let mut parser = ObjectParser {
+            bytes: object_table,
+            pos: offset as usize,
+            ref_size: trailer.object_ref_size,
+            max_children: remaining_children,
+            kind: None,
+            widths: Vec::new(),
+        };
+        match parser.parse() {
+            Ok(Some(children)) => {
+                object.len = Some(parser.pos as u64 - offset);
+                remaining_children -= children.len() as u64;
+                object.children = children;
+            }
+            Ok(None) => {
+                object.len = Some(parser.pos as u64 - offset);
+                issues.push(Issue::ReferenceLimitExceeded { object: object_ref });
+            }
+            Err(error) => issues.push(Issue::InvalidObject {
+                object: object_ref,
+                error,
+            }),
+        }
+        object.kind = parser.kind;
+        for (width, canonical) in parser.widths {
+            issues.push(Issue::NonCanonicalInteger {
+                object: object_ref,
+                width,
+                canonical,
+            });
+        }
+        objects.push(object);
+    }
+
+    link_parents(&mut objects, &mut issues);
+    check_layout(&objects, &mut issues);
+    check_reachability(&objects, trailer.root_object, &mut issues);
+
+    Ok(Inspection {
+        trailer,
+        offset_table,
+        objects,
+        issues,
+    })
+}
+
+fn read_trailer(bytes: &[u8], trailer_start: u64) -> Result<Trailer, Error> {
+    let read_u64 = |start: usize| {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(&bytes[start..start + 8]);
+        u64::from_be_bytes(buf)
+    };
+
+    let trailer = Trailer {
+        sort_version: bytes[5],
+        offset_size: bytes[6],
+        object_ref_size: bytes[7],
+        num_objects: read_u64(8),
+        root_object: read_u64(16),
+        offset_table_offset: read_u64(24),
+    };
+
+    if !matches!(trailer.offset_size, 1 | 2 | 4 | 8) {
+        return Err(ErrorKind::InvalidTrailerObjectOffsetSize.with_byte_offset(trailer_start + 6));
+    }
+    if !matches!(trailer.object_ref_size, 1 | 2 | 4 | 8) {
+        return Err(
+            ErrorKind::InvalidTrailerObjectReferenceSize.with_byte_offset(trailer_start + 7)
+        );
+    }
+    if trailer.root_object >= trailer.num_objects {
+        return Err(ErrorKind::ObjectReferenceTooLarge.with_byte_offset(trailer_start + 16));
+    }
+    Ok(trailer)
+}
+
+fn read_offset_table(
+    bytes: &[u8],
+    trailer: &Trailer,
+    trailer_start: u64,
+) -> Result<Vec<u64>, Error> {
+    let table_end = trailer
+        .num_objects
+        .checked_mul(trailer.offset_size.into())
+        .and_then(|len| trailer.offset_table_offset.checked_add(len));
+    match table_end {
+        Some(end) if trailer.offset_table_offset >= HEADER_LEN && end <= trailer_start => (),
+        _ => return Err(ErrorKind::ObjectOffsetTooLarge.with_byte_offset(trailer_start + 24)),
+    }
+
+    let start = trailer.offset_table_offset as usize;
+    let len = trailer.num_objects as usize * trailer.offset_size as usize;
+    Ok(bytes[start..start + len]
+        .chunks(trailer.offset_size.into())
+        .map(read_be_uint)
+        .collect())
+}
+
+fn link_parents(objects: &mut [Object], issues: &mut Vec<Issue>) {
+    let num_objects = objects.len() as u64;
+    for parent in 0..objects.len() {
+        for i in 0..objects[parent].children.len() {
+            let child = objects[parent].children[i];
+            if child < num_objects {
+                objects[child as usize].parents.push(parent as u64);
+            } else {
+                issues.push(Issue::InvalidReference {
+                    object: parent as u64,
+                    reference: child,
+                });
+            }
+        }
+    }
+}
+
+fn check_layout(objects: &[Object], issues: &mut Vec<Issue>) {
+    for (object_ref, pair) in objects.windows(2).enumerate() {
+        if pair[1].offset <= pair[0].offset {
+            issues.push(Issue::OutOfOrderObject {
+                object: object_ref as u64 + 1,
+            });
+        }
+    }
+
+    let mut extents: Vec<(u64, u64, u64)> = objects
+        .iter()
+        .enumerate()
+        .filter_map(|(object_ref, object)| {
+            let len = object.len?;
+            Some((object.offset, object.offset + len, object_ref as u64))
+        })
+        .collect();
+    extents.sort_unstable();
+
+    let mut furthest: Option<(u64, u64)> = None;
+    for (start, end, object_ref) in extents {
+        match furthest {
+            Some((furthest_end, furthest_ref)) if start < furthest_end => {
+                issues.push(Issue::OverlappingObjects {
+                    first: furthest_ref,
+                    second: object_ref,
+                });
+                if end > furthest_end {
+                    furthest = Some((end, object_ref));
+                }
+            }
+            _ => furthest = Some((end, object_ref)),
+        }
+    }
+}
+
+fn check_reachability(objects: &[Object], root_object: u64, issues: &mut Vec<Issue>) {
+    #[derive(Clone, Copy, PartialEq)]
+    enum State {
+        Unvisited,
+        OnStack,
+        Visited,
+    }
+
+    let mut states = vec![State::Unvisited; objects.len()];
+    let mut recursive = Vec::new();
+    // Each item is an object and the index of its next child to visit.
+    let mut stack = vec![(root_object as usize, 0)];
+    states[root_object as usize] = State::OnStack;
+
+    while let Some((object_ref, next_child)) = stack.last_mut() {
+        let object_ref = *object_ref;
+        let child = match objects[object_ref].children.get(*next_child) {
+            Some(&child) => child as usize,
+            None => {
+                states[object_ref] = State::Visited;
+                stack.pop();
+                continue;
+            }
+        };
+        *next_child += 1;
+
+        match states.get(child) {
+            Some(State::Unvisited) => {
+                states[child] = State::OnStack;
+                stack.push((child, 0));
+            }
+            Some(State::OnStack) if !recursive.contains(&child) => recursive.push(child),
+            _ => (),
+        }
+    }
+
+    for object in recursive {
+        issues.push(Issue::RecursiveObject {
+            object: object as u64,
+        });
+    }
+    for (object_ref, state) in states.into_iter().enumerate() {
+        if state == State::Unvisited {
+            issues.push(Issue::UnreferencedObject {
+                object: object_ref as u64,
+            });
+        }
+    }
+}
+
+struct ObjectParser<'a> {
+    /// The bytes of the file up to the start of the offset table.
+    bytes: &'a [u8],
+    pos: usize,
+    ref_size: u8,
+    /// The number of object references which may be returned.
+    max_children: u64,
+    kind: Option<ObjectKind>,
+    /// The widths and canonical widths of non-canonically encoded integers.
+    widths: Vec<(u8, u8)>,
+}
+
+impl<'a> ObjectParser<'a> {
+    /// Decodes the object at `pos`, returning its object references or `None` if there are more
+    /// than `max_children`.
+    fn parse(&mut self) -> Result<Option<Vec<u64>>, Error> {
+        let token = self.take(1)?[0];
+        let (ty, size) = (token >> 4, token & 0x0f);
+        self.kind = match (ty, size) {
+            (0x0, 0x0) => Some(ObjectKind::Null),
+            (0x0, 0x8) | (0x0, 0x9) => Some(ObjectKind::Boolean),
+            (0x0, 0xf) => Some(ObjectKind::Fill),
+            (0x1, 0..=4) => Some(ObjectKind::Integer),
+            (0x2, 2) | (0x2, 3) => Some(ObjectKind::Real),
+            (0x3, 3) => Some(ObjectKind::Date),
+            (0x4, _) => Some(ObjectKind::Data),
+            (0x5, _) => Some(ObjectKind::AsciiString),
+            (0x6, _) => Some(ObjectKind::Utf16String),
+            (0x8, 0..=7) => Some(ObjectKind::Uid),
+            (0xa, _) => Some(ObjectKind::Array),
+            (0xd, _) => Some(ObjectKind::Dictionary),
+            _ => return Err(self.error(ErrorKind::UnknownObjectType(token))),
+        };
+
+        match (ty, size) {
+            (0x1, 4) => {
+                let value = read_be_uint_i128(self.take(16)?);
+                if value < 0 || value > u64::MAX.into() {
+                    return Err(self.error(ErrorKind::IntegerOutOfRange));
+                }
+                self.check_width(16, value);
+            }
+            (0x1, _) => {
+                let width = 1 << size;
+                let value = read_be_uint(self.take(width)?);
+                // Only eight byte integers are signed.
+                let value = if width == 8 {
+                    i128::from(value as i64)
+                } else {
+                    value.into()
+                };
+                self.check_width(width as u8, value);
+            }
+            (0x2, _) => {
+                self.take(1 << size)?;
+            }
+            (0x3, _) => {
+                let secs = f64::from_bits(read_be_uint(self.take(8)?));
+                if !secs.is_finite() {
+                    return Err(self.error(ErrorKind::InfiniteOrNanDate));
+                }
+            }
+            (0x4, _) => {
+                let len = self.read_len(size)?;
+                self.take_u64(len)?;
+            }
+            (0x5, _) => {
+                let len = self.read_len(size)?;
+                let start = self.pos;
+                let bytes = self.take_u64(len)?;
+                if str::from_utf8(bytes).is_err() {
+                    self.pos = start;
+                    return Err(self.error(ErrorKind::InvalidUtf8String));
+                }
+            }
+            (0x6, _) => {
+                let len = self.read_len(size)?;
+                let byte_len = len
+                    .checked_mul(2)
+                    .ok_or_else(|| self.error(ErrorKind::ObjectTooLarge))?;
+                let start = self.pos;
+                let bytes = self.take_u64(byte_len)?;
+                let units = bytes
+                    .chunks(2)
+                    .map(|unit| u16::from_be_bytes([unit[0], unit[1]]));
+                if char::decode_utf16(units).any(|c| c.is_err()) {
+                    self.pos = start;
+                    return Err(self.error(ErrorKind::InvalidUtf16String));
+                }
+            }
+            (0x8, _) => {
+                let width = size + 1;
+                let value = read_be_uint(self.take(width.into())?);
+                self.check_width(width, value.into());
+            }
+            (0xa, _) => {
+                let len = self.read_len(size)?;
+                return self.read_refs(len);
+            }
+            (0xd, _) => {
+                let len = self.read_len(size)?;
+                let len = len
+                    .checked_mul(2)
+                    .ok_or_else(|| self.error(ErrorKind::ObjectTooLarge))?;
+                return self.read_refs(len);
+            }
+            _ => (),
+        }
+        Ok(Some(Vec::new()))
+    }
+
+    /// Reads the length of an object. `size` must contain the low 4 bits of the object token.
+    fn read_len(&mut self, size: u8) -> Result<u64, Error> {
+        if size != 0x0f {
+            return Ok(size.into());
+        }
+
+        let token = self.take(1)?[0];
+        if token >> 4 != 0x1 || token & 0x0f > 3 {
+            return Err(self.error(ErrorKind::InvalidObjectLength));
+        }
+        let width = 1 << (token & 0x0f);
+        let len = read_be_uint(self.take(width)?);
+        if len < 0x0f {
+            self.widths.push((width as u8, 0));
+        } else {
+            self.check_width(width as u8, len.into());
+        }
+        Ok(len)
+    }
+
+    fn read_refs(&mut self, len: u64) -> Result<Option<Vec<u64>>, Error> {
+        let byte_len = len
+            .checked_mul(self.ref_size.into())
+            .ok_or_else(|| self.error(ErrorKind::ObjectOffsetTooLarge))?;
+        let bytes = self.take_u64(byte_len)?;
+        if len > self.max_children {
+            return Ok(None);
+        }
+        Ok(Some(
+            bytes
+                .chunks(self.ref_size.into())
+                .map(read_be_uint)
+                .collect(),
+        ))
+    }
+
+    /// Records `width` if CoreFoundation would encode `value` using a different number of bytes.
+    fn check_width(&mut self, width: u8, value: i128) {
+        let canonical = match value {
+            v if v < 0 => 8,
+            v if v <= 0xff => 1,
+            v if v <= 0xffff => 2,
+            v if v <= 0xffff_ffff => 4,
+            v if v <= i64::MAX.into() => 8,
+            _ => 16,
+        };
+        if width != canonical {
+            self.widths.push((width, canonical));
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], Error> {
+        match self.pos.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => {
+                let bytes = &self.bytes[self.pos..end];
+                self.pos = end;
+                Ok(bytes)
+            }
+            _ => Err(self.error(ErrorKind::ObjectOffsetTooLarge)),
+        }
+    }
+
+    fn take_u64(&mut self, len: u64) -> Result<&'a [u8], Error> {
+        let len = u64_to_usize(len).ok_or_else(|| self.error(ErrorKind::ObjectOffsetTooLarge))?;
+        self.take(len)
+    }
+
+    fn error(&self, kind: ErrorKind) -> Error {
+        kind.with_byte_offset(self.pos as u64)
+    }
+}
+
+/// Returns the number of bytes CoreFoundation uses for an offset or reference no larger than
+/// `value`.
+fn byte_count(value: u64) -> u8 {
+    match value {
+        0..=0xff => 1,
+        0x100..=0xffff => 2,
+        0x1_0000..=0xffff_ffff => 4,
+        _ => 8,
+    }
+}
+
+fn read_be_uint(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| (value << 8) | u64::from(byte))
+}
+
+fn read_be_uint_i128(bytes: &[u8]) -> i128 {
+    let mut buf = [0; 16];
+    buf.copy_from_slice(bytes);
+    i128::from_be_bytes(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Builds a binary plist from an object table, appending the offset table and trailer.
+    fn build(objects: &[&[u8]], offsets: Option<&[u8]>, ref_size: u8, root: u8) -> Vec<u8> {
+        let mut bytes = b"bplist00".to_vec();
+        let mut computed = Vec::new();
+        for object in objects {
+            computed.push(bytes.len() as u8);
+            bytes.extend_from_slice(object);
+        }
+        let offset_table_offset = bytes.len() as u64;
+        let offsets = offsets.unwrap_or(&computed);
+        bytes.extend_from_slice(offsets);
+
+        bytes.extend_from_slice(&[0; 6]);
+        bytes.push(1);
+        bytes.push(ref_size);
+        bytes.extend_from_slice(&(offsets.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&u64::from(root).to_be_bytes());
+        bytes.extend_from_slice(&offset_table_offset.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn apple_plists_are_canonical() {
+        for path in &[
+            "./tests/data/binary.plist",
+            "./tests/data/binary_NSKeyedArchiver.plist",
+            "./tests/data/utf16_bplist.plist",
+        ] {
+            let bytes = fs::read(path).unwrap();
+            let inspection = inspect(&bytes).unwrap();
+            assert!(
+                inspection.issues.is_empty(),
+                "{}: {:?}",
+                path,
+                inspection.issues
+            );
+            assert_eq!(
+                inspection.objects.len() as u64,
+                inspection.trailer.num_objects
+            );
+        }
+    }
+
+    #[test]
+    fn objects_and_sharing() {
+        // ["a", "a", 1] with both strings sharing object 1.
+        let bytes = build(&[&[0xa3, 1, 1, 2], b"Qa", &[0x10, 1]], None, 1, 0);
+        let inspection = inspect(&bytes).unwrap();
+        assert!(inspection.issues.is_empty(), "{:?}", inspection.issues);
+        assert_eq!(inspection.offset_table, [8, 12, 14]);
+
+        let objects = &inspection.objects;
+        assert_eq!(objects[0].kind, Some(ObjectKind::Array));
+        assert_eq!(objects[0].len, Some(4));
+        assert_eq!(objects[0].children, [1, 1, 2]);
+        assert_eq!(objects[1].kind, Some(ObjectKind::AsciiString));
+        assert_eq!(objects[1].len, Some(2));
+        assert_eq!(objects[1].parents, [0, 0]);
+        assert_eq!(objects[2].kind, Some(ObjectKind::Integer));
+        assert_eq!(inspection.shared_objects().collect::<Vec<_>>(), [1]);
+    }
+
+    #[test]
+    fn structural_issues() {
+        let bytes = build(
+            &[
+                // A dictionary referencing itself, an invalid object and a missing object.
+                &[0xd1, 0, 9],
+                // An integer 1 encoded using 2 bytes.
+                &[0x11, 0, 1],
+                // Data with a length of 2 stored in a 1 byte extended length.
+                &[0x4f, 0x10, 2, 0xaa, 0xbb],
+                // An unknown object type.
+                &[0x70],
+            ],
+            // Object 3 overlaps the integer and is out of order.
+            Some(&[8, 11, 14, 12, 40, 19]),
+            1,
+            0,
+        );
+        let inspection = inspect(&bytes).unwrap();
+        let issues = &inspection.issues;
+
+        let has = |f: &dyn Fn(&Issue) -> bool| issues.iter().any(f);
+        assert!(has(&|i| matches!(i, Issue::RecursiveObject { object: 0 })));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::InvalidReference {
+                object: 0,
+                reference: 9
+            }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::NonCanonicalInteger {
+                object: 1,
+                width: 2,
+                canonical: 1
+            }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::NonCanonicalInteger {
+                object: 2,
+                width: 1,
+                canonical: 0
+            }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::OverlappingObjects {
+                first: 1,
+                second: 3
+            }
+        )));
+        assert!(has(&|i| matches!(i, Issue::OutOfOrderObject { object: 3 })));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::ObjectOutsideObjectTable { object: 4 }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::UnreferencedObject { object: 2 }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            Issue::InvalidObject { object: 5, .. }
+        )));
+        assert_eq!(inspection.objects[3].kind, Some(ObjectKind::Null));
+        assert_eq!(inspection.objects[5].kind, None);
+        assert!(inspection.objects[1].parents.is_empty());
+    }
+
+    #[test]
+    fn shared_offsets_limit_references() {
+        // An array of 20 references to an integer, shared by three further offset table entries.
+        let mut array = vec![0xaf, 0x10, 20];
+        array.extend_from_slice(&[1; 20]);
+        let bytes = build(&[&array, &[0x10, 1]], Some(&[8, 31, 8, 8, 8]), 1, 0);
+        let inspection = inspect(&bytes).unwrap();
+
+        assert_eq!(inspection.objects[0].children.len(), 20);
+        for object in 2..5 {
+            assert!(inspection.objects[object].children.is_empty());
+            assert_eq!(inspection.objects[object].len, Some(23));
+            assert!(inspection.issues.iter().any(|i| matches!(
+                i,
+                Issue::ReferenceLimitExceeded { object: o } if *o == object as u64
+            )));
+        }
+        assert_eq!(inspection.objects[1].parents, [0; 20]);
+    }
+
+    #[test]
+    fn invalid_trailer() {
+        assert!(matches!(
+            inspect(b"notaplist").unwrap_err().kind(),
+            ErrorKind::InvalidMagic
+        ));
+
+        let mut bytes = build(&[&[0x08]], None, 1, 0);
+        let len = bytes.len();
+        bytes[len - 25] = 3;
+        assert!(matches!(
+            inspect(&bytes).unwrap_err().kind(),
+            ErrorKind::InvalidTrailerObjectReferenceSize
+        ));
+
+        let bytes = build(&[&[0x08]], None, 1, 1);
+        assert!(matches!(
+            inspect(&bytes).unwrap_err().kind(),
+            ErrorKind::ObjectReferenceTooLarge
+        ));
+    }
+}
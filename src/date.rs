@@ -1,6 +1,7 @@
-use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, Utc};
 use std::{
-    fmt,
+    fmt::{self, Write},
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
@@ -8,7 +9,7 @@ use std::{
 ///
 /// Note that while this type implements `Serialize` and `Deserialize` it will behave strangely if
 /// used with serializers from outside this crate.
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Date {
     inner: SystemTime,
 }
@@ -20,15 +21,44 @@ impl Date {
     const PLIST_EPOCH_UNIX_TIMESTAMP: Duration = Duration::from_secs(978_307_200);
 
     pub(crate) fn from_rfc3339(date: &str) -> Result<Self, ()> {
-        let offset: DateTime<FixedOffset> = DateTime::parse_from_rfc3339(date).map_err(|_| ())?;
-        Ok(Date {
-            inner: offset.with_timezone(&Utc).into(),
-        })
+        let (secs, nanos) = parse_rfc3339(date).ok_or(())?;
+        let inner = if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::new(secs as u64, nanos))
+        } else {
+            UNIX_EPOCH
+                .checked_sub(Duration::from_secs(secs.unsigned_abs()))
+                .and_then(|time| time.checked_add(Duration::new(0, nanos)))
+        };
+        inner.map(|inner| Date { inner }).ok_or(())
+    }
+
+    pub(crate) fn to_rfc3339(self) -> String {
+        let (secs, nanos) = self.unix_timestamp();
+        format_rfc3339(secs, nanos, false)
     }
 
-    pub(crate) fn to_rfc3339(&self) -> String {
-        let datetime: DateTime<Utc> = self.inner.into();
-        datetime.to_rfc3339_opts(SecondsFormat::Secs, true)
+    /// Formats the date as an RFC 3339 string which, unlike `to_rfc3339`, keeps any fractional
+    /// seconds so that the date can be parsed back exactly.
+    #[cfg(feature = "serde")]
+    pub(crate) fn to_rfc3339_exact(self) -> String {
+        let (secs, nanos) = self.unix_timestamp();
+        format_rfc3339(secs, nanos, true)
+    }
+
+    /// Returns the number of whole seconds since the unix epoch, rounded down, and the remaining
+    /// nanoseconds.
+    fn unix_timestamp(self) -> (i64, u32) {
+        match self.inner.duration_since(UNIX_EPOCH) {
+            Ok(dur) => (dur.as_secs() as i64, dur.subsec_nanos()),
+            Err(err) => {
+                let dur = err.duration();
+                let secs = -(dur.as_secs() as i64);
+                match dur.subsec_nanos() {
+                    0 => (secs, 0),
+                    nanos => (secs - 1, 1_000_000_000 - nanos),
+                }
+            }
+        }
     }
 
     pub(crate) fn from_seconds_since_plist_epoch(
@@ -57,7 +87,7 @@ impl Date {
         Ok(Date { inner })
     }
 
-    pub(crate) fn to_seconds_since_plist_epoch(&self) -> f64 {
+    pub(crate) fn to_seconds_since_plist_epoch(self) -> f64 {
         // needed until #![feature(duration_float)] is stabilized
         fn as_secs_f64(d: Duration) -> f64 {
             const NANOS_PER_SEC: f64 = 1_000_000_000.00;
@@ -84,16 +114,197 @@ impl From<SystemTime> for Date {
     }
 }
 
-impl Into<SystemTime> for Date {
-    fn into(self) -> SystemTime {
-        self.inner
+impl From<Date> for SystemTime {
+    fn from(date: Date) -> Self {
+        date.inner
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<DateTime<Utc>> for Date {
+    fn from(date: DateTime<Utc>) -> Self {
+        Date { inner: date.into() }
+    }
+}
+
+#[cfg(feature = "chrono")]
+impl From<Date> for DateTime<Utc> {
+    fn from(date: Date) -> Self {
+        date.inner.into()
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<time::OffsetDateTime> for Date {
+    fn from(date: time::OffsetDateTime) -> Self {
+        Date { inner: date.into() }
+    }
+}
+
+#[cfg(feature = "time")]
+impl From<Date> for time::OffsetDateTime {
+    fn from(date: Date) -> Self {
+        date.inner.into()
+    }
+}
+
+/// Parses an RFC 3339 timestamp, e.g. `1981-05-16T11:32:06.5+02:00`, into the number of seconds
+/// since the unix epoch and the remaining nanoseconds. Digits of the fractional seconds beyond
+/// nanosecond precision are ignored.
+fn parse_rfc3339(date: &str) -> Option<(i64, u32)> {
+    let bytes = date.as_bytes();
+    let number = |start: usize, len: usize| -> Option<u32> {
+        bytes
+            .get(start..start + len)?
+            .iter()
+            .try_fold(0, |n: u32, b| {
+                if b.is_ascii_digit() {
+                    Some(n * 10 + u32::from(b - b'0'))
+                } else {
+                    None
+                }
+            })
+    };
+    let is_separator =
+        |pos: usize, separators: &[u8]| matches!(bytes.get(pos), Some(b) if separators.contains(b));
+
+    let separators = [(4, b"-"), (7, b"-"), (13, b":"), (16, b":")];
+    if !separators.iter().all(|(pos, sep)| is_separator(*pos, *sep)) || !is_separator(10, b"Tt ") {
+        return None;
+    }
+    let year = i64::from(number(0, 4)?);
+    let month = number(5, 2)?;
+    let day = number(8, 2)?;
+    let hour = number(11, 2)?;
+    let minute = number(14, 2)?;
+    let second = number(17, 2)?;
+    if month == 0 || month > 12 || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    // A second of 60 is a leap second, which is treated as the first second of the next minute.
+    if hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    let mut pos = 19;
+    let mut nanos = 0;
+    if is_separator(pos, b".") {
+        pos += 1;
+        let start = pos;
+        while let Some(b) = bytes.get(pos).filter(|b| b.is_ascii_digit()) {
+            if pos - start < 9 {
+                nanos = nanos * 10 + u32::from(b - b'0');
+            }
+            pos += 1;
+        }
+        if pos == start {
+            return None;
+        }
+        for _ in pos - start..9 {
+            nanos *= 10;
+        }
+    }
+
+    let offset = match bytes.get(pos) {
+        Some(b'Z') | Some(b'z') if pos + 1 == bytes.len() => 0,
+        Some(&sign) if (sign == b'+' || sign == b'-') && pos + 6 == bytes.len() => {
+            let offset_hours = number(pos + 1, 2)?;
+            let offset_minutes = number(pos + 4, 2)?;
+            if !is_separator(pos + 3, b":") || offset_hours > 23 || offset_minutes > 59 {
+                return None;
+            }
+            let offset = i64::from(offset_hours * 3600 + offset_minutes * 60);
+            if sign == b'-' {
+                -offset
+            } else {
+                offset
+            }
+        }
+        _ => return None,
+    };
+
+    let secs = days_from_civil(year, month, day) * 86_400
+        + i64::from(hour * 3600 + minute * 60 + second)
+        - offset;
+    Some((secs, nanos))
+}
+
+/// Formats a number of seconds since the unix epoch and the remaining nanoseconds as an RFC 3339
+/// timestamp in UTC. If `subsec` is set non-zero fractional seconds are written with three, six or
+/// nine digits.
+fn format_rfc3339(secs: i64, nanos: u32, subsec: bool) -> String {
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+
+    let mut formatted = if (0..=9999).contains(&year) {
+        format!("{:04}", year)
+    } else {
+        format!("{:+05}", year)
+    };
+    let _ = write!(
+        formatted,
+        "-{:02}-{:02}T{:02}:{:02}:{:02}",
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    );
+    if subsec && nanos != 0 {
+        let fraction = format!("{:09}", nanos);
+        let mut fraction = fraction.as_str();
+        while fraction.ends_with("000") {
+            fraction = &fraction[..fraction.len() - 3];
+        }
+        formatted.push('.');
+        formatted.push_str(fraction);
+    }
+    formatted.push('Z');
+    formatted
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
     }
 }
 
+/// Returns the number of days since the unix epoch of a date in the proleptic Gregorian calendar.
+///
+/// Uses the algorithm from <http://howardhinnant.github.io/date_algorithms.html>, which counts
+/// years from March so that leap days fall at the end of the year.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month_from_march = i64::from((month + 9) % 12);
+    let day_of_year = (153 * month_from_march + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`, returning the year, month and day.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u32;
+    let month = ((month_from_march + 2) % 12 + 1) as u32;
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
 #[cfg(feature = "serde")]
 pub mod serde_impls {
     use serde::{
-        de::{Deserialize, Deserializer, Error, Unexpected, Visitor},
+        de::{Deserialize, Deserializer, Error, MapAccess, Unexpected, Visitor},
         ser::{Serialize, Serializer},
     };
     use std::fmt;
@@ -101,13 +312,17 @@ pub mod serde_impls {
     use crate::Date;
 
     pub const DATE_NEWTYPE_STRUCT_NAME: &str = "PLIST-DATE";
+    /// `Deserializer::deserialize_any` passes dates as a single entry map with this key so they
+    /// remain distinguishable from strings when buffered by serde. The value is an RFC 3339 string
+    /// including any fractional seconds.
+    pub const DATE_MAP_KEY: &str = "$__plist_private_date";
 
     impl Serialize for Date {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where
             S: Serializer,
         {
-            let date_str = self.to_rfc3339();
+            let date_str = self.to_rfc3339_exact();
             serializer.serialize_newtype_struct(DATE_NEWTYPE_STRUCT_NAME, &date_str)
         }
     }
@@ -125,13 +340,13 @@ pub mod serde_impls {
         where
             D: Deserializer<'de>,
         {
-            deserializer.deserialize_str(DateStrVisitor)
+            deserializer.deserialize_any(DateVisitor)
         }
     }
 
-    struct DateStrVisitor;
+    struct DateVisitor;
 
-    impl<'de> Visitor<'de> for DateStrVisitor {
+    impl<'de> Visitor<'de> for DateVisitor {
         type Value = Date;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -144,6 +359,19 @@ pub mod serde_impls {
         {
             Date::from_rfc3339(v).map_err(|()| E::invalid_value(Unexpected::Str(v), &self))
         }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            match map.next_key::<String>()? {
+                Some(ref key) if key == DATE_MAP_KEY => {
+                    let date_str: String = map.next_value()?;
+                    self.visit_str(&date_str)
+                }
+                _ => Err(A::Error::invalid_type(Unexpected::Map, &self)),
+            }
+        }
     }
 
     impl<'de> Deserialize<'de> for Date {
@@ -176,4 +404,34 @@ mod testing {
         let date_str = "1920-01-01T00:00:00Z";
         Date::from_rfc3339(date_str).expect("should parse");
     }
+
+    #[test]
+    fn rfc3339_offsets_and_fractions() {
+        let to_rfc3339_exact = |date: Date| {
+            let (secs, nanos) = date.unix_timestamp();
+            format_rfc3339(secs, nanos, true)
+        };
+
+        let date = Date::from_rfc3339("2000-02-29T23:30:00.25-01:00").unwrap();
+        assert_eq!(date.to_rfc3339(), "2000-03-01T00:30:00Z");
+        assert_eq!(to_rfc3339_exact(date), "2000-03-01T00:30:00.250Z");
+
+        let date = Date::from_rfc3339("1969-12-31t23:59:59.000001z").unwrap();
+        assert_eq!(to_rfc3339_exact(date), "1969-12-31T23:59:59.000001Z");
+        assert_eq!(date.to_seconds_since_plist_epoch().round(), -978_307_201.0);
+    }
+
+    #[test]
+    fn invalid_rfc3339() {
+        for date_str in &[
+            "2001-02-29T00:00:00Z",
+            "2000-01-01T24:00:00Z",
+            "2000-01-01T10:00:00",
+            "2000-01-01T10:00:00.Z",
+            "2000-01-01T10:00:00+0530",
+            "+2000-01-01T10:00:00Z",
+        ] {
+            assert!(Date::from_rfc3339(date_str).is_err(), "{}", date_str);
+        }
+    }
 }
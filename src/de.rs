@@ -1,17 +1,32 @@
-use serde::de;
+use serde::{
+    de::{
+        self,
+        value::{MapDeserializer, StringDeserializer},
+        IntoDeserializer,
+    },
+    forward_to_deserialize_any,
+};
 use std::{
+    borrow::Cow,
+    cell::Cell,
     fmt::Display,
     fs::File,
     io::{BufReader, Cursor, Read, Seek},
-    iter::Peekable,
+    iter::{self, Peekable},
+    marker::PhantomData,
     mem,
     path::Path,
+    rc::Rc,
 };
 
 use crate::{
-    error::{self, Error, ErrorKind, EventKind},
-    stream::{self, Event},
+    date::serde_impls::DATE_MAP_KEY,
+    error::{self, Error, ErrorKind, EventKind, FilePosition, PathSegment},
+    serde_options::{NoneRepresentation, SerdeOptions, UnitRepresentation},
+    stream::{self, Event, EventPosition, Limiter},
     u64_to_usize,
+    uid::serde_impls::UID_MAP_KEY,
+    Value,
 };
 
 macro_rules! expect {
@@ -27,6 +42,19 @@ macro_rules! expect {
     };
 }
 
+macro_rules! forward_to_deserialize_event {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                self.deserialize_event(visitor, false)
+            }
+        )*
+    };
+}
+
 macro_rules! try_next {
     ($next:expr) => {
         match $next {
@@ -51,25 +79,84 @@ enum OptionMode {
 }
 
 /// A structure that deserializes plist event streams into Rust values.
+///
+/// Errors are annotated with the path of dictionary keys and array indices leading to the value
+/// being deserialized. Event streams exceeding the limits set by `SerdeOptions::reader_options`
+/// are rejected.
 pub struct Deserializer<I>
 where
-    I: IntoIterator<Item = Result<Event, Error>>,
+    I: IntoIterator,
 {
-    events: Peekable<<I as IntoIterator>::IntoIter>,
+    events: Peekable<LimitedEvents<<I as IntoIterator>::IntoIter>>,
     option_mode: OptionMode,
+    /// Dictionary keys are stored as the string events they were read from, which avoids copying
+    /// them.
+    path: Vec<PathSegment<<I as IntoIterator>::Item>>,
+    /// Set while a dictionary key is deserialized so that its string event is kept in
+    /// `captured_key` rather than being moved into the visitor.
+    capture_key: bool,
+    captured_key: Option<<I as IntoIterator>::Item>,
+    event_position: Option<Rc<Cell<Option<FilePosition>>>>,
+    options: SerdeOptions,
 }
 
-impl<I> Deserializer<I>
+impl<'de, I> Deserializer<I>
 where
-    I: IntoIterator<Item = Result<Event, Error>>,
+    I: IntoIterator<Item = Result<Event<'de>, Error>>,
 {
+    /// Creates a `Deserializer` reading from the given event stream.
+    ///
+    /// Strings and data borrowed by the events for `'de` are passed to the visitor as borrowed
+    /// values, allowing them to be deserialized into `&'de str` and `&'de [u8]` without copying.
     pub fn new(iter: I) -> Deserializer<I> {
+        Deserializer::new_with_options(iter, SerdeOptions::new())
+    }
+
+    /// Creates a `Deserializer` reading from the given event stream using the given options.
+    pub fn new_with_options(iter: I, options: SerdeOptions) -> Deserializer<I> {
         Deserializer {
-            events: iter.into_iter().peekable(),
+            events: LimitedEvents::new(iter.into_iter(), &options).peekable(),
             option_mode: OptionMode::Root,
+            path: Vec::new(),
+            capture_key: false,
+            captured_key: None,
+            event_position: None,
+            options,
         }
     }
 
+    /// Attaches the current path, and the position of the most recently read event if known, to
+    /// an error.
+    fn error_with_context(&self, err: Error) -> Error {
+        let position = self.event_position.as_ref().and_then(|pos| pos.get());
+        err.with_context(
+            || {
+                let path: Vec<_> = self
+                    .path
+                    .iter()
+                    .map(|segment| match segment {
+                        PathSegment::Key(Ok(Event::String(key))) => PathSegment::Key(&**key),
+                        PathSegment::Key(_) => PathSegment::Key(""),
+                        PathSegment::Index(i) => PathSegment::Index(*i),
+                    })
+                    .collect();
+                error::format_path(&path)
+            },
+            position,
+        )
+    }
+
+    fn with_path_segment<T, F: FnOnce(&mut Deserializer<I>) -> Result<T, Error>>(
+        &mut self,
+        segment: PathSegment<Result<Event<'de>, Error>>,
+        f: F,
+    ) -> Result<T, Error> {
+        self.path.push(segment);
+        let ret = f(&mut *self).map_err(|err| self.error_with_context(err));
+        self.path.pop();
+        ret
+    }
+
     fn with_option_mode<T, F: FnOnce(&mut Deserializer<I>) -> Result<T, Error>>(
         &mut self,
         option_mode: OptionMode,
@@ -80,15 +167,13 @@ where
         self.option_mode = prev_option_mode;
         ret
     }
-}
 
-impl<'de, 'a, I> de::Deserializer<'de> for &'a mut Deserializer<I>
-where
-    I: IntoIterator<Item = Result<Event, Error>>,
-{
-    type Error = Error;
-
-    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    /// Passes the next event to `visitor`.
+    ///
+    /// If `preserve_types` is set dates and uids are passed as single entry maps keyed by
+    /// `DATE_MAP_KEY` and `UID_MAP_KEY` so they survive being buffered by serde, e.g. for untagged
+    /// enums and flattened structs. Otherwise they are passed as a string and a `u64`.
+    fn deserialize_event<V>(&mut self, visitor: V, preserve_types: bool) -> Result<V::Value, Error>
     where
         V: de::Visitor<'de>,
     {
@@ -111,7 +196,11 @@ where
             )),
 
             Event::Boolean(v) => visitor.visit_bool(v),
-            Event::Data(v) => visitor.visit_byte_buf(v),
+            Event::Data(Cow::Borrowed(v)) => visitor.visit_borrowed_bytes(v),
+            Event::Data(Cow::Owned(v)) => visitor.visit_byte_buf(v),
+            Event::Date(v) if preserve_types => visitor.visit_map(MapDeserializer::new(
+                iter::once((DATE_MAP_KEY, v.to_rfc3339_exact())),
+            )),
             Event::Date(v) => visitor.visit_string(v.to_rfc3339()),
             Event::Integer(v) => {
                 if let Some(v) = v.as_unsigned() {
@@ -123,31 +212,104 @@ where
                 }
             }
             Event::Real(v) => visitor.visit_f64(v),
-            Event::String(v) => visitor.visit_string(v),
+            Event::String(v) => self.visit_string(v, visitor),
+            Event::Uid(v) if preserve_types => {
+                visitor.visit_map(MapDeserializer::new(iter::once((UID_MAP_KEY, v.get()))))
+            }
             Event::Uid(v) => visitor.visit_u64(v.get()),
+        }
+    }
 
-            Event::__Nonexhaustive => unreachable!(),
+    fn visit_string<V>(&mut self, v: Cow<'de, str>, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        if !mem::replace(&mut self.capture_key, false) {
+            return match v {
+                Cow::Borrowed(v) => visitor.visit_borrowed_str(v),
+                Cow::Owned(v) => visitor.visit_string(v),
+            };
         }
+
+        let ret = match &v {
+            Cow::Borrowed(v) => visitor.visit_borrowed_str(v),
+            Cow::Owned(v) => visitor.visit_str(v),
+        };
+        self.captured_key = Some(Ok(Event::String(v)));
+        ret
+    }
+}
+
+impl<'de, I> de::Deserializer<'de> for &mut Deserializer<I>
+where
+    I: IntoIterator<Item = Result<Event<'de>, Error>>,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        self.deserialize_event(visitor, true)
+    }
+
+    forward_to_deserialize_event! {
+        deserialize_bool deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64
+        deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_seq
+        deserialize_bytes deserialize_byte_buf deserialize_map deserialize_ignored_any
+        deserialize_identifier
     }
 
     forward_to_deserialize_any! {
-        bool u8 u16 u32 u64 i8 i16 i32 i64 f32 f64 char str string
-        seq bytes byte_buf map unit_struct
-        tuple_struct tuple ignored_any identifier
+        tuple_struct tuple
     }
 
     fn deserialize_unit<V>(self, visitor: V) -> Result<V::Value, Error>
     where
         V: de::Visitor<'de>,
     {
-        expect!(self.events.next(), EventKind::String);
+        match self.options.unit {
+            UnitRepresentation::EmptyString => {
+                expect!(self.events.next(), EventKind::String);
+            }
+            UnitRepresentation::EmptyDictionary => {
+                expect!(self.events.next(), EventKind::StartDictionary);
+                expect!(self.events.next(), EventKind::EndCollection);
+            }
+            UnitRepresentation::Error => {
+                return Err(ErrorKind::UnitNotSupported.without_position());
+            }
+        }
         visitor.visit_unit()
     }
 
+    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: de::Visitor<'de>,
+    {
+        de::Deserializer::deserialize_unit(self, visitor)
+    }
+
     fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Error>
     where
         V: de::Visitor<'de>,
     {
+        if self.options.none != NoneRepresentation::Wrapped {
+            let is_none = match (self.events.peek(), &self.options.none) {
+                (None, _) => matches!(self.option_mode, OptionMode::Root),
+                (Some(Ok(Event::String(s))), NoneRepresentation::Sentinel(sentinel)) => {
+                    **s == **sentinel
+                }
+                _ => false,
+            };
+            if is_none {
+                self.events.next();
+                return visitor.visit_none::<Error>();
+            }
+            return self.with_option_mode(OptionMode::Explicit, |this| visitor.visit_some(this));
+        }
+
         match self.option_mode {
             OptionMode::Root => {
                 if self.events.peek().is_none() {
@@ -158,7 +320,7 @@ where
             }
             OptionMode::StructField => {
                 // None struct values are ignored so if we're here the value must be Some.
-                self.with_option_mode(OptionMode::Explicit, |this| Ok(visitor.visit_some(this)?))
+                self.with_option_mode(OptionMode::Explicit, |this| visitor.visit_some(this))
             }
             OptionMode::Explicit => {
                 expect!(self.events.next(), EventKind::StartDictionary);
@@ -214,6 +376,18 @@ where
     where
         V: de::Visitor<'de>,
     {
+        // Unit variants may be represented by their name alone.
+        if let Some(Ok(Event::String(_))) = self.events.peek() {
+            if let Event::String(variant) = try_next!(self.events.next()) {
+                if !mem::replace(&mut self.capture_key, false) {
+                    return visitor.visit_enum(StringDeserializer::new(variant.into_owned()));
+                }
+                let ret = visitor.visit_enum((&*variant).into_deserializer());
+                self.captured_key = Some(Ok(Event::String(variant)));
+                return ret;
+            }
+        }
+
         expect!(self.events.next(), EventKind::StartDictionary);
         let ret = visitor.visit_enum(&mut *self)?;
         expect!(self.events.next(), EventKind::EndCollection);
@@ -221,9 +395,9 @@ where
     }
 }
 
-impl<'de, 'a, I> de::EnumAccess<'de> for &'a mut Deserializer<I>
+impl<'de, I> de::EnumAccess<'de> for &mut Deserializer<I>
 where
-    I: IntoIterator<Item = Result<Event, Error>>,
+    I: IntoIterator<Item = Result<Event<'de>, Error>>,
 {
     type Error = Error;
     type Variant = Self;
@@ -236,9 +410,9 @@ where
     }
 }
 
-impl<'de, 'a, I> de::VariantAccess<'de> for &'a mut Deserializer<I>
+impl<'de, I> de::VariantAccess<'de> for &mut Deserializer<I>
 where
-    I: IntoIterator<Item = Result<Event, Error>>,
+    I: IntoIterator<Item = Result<Event<'de>, Error>>,
 {
     type Error = Error;
 
@@ -275,16 +449,18 @@ where
 
 struct MapAndSeqAccess<'a, I>
 where
-    I: 'a + IntoIterator<Item = Result<Event, Error>>,
+    I: 'a + IntoIterator,
 {
     de: &'a mut Deserializer<I>,
     is_struct: bool,
     remaining: Option<usize>,
+    index: usize,
+    pending_key: Option<<I as IntoIterator>::Item>,
 }
 
 impl<'a, I> MapAndSeqAccess<'a, I>
 where
-    I: 'a + IntoIterator<Item = Result<Event, Error>>,
+    I: 'a + IntoIterator,
 {
     fn new(
         de: &'a mut Deserializer<I>,
@@ -295,13 +471,15 @@ where
             de,
             is_struct,
             remaining: len,
+            index: 0,
+            pending_key: None,
         }
     }
 }
 
 impl<'de, 'a, I> de::SeqAccess<'de> for MapAndSeqAccess<'a, I>
 where
-    I: 'a + IntoIterator<Item = Result<Event, Error>>,
+    I: 'a + IntoIterator<Item = Result<Event<'de>, Error>>,
 {
     type Error = Error;
 
@@ -314,8 +492,12 @@ where
         }
 
         self.remaining = self.remaining.map(|r| r.saturating_sub(1));
+        let index = self.index;
+        self.index += 1;
         self.de
-            .with_option_mode(OptionMode::Explicit, |this| seed.deserialize(this))
+            .with_path_segment(PathSegment::Index(index), |this| {
+                this.with_option_mode(OptionMode::Explicit, |this| seed.deserialize(this))
+            })
             .map(Some)
     }
 
@@ -326,7 +508,7 @@ where
 
 impl<'de, 'a, I> de::MapAccess<'de> for MapAndSeqAccess<'a, I>
 where
-    I: 'a + IntoIterator<Item = Result<Event, Error>>,
+    I: 'a + IntoIterator<Item = Result<Event<'de>, Error>>,
 {
     type Error = Error;
 
@@ -334,14 +516,19 @@ where
     where
         K: de::DeserializeSeed<'de>,
     {
-        if let Some(&Ok(Event::EndCollection)) = self.de.events.peek() {
-            return Ok(None);
+        match self.de.events.peek() {
+            Some(Ok(Event::EndCollection)) => return Ok(None),
+            Some(Ok(Event::String(_))) => self.de.capture_key = true,
+            _ => {}
         }
 
         self.remaining = self.remaining.map(|r| r.saturating_sub(1));
-        self.de
-            .with_option_mode(OptionMode::Explicit, |this| seed.deserialize(this))
-            .map(Some)
+        let ret = self
+            .de
+            .with_option_mode(OptionMode::Explicit, |this| seed.deserialize(this));
+        self.de.capture_key = false;
+        self.pending_key = self.de.captured_key.take();
+        ret.map(Some)
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
@@ -353,8 +540,13 @@ where
         } else {
             OptionMode::Explicit
         };
-        self.de
-            .with_option_mode(option_mode, |this| Ok(seed.deserialize(this)?))
+        let key = self
+            .pending_key
+            .take()
+            .unwrap_or(Ok(Event::String(Cow::Borrowed(""))));
+        self.de.with_path_segment(PathSegment::Key(key), |this| {
+            this.with_option_mode(option_mode, |this| seed.deserialize(this))
+        })
     }
 
     fn size_hint(&self) -> Option<usize> {
@@ -362,6 +554,179 @@ where
     }
 }
 
+macro_rules! forward_to_event_deserializer {
+    ($events:expr; $($method:ident($($arg:ident: $ty:ty),*))*) => {
+        $(
+            fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Error>
+            where
+                V: de::Visitor<'de>,
+            {
+                let mut de = Deserializer::new($events(self).map(|event| Ok(event)));
+                de::Deserializer::$method(&mut de, $($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! value_deserializer_methods {
+    ($events:expr) => {
+        forward_to_event_deserializer! {
+            $events;
+            deserialize_any()
+            deserialize_bool()
+            deserialize_i8()
+            deserialize_i16()
+            deserialize_i32()
+            deserialize_i64()
+            deserialize_u8()
+            deserialize_u16()
+            deserialize_u32()
+            deserialize_u64()
+            deserialize_f32()
+            deserialize_f64()
+            deserialize_char()
+            deserialize_str()
+            deserialize_string()
+            deserialize_bytes()
+            deserialize_byte_buf()
+            deserialize_option()
+            deserialize_unit()
+            deserialize_unit_struct(name: &'static str)
+            deserialize_newtype_struct(name: &'static str)
+            deserialize_seq()
+            deserialize_tuple(len: usize)
+            deserialize_tuple_struct(name: &'static str, len: usize)
+            deserialize_map()
+            deserialize_struct(name: &'static str, fields: &'static [&'static str])
+            deserialize_enum(name: &'static str, variants: &'static [&'static str])
+            deserialize_identifier()
+            deserialize_ignored_any()
+        }
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = Error;
+
+    value_deserializer_methods!(Value::into_events);
+}
+
+/// Deserializing from a borrowed `Value` borrows its strings and data.
+impl<'de> de::Deserializer<'de> for &'de Value {
+    type Error = Error;
+
+    value_deserializer_methods!(Value::events);
+}
+
+/// Deserializes an instance of type `T` from a `Value`.
+///
+/// Dates, uids and data are passed to `T` in the same way as when deserializing from a plist file.
+pub fn from_value<T: de::DeserializeOwned>(value: Value) -> Result<T, Error> {
+    de::Deserialize::deserialize(value)
+}
+
+/// Checks each event against the limits in `SerdeOptions::reader_options`, ending the stream with
+/// an error when one is exceeded. Events from readers, which enforce the limits themselves, are not
+/// checked again.
+///
+/// The check is stored as a function as `Deserializer` does not constrain the type of its events.
+struct LimitedEvents<I: Iterator> {
+    iter: I,
+    limiter: Option<Limiter>,
+    check: fn(&mut Limiter, &I::Item) -> Option<I::Item>,
+    finished: bool,
+}
+
+impl<'de, I> LimitedEvents<I>
+where
+    I: Iterator<Item = Result<Event<'de>, Error>>,
+{
+    fn new(iter: I, options: &SerdeOptions) -> LimitedEvents<I> {
+        LimitedEvents {
+            iter,
+            limiter: Some(Limiter::new(options.reader_options.clone())),
+            check: |limiter, event| match event {
+                Ok(event) => match limiter.check_event(event) {
+                    Ok(()) => None,
+                    Err(kind) => Some(Err(kind.without_position())),
+                },
+                Err(_) => None,
+            },
+            finished: false,
+        }
+    }
+
+    fn unchecked(iter: I) -> LimitedEvents<I> {
+        LimitedEvents {
+            iter,
+            limiter: None,
+            check: |_, _| None,
+            finished: false,
+        }
+    }
+}
+
+impl<I: Iterator> Iterator for LimitedEvents<I> {
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<I::Item> {
+        if self.finished {
+            return None;
+        }
+        let event = self.iter.next()?;
+        if let Some(limiter) = &mut self.limiter {
+            if let Some(err) = (self.check)(limiter, &event) {
+                self.finished = true;
+                return Some(err);
+            }
+        }
+        Some(event)
+    }
+}
+
+/// Records the position of each event read from a reader so it can be attached to errors.
+pub(crate) struct PositionedEvents<R> {
+    reader: R,
+    position: Rc<Cell<Option<FilePosition>>>,
+}
+
+impl<R> Iterator for PositionedEvents<R>
+where
+    R: Iterator + EventPosition,
+{
+    type Item = R::Item;
+
+    fn next(&mut self) -> Option<R::Item> {
+        let event = self.reader.next();
+        self.position.set(self.reader.event_position());
+        event
+    }
+}
+
+impl<'de, R> Deserializer<PositionedEvents<R>>
+where
+    R: Iterator<Item = Result<Event<'de>, Error>> + EventPosition,
+{
+    /// Creates a `Deserializer` reading from a reader which already enforces
+    /// `options.reader_options`.
+    pub(crate) fn from_positioned_reader(reader: R, options: SerdeOptions) -> Self {
+        let position = Rc::new(Cell::new(None));
+        let events = PositionedEvents {
+            reader,
+            position: position.clone(),
+        };
+        Deserializer {
+            events: LimitedEvents::unchecked(events).peekable(),
+            option_mode: OptionMode::Root,
+            path: Vec::new(),
+            capture_key: false,
+            captured_key: None,
+            event_position: Some(position),
+            options,
+        }
+    }
+}
+
 /// Deserializes an instance of type `T` from a byte slice.
 pub fn from_bytes<T: de::DeserializeOwned>(bytes: &[u8]) -> Result<T, Error> {
     let cursor = Cursor::new(bytes);
@@ -377,13 +742,97 @@ pub fn from_file<P: AsRef<Path>, T: de::DeserializeOwned>(path: P) -> Result<T,
 /// Deserializes an instance of type `T` from a seekable byte stream containing a plist of any encoding.
 pub fn from_reader<R: Read + Seek, T: de::DeserializeOwned>(reader: R) -> Result<T, Error> {
     let reader = stream::Reader::new(reader);
-    let mut de = Deserializer::new(reader);
+    let mut de = Deserializer::from_positioned_reader(reader, SerdeOptions::new());
     de::Deserialize::deserialize(&mut de)
 }
 
 /// Deserializes an instance of type `T` from a byte stream containing an XML encoded plist.
 pub fn from_reader_xml<R: Read, T: de::DeserializeOwned>(reader: R) -> Result<T, Error> {
     let reader = stream::XmlReader::new(reader);
-    let mut de = Deserializer::new(reader);
+    let mut de = Deserializer::from_positioned_reader(reader, SerdeOptions::new());
     de::Deserialize::deserialize(&mut de)
 }
+
+/// An iterator that deserializes the elements of a plist whose root is an array one at a time.
+///
+/// Only the element currently being deserialized is held in memory, making it suitable for
+/// plists containing many records.
+///
+/// ```
+/// use std::io::Cursor;
+/// use plist::StreamDeserializer;
+///
+/// let plist = b"<plist><array><integer>1</integer><integer>2</integer></array></plist>";
+/// let elements: Result<Vec<u32>, _> = StreamDeserializer::new(Cursor::new(&plist[..])).collect();
+/// assert_eq!(elements.unwrap(), vec![1, 2]);
+/// ```
+///
+/// Iteration stops after the first error.
+pub struct StreamDeserializer<R: Read + Seek, T> {
+    de: Deserializer<PositionedEvents<stream::Reader<R>>>,
+    started: bool,
+    finished: bool,
+    index: usize,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<R: Read + Seek, T: de::DeserializeOwned> StreamDeserializer<R, T> {
+    /// Creates a `StreamDeserializer` reading a plist of any encoding from a seekable byte stream.
+    pub fn new(reader: R) -> StreamDeserializer<R, T> {
+        StreamDeserializer::new_with_options(reader, SerdeOptions::new())
+    }
+
+    /// Creates a `StreamDeserializer` which deserializes elements using the given options.
+    pub fn new_with_options(reader: R, options: SerdeOptions) -> StreamDeserializer<R, T> {
+        let reader = stream::Reader::new_with_options(reader, options.reader_options.clone());
+        StreamDeserializer {
+            de: Deserializer::from_positioned_reader(reader, options),
+            started: false,
+            finished: false,
+            index: 0,
+            marker: PhantomData,
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<T>, Error> {
+        if !self.started {
+            self.started = true;
+            expect!(self.de.events.next(), EventKind::StartArray);
+        }
+
+        if let Some(Ok(Event::EndCollection)) = self.de.events.peek() {
+            self.de.events.next();
+            return Ok(None);
+        }
+
+        let index = self.index;
+        self.index += 1;
+        self.de
+            .with_path_segment(PathSegment::Index(index), |this| {
+                this.with_option_mode(OptionMode::Explicit, |this| T::deserialize(this))
+            })
+            .map(Some)
+    }
+}
+
+impl<R: Read + Seek, T: de::DeserializeOwned> Iterator for StreamDeserializer<R, T> {
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Result<T, Error>> {
+        if self.finished {
+            return None;
+        }
+
+        match self.next_element() {
+            Ok(Some(element)) => Some(Ok(element)),
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                self.finished = true;
+                Some(Err(self.de.error_with_context(err)))
+            }
+        }
+    }
+}
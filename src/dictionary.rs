@@ -4,9 +4,11 @@
 //!
 //! [`IndexMap`]: https://docs.rs/indexmap/latest/indexmap/map/struct.IndexMap.html
 
-//use serde::{de, ser};
 use indexmap::{map, IndexMap};
+#[cfg(feature = "serde")]
+use serde::{de, ser};
 use std::{
+    cmp::Ordering,
     fmt::{self, Debug},
     iter::FromIterator,
     ops,
@@ -67,6 +69,9 @@ impl Dictionary {
 
     /// Removes a key from the dictionary, returning the value at the key if the key was previously
     /// in the dictionary.
+    ///
+    /// The last entry is moved into the position of the removed entry. Use `shift_remove` to
+    /// preserve the order of the remaining entries.
     #[inline]
     pub fn remove(&mut self, key: &str) -> Option<Value> {
         self.map.remove(key)
@@ -79,7 +84,7 @@ impl Dictionary {
         test,
         feature = "enable_unstable_features_that_may_break_with_minor_version_bumps"
     ))]
-    pub fn entry<S>(&mut self, key: S) -> Entry
+    pub fn entry<S>(&mut self, key: S) -> Entry<'_>
     where
         S: Into<String>,
     {
@@ -103,7 +108,7 @@ impl Dictionary {
 
     /// Gets an iterator over the entries of the dictionary.
     #[inline]
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter {
             iter: self.map.iter(),
         }
@@ -111,7 +116,7 @@ impl Dictionary {
 
     /// Gets a mutable iterator over the entries of the dictionary.
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut {
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
         IterMut {
             iter: self.map.iter_mut(),
         }
@@ -119,7 +124,7 @@ impl Dictionary {
 
     /// Gets an iterator over the keys of the dictionary.
     #[inline]
-    pub fn keys(&self) -> Keys {
+    pub fn keys(&self) -> Keys<'_> {
         Keys {
             iter: self.map.keys(),
         }
@@ -127,7 +132,7 @@ impl Dictionary {
 
     /// Gets an iterator over the values of the dictionary.
     #[inline]
-    pub fn values(&self) -> Values {
+    pub fn values(&self) -> Values<'_> {
         Values {
             iter: self.map.values(),
         }
@@ -135,11 +140,115 @@ impl Dictionary {
 
     /// Gets an iterator over mutable values of the dictionary.
     #[inline]
-    pub fn values_mut(&mut self) -> ValuesMut {
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
         ValuesMut {
             iter: self.map.values_mut(),
         }
     }
+
+    /// Inserts a key-value pair into the dictionary at the given position.
+    ///
+    /// If the dictionary did not have this key present, the entries at and after `index` are
+    /// shifted up and `None` is returned.
+    ///
+    /// If the dictionary did have this key present, the entry is moved to `index`, the value is
+    /// updated, and the old value is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds. When inserting a new key `index` may be equal to
+    /// `len()`.
+    pub fn insert_at(&mut self, index: usize, k: String, v: Value) -> Option<Value> {
+        let len = if self.map.contains_key(&k) {
+            self.map.len()
+        } else {
+            self.map.len() + 1
+        };
+        assert!(
+            index < len,
+            "index out of bounds: the len is {} but the index is {}",
+            len,
+            index
+        );
+
+        let (current_index, old) = self.map.insert_full(k, v);
+        self.map.move_index(current_index, index);
+        old
+    }
+
+    /// Returns the key-value pair at the given position.
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<(&String, &Value)> {
+        self.map.get_index(index)
+    }
+
+    /// Returns the key and a mutable reference to the value at the given position.
+    #[inline]
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&String, &mut Value)> {
+        self.map.get_index_mut(index).map(|(k, v)| (&*k, v))
+    }
+
+    /// Returns the position of the given key.
+    #[inline]
+    pub fn get_index_of(&self, key: &str) -> Option<usize> {
+        self.map.get_index_of(key)
+    }
+
+    /// Removes a key from the dictionary by swapping it with the last entry, returning the value
+    /// at the key if the key was previously in the dictionary.
+    ///
+    /// This is the same as `remove` and does not preserve the order of the remaining entries.
+    #[inline]
+    pub fn swap_remove(&mut self, key: &str) -> Option<Value> {
+        self.map.swap_remove(key)
+    }
+
+    /// Removes a key from the dictionary by shifting all following entries down, returning the
+    /// value at the key if the key was previously in the dictionary.
+    ///
+    /// This preserves the order of the remaining entries.
+    #[inline]
+    pub fn shift_remove(&mut self, key: &str) -> Option<Value> {
+        self.map.shift_remove(key)
+    }
+
+    /// Removes the entry at the given position by swapping it with the last entry.
+    #[inline]
+    pub fn swap_remove_index(&mut self, index: usize) -> Option<(String, Value)> {
+        self.map.swap_remove_index(index)
+    }
+
+    /// Removes the entry at the given position by shifting all following entries down.
+    #[inline]
+    pub fn shift_remove_index(&mut self, index: usize) -> Option<(String, Value)> {
+        self.map.shift_remove_index(index)
+    }
+
+    /// Retains only the entries for which the predicate returns `true`, preserving their order.
+    #[inline]
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&String, &mut Value) -> bool,
+    {
+        self.map.retain(keep)
+    }
+
+    /// Sorts the dictionary's entries by key.
+    #[inline]
+    pub fn sort_keys(&mut self) {
+        self.map.sort_keys()
+    }
+
+    /// Sorts the dictionary's entries using the given comparison function.
+    ///
+    /// The sort is stable.
+    #[inline]
+    pub fn sort_by<F>(&mut self, cmp: F)
+    where
+        F: FnMut(&String, &Value, &String, &Value) -> Ordering,
+    {
+        self.map.sort_by(cmp)
+    }
 }
 
 impl Default for Dictionary {
@@ -182,7 +291,7 @@ impl PartialEq for Dictionary {
 /// }
 /// # ;
 /// ```
-impl<'a> ops::Index<&'a str> for Dictionary {
+impl ops::Index<&str> for Dictionary {
     type Output = Value;
 
     fn index(&self, index: &str) -> &Value {
@@ -199,7 +308,7 @@ impl<'a> ops::Index<&'a str> for Dictionary {
 /// #
 /// dict["key"] = "value".into();
 /// ```
-impl<'a> ops::IndexMut<&'a str> for Dictionary {
+impl ops::IndexMut<&str> for Dictionary {
     fn index_mut(&mut self, index: &str) -> &mut Value {
         self.map.get_mut(index).expect("no entry found for key")
     }
@@ -212,22 +321,23 @@ impl Debug for Dictionary {
     }
 }
 
-/*impl ser::Serialize for Dictionary {
+#[cfg(feature = "serde")]
+impl ser::Serialize for Dictionary {
     #[inline]
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: ser::Serializer,
     {
         use serde::ser::SerializeMap;
-        let mut map = try!(serializer.serialize_map(Some(self.len())));
+        let mut map = serializer.serialize_map(Some(self.len()))?;
         for (k, v) in self {
-            try!(map.serialize_key(k));
-            try!(map.serialize_value(v));
+            map.serialize_entry(k, v)?;
         }
         map.end()
     }
 }
 
+#[cfg(feature = "serde")]
 impl<'de> de::Deserialize<'de> for Dictionary {
     #[inline]
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -258,7 +368,7 @@ impl<'de> de::Deserialize<'de> for Dictionary {
             {
                 let mut values = Dictionary::new();
 
-                while let Some((key, value)) = try!(visitor.next_entry()) {
+                while let Some((key, value)) = visitor.next_entry()? {
                     values.insert(key, value);
                 }
 
@@ -268,7 +378,7 @@ impl<'de> de::Deserialize<'de> for Dictionary {
 
         deserializer.deserialize_map(Visitor)
     }
-}*/
+}
 
 impl FromIterator<(String, Value)> for Dictionary {
     fn from_iter<T>(iter: T) -> Self
@@ -696,3 +806,66 @@ pub struct ValuesMut<'a> {
 }
 
 delegate_iterator!((ValuesMut<'a>) => &'a mut Value);
+
+#[cfg(test)]
+mod tests {
+    use super::Dictionary;
+    use crate::Value;
+
+    fn keys(dict: &Dictionary) -> Vec<&str> {
+        dict.keys().map(String::as_str).collect()
+    }
+
+    fn dict(keys: &[&str]) -> Dictionary {
+        keys.iter()
+            .enumerate()
+            .map(|(i, k)| (k.to_string(), Value::from(i as u64)))
+            .collect()
+    }
+
+    #[test]
+    fn insert_at() {
+        let mut d = dict(&["a", "b", "c"]);
+
+        assert_eq!(d.insert_at(0, "z".to_owned(), 9.into()), None);
+        assert_eq!(keys(&d), ["z", "a", "b", "c"]);
+
+        assert_eq!(d.insert_at(4, "y".to_owned(), 8.into()), None);
+        assert_eq!(keys(&d), ["z", "a", "b", "c", "y"]);
+
+        assert_eq!(d.insert_at(1, "c".to_owned(), 7.into()), Some(2.into()));
+        assert_eq!(keys(&d), ["z", "c", "a", "b", "y"]);
+        assert_eq!(d.get_index(1), Some((&"c".to_owned(), &7.into())));
+    }
+
+    #[test]
+    #[should_panic]
+    fn insert_at_existing_key_out_of_bounds() {
+        let mut d = dict(&["a", "b"]);
+        d.insert_at(2, "a".to_owned(), 0.into());
+    }
+
+    #[test]
+    fn removal_order() {
+        let mut d = dict(&["a", "b", "c", "d"]);
+        assert_eq!(d.shift_remove("a"), Some(0.into()));
+        assert_eq!(keys(&d), ["b", "c", "d"]);
+        assert_eq!(d.swap_remove("b"), Some(1.into()));
+        assert_eq!(keys(&d), ["d", "c"]);
+        assert_eq!(d.shift_remove_index(0), Some(("d".to_owned(), 3.into())));
+        assert_eq!(keys(&d), ["c"]);
+    }
+
+    #[test]
+    fn retain_and_sort() {
+        let mut d = dict(&["d", "a", "c", "b"]);
+        d.retain(|k, _| k != "c");
+        assert_eq!(keys(&d), ["d", "a", "b"]);
+
+        d.sort_keys();
+        assert_eq!(keys(&d), ["a", "b", "d"]);
+
+        d.sort_by(|_, v1, _, v2| v2.as_unsigned_integer().cmp(&v1.as_unsigned_integer()));
+        assert_eq!(keys(&d), ["b", "a", "d"]);
+    }
+}
@@ -1,4 +1,8 @@
-use std::{error, fmt, io};
+use std::{
+    error,
+    fmt::{self, Write},
+    io,
+};
 
 use crate::stream::Event;
 
@@ -12,60 +16,127 @@ pub struct Error {
 pub(crate) struct ErrorImpl {
     kind: ErrorKind,
     file_position: Option<FilePosition>,
+    /// The path of dictionary keys and array indices leading to the value which caused the error.
+    path: Option<String>,
 }
 
+/// The specific cause of an `Error`.
+///
+/// New variants may be added in minor releases.
 #[derive(Debug)]
-pub(crate) enum ErrorKind {
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The input ended before a complete plist was read.
     UnexpectedEof,
+    /// The event stream ended before a complete value was read.
     UnexpectedEndOfEventStream,
+    /// An event of an unexpected type was encountered.
     UnexpectedEventType {
         expected: EventKind,
         found: EventKind,
     },
 
     // Xml format-specific errors
+    /// An XML element was not closed before the end of the document.
     UnclosedXmlElement,
+    /// An XML closing tag did not match the currently open element.
     UnpairedXmlClosingTag,
+    /// Character data was found where an XML element was expected.
     UnexpectedXmlCharactersExpectedElement,
+    /// An XML opening tag was found where it is not allowed.
     UnexpectedXmlOpeningTag,
+    /// An XML element which is not part of the plist format was found.
     UnknownXmlElement,
+    /// The XML document is malformed.
     InvalidXmlSyntax,
+    /// The XML document is not valid UTF-8.
     InvalidXmlUtf8,
+    /// A `<data>` element does not contain valid base 64.
     InvalidDataString,
+    /// A `<date>` element does not contain a valid RFC 3339 date.
     InvalidDateString,
+    /// An `<integer>` element does not contain a valid integer.
     InvalidIntegerString,
+    /// A `<real>` element does not contain a valid real number.
     InvalidRealString,
+    /// A uid cannot be written to an XML plist.
     UidNotSupportedInXmlPlist,
 
     // Binary format-specific errors
+    /// An object is larger than can be represented on this platform.
     ObjectTooLarge,
+    /// The input does not start with the binary plist magic number.
     InvalidMagic,
-    InvalidTrailerObjectOffsetSize, // the size of byte offsets to objects in the object table
-    InvalidTrailerObjectReferenceSize, // the size of indices into the object table
+    /// The trailer specifies an invalid size for byte offsets to objects in the object table.
+    InvalidTrailerObjectOffsetSize,
+    /// The trailer specifies an invalid size for indices into the object table.
+    InvalidTrailerObjectReferenceSize,
+    /// An object's length is invalid.
     InvalidObjectLength,
+    /// An object reference is outside the object table.
     ObjectReferenceTooLarge,
+    /// An object offset is outside the input.
     ObjectOffsetTooLarge,
+    /// An object contains a reference to itself or one of its ancestors.
     RecursiveObject,
+    /// A `GraphEvent::Reference` refers to an object which has not been written.
+    UnresolvedObjectReference(u64),
+    /// The binary plist contains a null object, which is not supported.
     NullObjectUnimplemented,
+    /// The binary plist contains a fill object, which is not supported.
     FillObjectUnimplemented,
+    /// An integer is outside the range which can be represented.
     IntegerOutOfRange,
+    /// A date is infinite or `NaN`.
     InfiniteOrNanDate,
+    /// A string is not valid UTF-8.
     InvalidUtf8String,
+    /// A string is not valid UTF-16.
     InvalidUtf16String,
+    /// An object has an unknown type marker.
     UnknownObjectType(u8),
 
+    // Limit errors
+    /// Arrays and dictionaries are nested more deeply than `ReaderOptions::max_depth` allows.
+    DepthLimitExceeded,
+    /// The plist contains more objects than `ReaderOptions::max_objects` allows.
+    ObjectLimitExceeded,
+    /// A string is longer than `ReaderOptions::max_string_len` allows.
+    StringLengthLimitExceeded,
+    /// A data object is longer than `ReaderOptions::max_data_len` allows.
+    DataLengthLimitExceeded,
+    /// The strings and data in the plist are longer in total than `ReaderOptions::max_total_bytes`
+    /// allows.
+    TotalBytesLimitExceeded,
+
+    // Serde errors
+    /// A `None` cannot be represented using the configured `NoneRepresentation`.
+    NoneNotSupported,
+    /// A unit cannot be represented using the configured `UnitRepresentation`.
+    UnitNotSupported,
+
+    // Json conversion errors
+    /// A real is infinite or `NaN`, which JSON cannot represent.
+    InfiniteOrNanReal,
+    /// A JSON `null` was found, which plists cannot represent.
+    NullNotSupportedInPlist,
+
+    /// An error occurred while reading or writing bytes on an IO stream.
     Io(io::Error),
+    /// A custom error reported by a `Serialize` or `Deserialize` implementation.
     Serde(String),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub(crate) enum FilePosition {
     LineColumn(u64, u64),
     Offset(u64),
 }
 
+/// The type of a plist event, used to describe unexpected events in `ErrorKind`.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
-pub(crate) enum EventKind {
+#[non_exhaustive]
+pub enum EventKind {
     StartArray,
     StartDictionary,
     EndCollection,
@@ -82,6 +153,33 @@ pub(crate) enum EventKind {
 }
 
 impl Error {
+    /// Returns the specific cause of this error.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.inner.kind
+    }
+
+    /// Returns the byte offset into the input at which a binary plist error occurred.
+    pub fn byte_offset(&self) -> Option<u64> {
+        match self.inner.file_position {
+            Some(FilePosition::Offset(offset)) => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the 1-based line and column in the input at which an XML plist error occurred.
+    pub fn line_column(&self) -> Option<(u64, u64)> {
+        match self.inner.file_position {
+            Some(FilePosition::LineColumn(line, column)) => Some((line, column)),
+            _ => None,
+        }
+    }
+
+    /// Returns the path of dictionary keys and array indices leading to the value which caused the
+    /// error, formatted like `key.array[2].key`.
+    pub fn path(&self) -> Option<&str> {
+        self.inner.path.as_deref()
+    }
+
     /// Returns true if this error was caused by a failure to read or write bytes on an IO stream.
     pub fn is_io(&self) -> bool {
         self.as_io().is_some()
@@ -89,11 +187,7 @@ impl Error {
 
     /// Returns true if this error was caused by prematurely reaching the end of the input data.
     pub fn is_eof(&self) -> bool {
-        if let ErrorKind::UnexpectedEof = self.inner.kind {
-            true
-        } else {
-            false
-        }
+        matches!(self.inner.kind, ErrorKind::UnexpectedEof)
     }
 
     /// Returns the underlying error if it was caused by a failure to read or write bytes on an IO
@@ -117,6 +211,60 @@ impl Error {
     }
 }
 
+impl Error {
+    #[cfg_attr(not(feature = "serde_json"), allow(dead_code))]
+    pub(crate) fn with_path(mut self, path: String) -> Error {
+        self.inner.path = Some(path);
+        self
+    }
+
+    /// Attaches the path and position of the value being processed when the error occurred. Errors
+    /// which already have a path are returned unchanged so the innermost context is kept.
+    #[cfg_attr(not(feature = "serde"), allow(dead_code))]
+    pub(crate) fn with_context<F>(mut self, path: F, position: Option<FilePosition>) -> Error
+    where
+        F: FnOnce() -> String,
+    {
+        if self.inner.path.is_none() {
+            self.inner.path = Some(path());
+            if self.inner.file_position.is_none() {
+                self.inner.file_position = position;
+            }
+        }
+        self
+    }
+}
+
+/// A single dictionary key or array index in the path to a value.
+#[cfg_attr(not(any(feature = "serde", feature = "serde_json")), allow(dead_code))]
+pub(crate) enum PathSegment<S> {
+    Key(S),
+    Index(usize),
+}
+
+/// Formats a path in the style `key.array[2].key`. The empty path is formatted as `.`.
+#[cfg_attr(not(any(feature = "serde", feature = "serde_json")), allow(dead_code))]
+pub(crate) fn format_path<S: AsRef<str>>(path: &[PathSegment<S>]) -> String {
+    let mut formatted = String::new();
+    for segment in path {
+        match segment {
+            PathSegment::Key(key) => {
+                if !formatted.is_empty() {
+                    formatted.push('.');
+                }
+                formatted.push_str(key.as_ref());
+            }
+            PathSegment::Index(i) => {
+                let _ = write!(formatted, "[{}]", i);
+            }
+        }
+    }
+    if formatted.is_empty() {
+        formatted.push('.');
+    }
+    formatted
+}
+
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match &self.inner.kind {
@@ -126,38 +274,69 @@ impl error::Error for Error {
     }
 }
 
+/// Wraps an IO error, e.g. from the byte stream underlying a `stream::Writer` implementation.
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        from_io_without_position(err)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        fmt::Debug::fmt(&self.inner.kind, f)
+        fmt::Debug::fmt(&self.inner.kind, f)?;
+        if let Some(path) = &self.inner.path {
+            write!(f, " at `{}`", path)?;
+        }
+        match self.inner.file_position {
+            Some(FilePosition::LineColumn(line, column)) => {
+                write!(f, " (line {}, column {})", line, column)
+            }
+            Some(FilePosition::Offset(offset)) => write!(f, " (offset {})", offset),
+            None => Ok(()),
+        }
     }
 }
 
 impl ErrorKind {
-    pub fn with_byte_offset(self, offset: u64) -> Error {
+    pub(crate) fn with_byte_offset(self, offset: u64) -> Error {
         self.with_position(FilePosition::Offset(offset))
     }
 
-    pub fn with_position(self, pos: FilePosition) -> Error {
+    pub(crate) fn with_position(self, pos: FilePosition) -> Error {
         Error {
             inner: Box::new(ErrorImpl {
                 kind: self,
                 file_position: Some(pos),
+                path: None,
             }),
         }
     }
 
-    pub fn without_position(self) -> Error {
+    /// Returns true if this error was caused by exceeding a limit set in `ReaderOptions`.
+    pub(crate) fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::DepthLimitExceeded
+                | ErrorKind::ObjectLimitExceeded
+                | ErrorKind::StringLengthLimitExceeded
+                | ErrorKind::DataLengthLimitExceeded
+                | ErrorKind::TotalBytesLimitExceeded
+        )
+    }
+
+    pub(crate) fn without_position(self) -> Error {
         Error {
             inner: Box::new(ErrorImpl {
                 kind: self,
                 file_position: None,
+                path: None,
             }),
         }
     }
 }
 
 impl EventKind {
-    pub fn of_event(event: &Event) -> EventKind {
+    pub(crate) fn of_event(event: &Event<'_>) -> EventKind {
         match event {
             Event::StartArray(_) => EventKind::StartArray,
             Event::StartDictionary(_) => EventKind::StartDictionary,
@@ -169,7 +348,6 @@ impl EventKind {
             Event::Real(_) => EventKind::Real,
             Event::String(_) => EventKind::String,
             Event::Uid(_) => EventKind::Uid,
-            Event::__Nonexhaustive => unreachable!(),
         }
     }
 }
@@ -198,7 +376,7 @@ pub(crate) fn from_io_without_position(err: io::Error) -> Error {
     ErrorKind::Io(err).without_position()
 }
 
-pub(crate) fn unexpected_event_type(expected: EventKind, found: &Event) -> Error {
-    let found = EventKind::of_event(&found);
+pub(crate) fn unexpected_event_type(expected: EventKind, found: &Event<'_>) -> Error {
+    let found = EventKind::of_event(found);
     ErrorKind::UnexpectedEventType { expected, found }.without_position()
 }
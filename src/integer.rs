@@ -9,8 +9,7 @@ pub struct Integer {
 impl Integer {
     /// Returns the value as an `i64` if it can be represented by that type.
     pub fn as_signed(self) -> Option<i64> {
-        if self.value >= i128::from(i64::min_value()) && self.value <= i128::from(i64::max_value())
-        {
+        if self.value >= i128::from(i64::MIN) && self.value <= i128::from(i64::MAX) {
             Some(self.value as i64)
         } else {
             None
@@ -19,7 +18,7 @@ impl Integer {
 
     /// Returns the value as a `u64` if it can be represented by that type.
     pub fn as_unsigned(self) -> Option<u64> {
-        if self.value >= 0 && self.value <= i128::from(u64::max_value()) {
+        if self.value >= 0 && self.value <= i128::from(u64::MAX) {
             Some(self.value as u64)
         } else {
             None
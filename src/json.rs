@@ -0,0 +1,434 @@
+//! Conversions between plist `Value`s and `serde_json::Value`s.
+//!
+//! The `TryFrom` implementations use the default `JsonOptions`. Use `JsonOptions` directly to
+//! configure how plist-specific types are represented in JSON.
+//!
+//! ```
+//! use std::convert::TryFrom;
+//! use plist::{json::{DataMapping, JsonOptions}, Value};
+//!
+//! let value = Value::Data(vec![1, 2, 3]);
+//!
+//! let json = serde_json::Value::try_from(&value).unwrap();
+//! assert_eq!(json, serde_json::json!("AQID"));
+//!
+//! let options = JsonOptions::new().data(DataMapping::Tagged);
+//! let json = options.to_json(&value).unwrap();
+//! assert_eq!(json, serde_json::json!({ "$data": "AQID" }));
+//! assert_eq!(options.from_json(json).unwrap(), value);
+//! ```
+
+use serde_json::{Map, Number, Value as JsonValue};
+use std::convert::TryFrom;
+
+use crate::{
+    error::{format_path, Error, ErrorKind, PathSegment},
+    Date, Dictionary, Integer, Uid, Value,
+};
+
+/// The key of the single entry object used by `DateMapping::Tagged`.
+pub const DATE_TAG: &str = "$date";
+/// The key of the single entry object used by `DataMapping::Tagged`.
+pub const DATA_TAG: &str = "$data";
+/// The key of the single entry object used by `UidMapping::Tagged`. This matches the key used by
+/// Apple's XML representation of keyed archives.
+pub const UID_TAG: &str = "CF$UID";
+
+/// The largest integer magnitude which can be exactly represented by a JSON number in most
+/// JavaScript implementations.
+const MAX_SAFE_INTEGER: i64 = (1 << 53) - 1;
+
+/// How plist dates are represented in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DateMapping {
+    /// An RFC 3339 string e.g. `"1981-05-16T11:32:06Z"`. This is the default.
+    ///
+    /// Strings are not converted back to dates when converting from JSON.
+    Iso8601String,
+    /// An object with a single `"$date"` key holding an RFC 3339 string.
+    Tagged,
+}
+
+/// How plist data is represented in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataMapping {
+    /// A standard base 64 string. This is the default.
+    ///
+    /// Strings are not converted back to data when converting from JSON.
+    Base64String,
+    /// An array of byte values.
+    ///
+    /// Arrays are not converted back to data when converting from JSON.
+    ByteArray,
+    /// An object with a single `"$data"` key holding a standard base 64 string.
+    Tagged,
+}
+
+/// How plist uids are represented in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UidMapping {
+    /// An unsigned integer. This is the default.
+    ///
+    /// Integers are not converted back to uids when converting from JSON.
+    Integer,
+    /// An object with a single `"CF$UID"` key holding an unsigned integer.
+    Tagged,
+}
+
+/// How plist integers which cannot be exactly represented by an IEEE 754 double are represented
+/// in JSON.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BigIntegerMapping {
+    /// A JSON number. `serde_json` represents all 64 bit integers exactly but many other JSON
+    /// implementations do not. This is the default.
+    Number,
+    /// A decimal string.
+    ///
+    /// Strings are not converted back to integers when converting from JSON.
+    String,
+    /// Conversion fails.
+    Error,
+}
+
+/// Options controlling conversion between plist `Value`s and `serde_json::Value`s.
+#[derive(Clone, Debug)]
+pub struct JsonOptions {
+    date: DateMapping,
+    data: DataMapping,
+    uid: UidMapping,
+    big_integer: BigIntegerMapping,
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions::new()
+    }
+}
+
+impl JsonOptions {
+    /// Returns the default options.
+    pub fn new() -> JsonOptions {
+        JsonOptions {
+            date: DateMapping::Iso8601String,
+            data: DataMapping::Base64String,
+            uid: UidMapping::Integer,
+            big_integer: BigIntegerMapping::Number,
+        }
+    }
+
+    /// Sets how dates are represented.
+    pub fn date(mut self, mapping: DateMapping) -> Self {
+        self.date = mapping;
+        self
+    }
+
+    /// Sets how data is represented.
+    pub fn data(mut self, mapping: DataMapping) -> Self {
+        self.data = mapping;
+        self
+    }
+
+    /// Sets how uids are represented.
+    pub fn uid(mut self, mapping: UidMapping) -> Self {
+        self.uid = mapping;
+        self
+    }
+
+    /// Sets how integers outside the range `-(2^53 - 1)..=2^53 - 1` are represented.
+    pub fn big_integer(mut self, mapping: BigIntegerMapping) -> Self {
+        self.big_integer = mapping;
+        self
+    }
+
+    /// Converts a plist `Value` to JSON.
+    ///
+    /// Fails if the value contains an infinite or `NaN` real, or a big integer when using
+    /// `BigIntegerMapping::Error`. The error includes the path to the offending value.
+    pub fn to_json(&self, value: &Value) -> Result<JsonValue, Error> {
+        let mut path = Vec::new();
+        self.value_to_json(value, &mut path)
+            .map_err(|kind| kind.without_position().with_path(format_path(&path)))
+    }
+
+    /// Converts a JSON value to a plist `Value`.
+    ///
+    /// Fails if the value contains `null` or a malformed tagged object. The error includes the
+    /// path to the offending value.
+    pub fn from_json(&self, value: JsonValue) -> Result<Value, Error> {
+        let mut path = Vec::new();
+        self.json_to_value(&value, &mut path)
+            .map_err(|kind| kind.without_position().with_path(format_path(&path)))
+    }
+
+    fn value_to_json<'a>(
+        &self,
+        value: &'a Value,
+        path: &mut Vec<PathSegment<&'a str>>,
+    ) -> Result<JsonValue, ErrorKind> {
+        Ok(match value {
+            Value::Array(array) => {
+                let mut json = Vec::with_capacity(array.len());
+                for (i, value) in array.iter().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    json.push(self.value_to_json(value, path)?);
+                    path.pop();
+                }
+                JsonValue::Array(json)
+            }
+            Value::Dictionary(dict) => {
+                let mut json = Map::new();
+                for (key, value) in dict {
+                    path.push(PathSegment::Key(key));
+                    json.insert(key.clone(), self.value_to_json(value, path)?);
+                    path.pop();
+                }
+                JsonValue::Object(json)
+            }
+            Value::Boolean(v) => JsonValue::Bool(*v),
+            Value::Data(v) => match self.data {
+                DataMapping::Base64String => JsonValue::String(base64::encode(v)),
+                DataMapping::ByteArray => JsonValue::Array(v.iter().map(|&b| b.into()).collect()),
+                DataMapping::Tagged => tagged(DATA_TAG, base64::encode(v).into()),
+            },
+            Value::Date(v) => match self.date {
+                DateMapping::Iso8601String => JsonValue::String(v.to_rfc3339()),
+                DateMapping::Tagged => tagged(DATE_TAG, v.to_rfc3339().into()),
+            },
+            Value::Real(v) => {
+                JsonValue::Number(Number::from_f64(*v).ok_or(ErrorKind::InfiniteOrNanReal)?)
+            }
+            Value::Integer(v) => self.integer_to_json(*v)?,
+            Value::String(v) => JsonValue::String(v.clone()),
+            Value::Uid(v) => match self.uid {
+                UidMapping::Integer => v.get().into(),
+                UidMapping::Tagged => tagged(UID_TAG, v.get().into()),
+            },
+        })
+    }
+
+    fn integer_to_json(&self, value: Integer) -> Result<JsonValue, ErrorKind> {
+        let is_big = match value.as_signed() {
+            Some(v) => v.unsigned_abs() > MAX_SAFE_INTEGER as u64,
+            None => true,
+        };
+        match self.big_integer {
+            BigIntegerMapping::String if is_big => return Ok(value.to_string().into()),
+            BigIntegerMapping::Error if is_big => return Err(ErrorKind::IntegerOutOfRange),
+            _ => (),
+        }
+        if let Some(v) = value.as_signed() {
+            Ok(v.into())
+        } else if let Some(v) = value.as_unsigned() {
+            Ok(v.into())
+        } else {
+            unreachable!("an integer can be represented as either an i64 or u64")
+        }
+    }
+
+    fn json_to_value<'a>(
+        &self,
+        value: &'a JsonValue,
+        path: &mut Vec<PathSegment<&'a str>>,
+    ) -> Result<Value, ErrorKind> {
+        Ok(match value {
+            JsonValue::Null => return Err(ErrorKind::NullNotSupportedInPlist),
+            JsonValue::Bool(v) => Value::Boolean(*v),
+            JsonValue::Number(v) => {
+                if let Some(v) = v.as_i64() {
+                    Value::Integer(v.into())
+                } else if let Some(v) = v.as_u64() {
+                    Value::Integer(v.into())
+                } else if let Some(v) = v.as_f64() {
+                    Value::Real(v)
+                } else {
+                    unreachable!("a json number can be represented as an i64, u64 or f64")
+                }
+            }
+            JsonValue::String(v) => Value::String(v.clone()),
+            JsonValue::Array(array) => {
+                let mut values = Vec::with_capacity(array.len());
+                for (i, value) in array.iter().enumerate() {
+                    path.push(PathSegment::Index(i));
+                    values.push(self.json_to_value(value, path)?);
+                    path.pop();
+                }
+                Value::Array(values)
+            }
+            JsonValue::Object(object) => {
+                if let Some(value) = self.tagged_json_to_value(object)? {
+                    return Ok(value);
+                }
+                let mut dict = Dictionary::new();
+                for (key, value) in object {
+                    path.push(PathSegment::Key(key));
+                    dict.insert(key.clone(), self.json_to_value(value, path)?);
+                    path.pop();
+                }
+                Value::Dictionary(dict)
+            }
+        })
+    }
+
+    fn tagged_json_to_value(
+        &self,
+        object: &Map<String, JsonValue>,
+    ) -> Result<Option<Value>, ErrorKind> {
+        if object.len() != 1 {
+            return Ok(None);
+        }
+        let (key, value) = object.iter().next().expect("object has one entry");
+        match &key[..] {
+            DATE_TAG if self.date == DateMapping::Tagged => {
+                let date = value
+                    .as_str()
+                    .and_then(|s| Date::from_rfc3339(s).ok())
+                    .ok_or(ErrorKind::InvalidDateString)?;
+                Ok(Some(Value::Date(date)))
+            }
+            DATA_TAG if self.data == DataMapping::Tagged => {
+                let data = value
+                    .as_str()
+                    .and_then(|s| base64::decode(s).ok())
+                    .ok_or(ErrorKind::InvalidDataString)?;
+                Ok(Some(Value::Data(data)))
+            }
+            UID_TAG if self.uid == UidMapping::Tagged => {
+                let uid = value.as_u64().ok_or(ErrorKind::IntegerOutOfRange)?;
+                Ok(Some(Value::Uid(Uid::new(uid))))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+fn tagged(tag: &str, value: JsonValue) -> JsonValue {
+    let mut object = Map::with_capacity(1);
+    object.insert(tag.to_owned(), value);
+    JsonValue::Object(object)
+}
+
+impl TryFrom<&Value> for JsonValue {
+    type Error = Error;
+
+    fn try_from(value: &Value) -> Result<JsonValue, Error> {
+        JsonOptions::new().to_json(value)
+    }
+}
+
+impl TryFrom<Value> for JsonValue {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<JsonValue, Error> {
+        JsonOptions::new().to_json(&value)
+    }
+}
+
+impl TryFrom<JsonValue> for Value {
+    type Error = Error;
+
+    fn try_from(value: JsonValue) -> Result<Value, Error> {
+        JsonOptions::new().from_json(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    fn sample() -> Value {
+        let mut inner = Dictionary::new();
+        inner.insert("uid".to_owned(), Value::Uid(Uid::new(7)));
+        inner.insert("data".to_owned(), Value::Data(vec![0, 1, 2]));
+        inner.insert(
+            "date".to_owned(),
+            Value::Date(Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap()),
+        );
+        inner.insert("big".to_owned(), Value::Integer(u64::MAX.into()));
+
+        let mut dict = Dictionary::new();
+        dict.insert("inner".to_owned(), Value::Dictionary(inner));
+        dict.insert(
+            "list".to_owned(),
+            Value::Array(vec![true.into(), 1.5.into(), "s".into()]),
+        );
+        Value::Dictionary(dict)
+    }
+
+    #[test]
+    fn default_mapping() {
+        let json = JsonValue::try_from(&sample()).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "inner": {
+                    "uid": 7,
+                    "data": "AAEC",
+                    "date": "1981-05-16T11:32:06Z",
+                    "big": 18446744073709551615u64,
+                },
+                "list": [true, 1.5, "s"],
+            })
+        );
+    }
+
+    #[test]
+    fn tagged_roundtrip() {
+        let options = JsonOptions::new()
+            .date(DateMapping::Tagged)
+            .data(DataMapping::Tagged)
+            .uid(UidMapping::Tagged)
+            .big_integer(BigIntegerMapping::Number);
+
+        let json = options.to_json(&sample()).unwrap();
+        assert_eq!(json["inner"]["uid"], json!({ "CF$UID": 7 }));
+        assert_eq!(json["inner"]["data"], json!({ "$data": "AAEC" }));
+        assert_eq!(
+            json["inner"]["date"],
+            json!({ "$date": "1981-05-16T11:32:06Z" })
+        );
+
+        assert_eq!(options.from_json(json).unwrap(), sample());
+    }
+
+    #[test]
+    fn big_integers() {
+        let value = Value::Array(vec![Value::Integer((-(1i64 << 60)).into())]);
+
+        let options = JsonOptions::new().big_integer(BigIntegerMapping::String);
+        assert_eq!(
+            options.to_json(&value).unwrap(),
+            json!(["-1152921504606846976"])
+        );
+
+        let options = JsonOptions::new().big_integer(BigIntegerMapping::Error);
+        let err = options.to_json(&value).unwrap_err();
+        assert_eq!(err.to_string(), "IntegerOutOfRange at `[0]`");
+
+        let value = Value::Array(vec![
+            Value::Integer(i64::MIN.into()),
+            Value::Integer(u64::MAX.into()),
+        ]);
+        let options = JsonOptions::new().big_integer(BigIntegerMapping::String);
+        assert_eq!(
+            options.to_json(&value).unwrap(),
+            json!(["-9223372036854775808", "18446744073709551615"])
+        );
+    }
+
+    #[test]
+    fn unrepresentable_values_report_path() {
+        let mut dict = Dictionary::new();
+        dict.insert(
+            "values".to_owned(),
+            Value::Array(vec![1.0.into(), f64::NAN.into()]),
+        );
+        let err = JsonValue::try_from(Value::Dictionary(dict)).unwrap_err();
+        assert_eq!(err.to_string(), "InfiniteOrNanReal at `values[1]`");
+
+        let err = Value::try_from(json!({ "a": { "b": null } })).unwrap_err();
+        assert_eq!(err.to_string(), "NullNotSupportedInPlist at `a.b`");
+    }
+}
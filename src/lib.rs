@@ -64,54 +64,99 @@
 //! assert_eq!(title, Some("Great Expectations"));
 //! ```
 //!
+//! ## Event Streams
+//!
+//! The [`stream`](stream/index.html) module represents plists as a flat stream of `Event`s. The
+//! readers and writers in that module, together with `Deserializer`, `Serializer`,
+//! `Value::from_events` and `Value::into_events`, can be used to process plists without holding
+//! them in memory. Events borrow strings and data where possible, e.g. from `Value::events` or a
+//! `BinaryReader` reading from a byte slice, so they can be passed through without copying.
+//!
+//! `transcode` pumps the events of any reader into any writer, converting plists between
+//! encodings without building a `Value`.
+//!
+//! ## Shared Objects
+//!
+//! Binary plists can refer to the same object from many places. `BinaryReader::into_graph` reports
+//! each shared object once and later occurrences as references, `SharedValue` holds shared arrays
+//! and dictionaries behind an `Arc`, and `BinaryWriter::write_graph_event` writes them back with
+//! the same sharing.
+//!
+//! ## Inspecting Binary Plists
+//!
+//! [`bplist::inspect`](bplist/fn.inspect.html) reports the trailer, offset table and objects of a
+//! binary plist along with any structural irregularities, such as overlapping or unreferenced
+//! objects.
+//!
 //! ## Unstable Features
 //!
-//! Many features from previous versions are now hidden behind the
-//! `enable_unstable_features_that_may_break_with_minor_version_bumps` feature. These will break in
-//! minor version releases after the 1.0 release. If you really really must use them you should
+//! The `Dictionary::entry` API is hidden behind the
+//! `enable_unstable_features_that_may_break_with_minor_version_bumps` feature. It will break in
+//! minor version releases after the 1.0 release. If you really really must use it you should
 //! specify a tilde requirement e.g. `plist = "~1.0.3"` in you `Cargo.toml` so that the plist crate
 //! is not automatically updated to version 1.1.
+//!
+//! ## Serde Field Helpers
+//!
+//! The [`serde`](serde/index.html) module contains helpers for use with `#[serde(with = "...")]`
+//! which serialize byte buffers, timestamps and integers as plist data, dates and uids.
+//!
+//! ## JSON Conversion
+//!
+//! The optional `serde_json` feature enables conversions between `Value` and `serde_json::Value`
+//! in the [`json`](json/index.html) module.
+
+#[macro_use]
+mod macros;
 
+pub mod bplist;
 pub mod dictionary;
 
-#[cfg(feature = "enable_unstable_features_that_may_break_with_minor_version_bumps")]
 pub mod stream;
-#[cfg(not(feature = "enable_unstable_features_that_may_break_with_minor_version_bumps"))]
-mod stream;
 
 mod date;
 mod error;
 mod integer;
+mod shared_value;
+mod transcode;
 mod uid;
 mod value;
 
 pub use date::Date;
 pub use dictionary::Dictionary;
-pub use error::Error;
+pub use error::{Error, ErrorKind, EventKind};
 pub use integer::Integer;
+pub use shared_value::{GraphEvents, SharedValue};
+pub use transcode::{transcode, TranscodeOptions};
 pub use uid::Uid;
-pub use value::Value;
+pub use value::{OrdValue, Pretty, Value};
+
+#[cfg(feature = "serde_json")]
+pub mod json;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::macros::IntoDate;
+}
 
 // Optional serde module
 #[cfg(feature = "serde")]
-#[macro_use]
-extern crate serde;
-#[cfg(feature = "serde")]
 mod de;
 #[cfg(feature = "serde")]
 mod ser;
-#[cfg(all(
-    feature = "serde",
-    any(
-        test,
-        feature = "enable_unstable_features_that_may_break_with_minor_version_bumps"
-    )
-))]
-pub use self::{de::Deserializer, ser::Serializer};
+#[cfg(feature = "serde")]
+pub mod serde;
+#[cfg(feature = "serde")]
+mod serde_options;
 #[cfg(feature = "serde")]
 pub use self::{
-    de::{from_bytes, from_file, from_reader, from_reader_xml},
-    ser::{to_file_binary, to_file_xml, to_writer_binary, to_writer_xml},
+    de::Deserializer,
+    de::{from_bytes, from_file, from_reader, from_reader_xml, from_value, StreamDeserializer},
+    ser::Serializer,
+    ser::{to_file_binary, to_file_xml, to_value, to_writer_binary, to_writer_xml},
+    serde_options::{
+        NoneRepresentation, SerdeOptions, UnitRepresentation, UnitVariantRepresentation,
+    },
 };
 
 #[cfg(all(test, feature = "serde"))]
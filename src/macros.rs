@@ -0,0 +1,248 @@
+use std::time::SystemTime;
+
+use crate::Date;
+
+/// Constructs a `plist::Value` from a JSON-like literal.
+///
+/// ```
+/// # #[macro_use] extern crate plist;
+/// # fn main() {
+/// let version = "1.0";
+///
+/// let value = plist!({
+///     "CFBundleName": "Example",
+///     "CFBundleShortVersionString": version,
+///     "LSRequiresIPhoneOS": true,
+///     "UIDeviceFamily": [1, 2],
+///     "Icon": data(b"\x89PNG"),
+///     "Created": date("1981-05-16T11:32:06Z"),
+///     "$objects": [{ "root": uid(1) }],
+/// });
+///
+/// let dict = value.as_dictionary().unwrap();
+/// assert_eq!(dict.get("CFBundleShortVersionString"), Some(&plist::Value::from("1.0")));
+/// # }
+/// ```
+///
+/// Dictionaries are written as `{ key: value, ... }` where a key is either a string literal or a
+/// parenthesized expression implementing `Into<String>`. Arrays are written as `[value, ...]`.
+/// Any other expression implementing `Into<Value>` is converted with `Value::from`.
+///
+/// The following markers construct the plist types which have no literal syntax:
+///
+/// - `data(bytes)` creates `Value::Data` from anything implementing `AsRef<[u8]>`.
+/// - `date(date)` creates `Value::Date` from an RFC 3339 string, a `Date` or a `SystemTime`. Invalid
+///   date strings cause a panic.
+/// - `uid(n)` creates `Value::Uid` from a `u64`.
+#[macro_export]
+macro_rules! plist {
+    ([ $($tt:tt)* ]) => {
+        $crate::Value::Array($crate::plist_internal!(@array [] $($tt)*))
+    };
+    ({ $($tt:tt)* }) => {
+        $crate::Value::Dictionary({
+            #[allow(unused_mut)]
+            let mut dict = $crate::Dictionary::new();
+            $crate::plist_internal!(@dict dict $($tt)*);
+            dict
+        })
+    };
+    (data($e:expr)) => {
+        $crate::Value::Data(::std::convert::AsRef::<[u8]>::as_ref(&$e).to_vec())
+    };
+    (date($e:expr)) => {
+        $crate::Value::Date($crate::__private::IntoDate::into_date($e))
+    };
+    (uid($e:expr)) => {
+        $crate::Value::Uid($crate::Uid::new($e))
+    };
+    ($e:expr) => {
+        $crate::Value::from($e)
+    };
+}
+
+#[macro_export]
+#[doc(hidden)]
+macro_rules! plist_internal {
+    // Arrays are built by accumulating each converted element.
+    (@array [$($elems:expr,)*]) => {
+        ::std::vec![$($elems,)*]
+    };
+    (@array [$($elems:expr,)*] , $($rest:tt)*) => {
+        $crate::plist_internal!(@array [$($elems,)*] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] [$($array:tt)*] $($rest:tt)*) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!([$($array)*]),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] {$($dict:tt)*} $($rest:tt)*) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!({$($dict)*}),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] data($e:expr) $(, $($rest:tt)*)?) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!(data($e)),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] date($e:expr) $(, $($rest:tt)*)?) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!(date($e)),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] uid($e:expr) $(, $($rest:tt)*)?) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!(uid($e)),] $($($rest)*)?)
+    };
+    (@array [$($elems:expr,)*] $next:expr, $($rest:tt)*) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!($next),] $($rest)*)
+    };
+    (@array [$($elems:expr,)*] $last:expr) => {
+        $crate::plist_internal!(@array [$($elems,)* $crate::plist!($last),])
+    };
+
+    // Dictionary entries are inserted one at a time.
+    (@dict $dict:ident) => {};
+    (@dict $dict:ident , $($rest:tt)*) => {
+        $crate::plist_internal!(@dict $dict $($rest)*)
+    };
+    (@dict $dict:ident $key:literal : $($rest:tt)*) => {
+        $crate::plist_internal!(@value $dict ($key) $($rest)*)
+    };
+    (@dict $dict:ident ($key:expr) : $($rest:tt)*) => {
+        $crate::plist_internal!(@value $dict ($key) $($rest)*)
+    };
+
+    (@value $dict:ident ($key:expr) [$($array:tt)*] $($rest:tt)*) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!([$($array)*])) $($rest)*)
+    };
+    (@value $dict:ident ($key:expr) {$($inner:tt)*} $($rest:tt)*) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!({$($inner)*})) $($rest)*)
+    };
+    (@value $dict:ident ($key:expr) data($e:expr) $(, $($rest:tt)*)?) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!(data($e))) $($($rest)*)?)
+    };
+    (@value $dict:ident ($key:expr) date($e:expr) $(, $($rest:tt)*)?) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!(date($e))) $($($rest)*)?)
+    };
+    (@value $dict:ident ($key:expr) uid($e:expr) $(, $($rest:tt)*)?) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!(uid($e))) $($($rest)*)?)
+    };
+    (@value $dict:ident ($key:expr) $value:expr, $($rest:tt)*) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!($value)) , $($rest)*)
+    };
+    (@value $dict:ident ($key:expr) $value:expr) => {
+        $crate::plist_internal!(@insert $dict ($key) ($crate::plist!($value)))
+    };
+
+    (@insert $dict:ident ($key:expr) ($value:expr) $($rest:tt)*) => {
+        $dict.insert(::std::string::String::from($key), $value);
+        $crate::plist_internal!(@dict $dict $($rest)*);
+    };
+}
+
+/// Conversion used by the `date` marker of the `plist!` macro.
+pub trait IntoDate {
+    fn into_date(self) -> Date;
+}
+
+impl IntoDate for Date {
+    fn into_date(self) -> Date {
+        self
+    }
+}
+
+impl IntoDate for SystemTime {
+    fn into_date(self) -> Date {
+        self.into()
+    }
+}
+
+impl IntoDate for &str {
+    fn into_date(self) -> Date {
+        Date::from_rfc3339(self).unwrap_or_else(|()| panic!("invalid RFC 3339 date: {:?}", self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use crate::{Date, Dictionary, Uid, Value};
+
+    #[test]
+    fn scalars() {
+        assert_eq!(plist!(true), Value::Boolean(true));
+        assert_eq!(plist!(1.5), Value::Real(1.5));
+        assert_eq!(plist!(-3), Value::Integer((-3).into()));
+        assert_eq!(plist!("s"), Value::String("s".to_owned()));
+        assert_eq!(plist!(data(vec![1, 2])), Value::Data(vec![1, 2]));
+        assert_eq!(plist!(uid(3)), Value::Uid(Uid::new(3)));
+
+        let time = UNIX_EPOCH + Duration::from_secs(358_860_726);
+        assert_eq!(
+            plist!(date("1981-05-16T11:32:06Z")),
+            Value::Date(time.into())
+        );
+        assert_eq!(plist!(date(time)), plist!(date(Date::from(time))));
+    }
+
+    #[test]
+    fn nested() {
+        let key = String::from("computed");
+        let values = vec![Value::from(1), Value::from(2)];
+
+        let value = plist!({
+            "array": [1, "two", [3.0], { "four": 4 }, data(b"5"), values.clone()],
+            (key): { "empty_array": [], "empty_dict": {} },
+            "uid": uid(7),
+        });
+
+        let mut four = Dictionary::new();
+        four.insert("four".to_owned(), 4.into());
+
+        let mut computed = Dictionary::new();
+        computed.insert("empty_array".to_owned(), Value::Array(Vec::new()));
+        computed.insert(
+            "empty_dict".to_owned(),
+            Value::Dictionary(Dictionary::new()),
+        );
+
+        let mut expected = Dictionary::new();
+        expected.insert(
+            "array".to_owned(),
+            Value::Array(vec![
+                1.into(),
+                "two".into(),
+                Value::Array(vec![3.0.into()]),
+                Value::Dictionary(four),
+                Value::Data(b"5".to_vec()),
+                Value::Array(values),
+            ]),
+        );
+        expected.insert("computed".to_owned(), Value::Dictionary(computed));
+        expected.insert("uid".to_owned(), Value::Uid(Uid::new(7)));
+
+        assert_eq!(value, Value::Dictionary(expected));
+    }
+
+    #[test]
+    fn call_expressions() {
+        fn f(n: i64) -> i64 {
+            n
+        }
+
+        assert_eq!(
+            plist!({ "sum": f(1) + 1, "max": f(2).max(3) }),
+            plist!({ "sum": 2, "max": 3 })
+        );
+        assert_eq!(plist!([f(1) + 1, f(2).max(3)]), plist!([2, 3]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_date() {
+        let _ = plist!(date("not a date"));
+    }
+
+    #[test]
+    fn system_time_date() {
+        let now = SystemTime::now();
+        assert_eq!(
+            plist!([date(now)]),
+            Value::Array(vec![Value::Date(now.into())])
+        );
+    }
+}
@@ -1,5 +1,6 @@
 use serde::ser;
 use std::{
+    borrow::Cow,
     fmt::Display,
     fs::File,
     io::{BufWriter, Write},
@@ -9,10 +10,13 @@ use std::{
 
 use crate::{
     date::serde_impls::DATE_NEWTYPE_STRUCT_NAME,
-    error::{self, Error, ErrorKind},
+    error::{self, Error, ErrorKind, EventKind},
+    serde_options::{
+        NoneRepresentation, SerdeOptions, UnitRepresentation, UnitVariantRepresentation,
+    },
     stream::{self, Writer},
     uid::serde_impls::UID_NEWTYPE_STRUCT_NAME,
-    Date, Integer, Uid,
+    Date, Integer, Uid, Value,
 };
 
 #[doc(hidden)]
@@ -24,25 +28,34 @@ impl ser::Error for Error {
 
 enum OptionMode {
     Root,
-    StructField(&'static str),
+    StructField(Cow<'static, str>),
     StructFieldNameWritten,
     Explicit,
 }
 
-/// A structure that serializes Rust values plist event streams.
+/// A structure that serializes Rust values to plist event streams.
 pub struct Serializer<W: Writer> {
     writer: W,
     option_mode: OptionMode,
+    options: SerdeOptions,
 }
 
 impl<W: Writer> Serializer<W> {
+    /// Creates a `Serializer` writing events to `writer`.
     pub fn new(writer: W) -> Serializer<W> {
+        Serializer::new_with_options(writer, SerdeOptions::new())
+    }
+
+    /// Creates a `Serializer` writing events to `writer` using the given options.
+    pub fn new_with_options(writer: W, options: SerdeOptions) -> Serializer<W> {
         Serializer {
             writer,
             option_mode: OptionMode::Root,
+            options,
         }
     }
 
+    /// Consumes the `Serializer`, returning the underlying writer.
     pub fn into_inner(self) -> W {
         self.writer
     }
@@ -59,9 +72,12 @@ impl<W: Writer> Serializer<W> {
     }
 
     fn maybe_write_pending_struct_field_name(&mut self) -> Result<(), Error> {
-        if let OptionMode::StructField(field_name) = self.option_mode {
-            self.option_mode = OptionMode::StructFieldNameWritten;
-            self.writer.write_string(field_name)?;
+        if let OptionMode::StructField(_) = self.option_mode {
+            let prev_option_mode =
+                mem::replace(&mut self.option_mode, OptionMode::StructFieldNameWritten);
+            if let OptionMode::StructField(field_name) = prev_option_mode {
+                self.writer.write_string(&field_name)?;
+            }
         }
         Ok(())
     }
@@ -188,14 +204,26 @@ impl<'a, W: Writer> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_none(self) -> Result<(), Error> {
-        match self.option_mode {
-            OptionMode::Root | OptionMode::StructField(_) => (),
-            OptionMode::StructFieldNameWritten => unreachable!(),
-            OptionMode::Explicit => {
-                self.write_start_dictionary(Some(1))?;
-                self.write_string("None")?;
-                self.serialize_unit()?;
-                self.write_end_collection()?;
+        match self.options.none {
+            NoneRepresentation::Wrapped | NoneRepresentation::Omitted => match self.option_mode {
+                OptionMode::Root | OptionMode::StructField(_) => (),
+                OptionMode::StructFieldNameWritten => unreachable!(),
+                OptionMode::Explicit if self.options.none == NoneRepresentation::Omitted => {
+                    return Err(ErrorKind::NoneNotSupported.without_position());
+                }
+                OptionMode::Explicit => {
+                    self.write_start_dictionary(Some(1))?;
+                    self.write_string("None")?;
+                    self.write_string("")?;
+                    self.write_end_collection()?;
+                }
+            },
+            NoneRepresentation::Sentinel(ref sentinel) => {
+                let sentinel = sentinel.clone();
+                self.write_string(&sentinel)?;
+            }
+            NoneRepresentation::Error => {
+                return Err(ErrorKind::NoneNotSupported.without_position());
             }
         }
         Ok(())
@@ -204,24 +232,31 @@ impl<'a, W: Writer> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_some<T: ?Sized + ser::Serialize>(self, value: &T) -> Result<(), Error> {
         match self.option_mode {
             OptionMode::Root => self.serialize_with_option_mode(OptionMode::Explicit, value)?,
-            OptionMode::StructField(field_name) => {
-                self.option_mode = OptionMode::StructFieldNameWritten;
-                self.write_string(field_name)?;
+            OptionMode::StructField(_) => {
+                self.maybe_write_pending_struct_field_name()?;
                 self.serialize_with_option_mode(OptionMode::Explicit, value)?;
             }
             OptionMode::StructFieldNameWritten => unreachable!(),
-            OptionMode::Explicit => {
+            OptionMode::Explicit if self.options.none == NoneRepresentation::Wrapped => {
                 self.write_start_dictionary(Some(1))?;
                 self.write_string("Some")?;
                 value.serialize(&mut *self)?;
                 self.write_end_collection()?;
             }
+            OptionMode::Explicit => value.serialize(&mut *self)?,
         }
         Ok(())
     }
 
     fn serialize_unit(self) -> Result<(), Error> {
-        self.write_string("")
+        match self.options.unit {
+            UnitRepresentation::EmptyString => self.write_string(""),
+            UnitRepresentation::EmptyDictionary => {
+                self.write_start_dictionary(Some(0))?;
+                self.write_end_collection()
+            }
+            UnitRepresentation::Error => Err(ErrorKind::UnitNotSupported.without_position()),
+        }
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
@@ -234,10 +269,15 @@ impl<'a, W: Writer> ser::Serializer for &'a mut Serializer<W> {
         _variant_index: u32,
         variant: &'static str,
     ) -> Result<(), Error> {
-        self.write_start_dictionary(Some(1))?;
-        self.write_string(variant)?;
-        self.serialize_unit()?;
-        self.write_end_collection()
+        match self.options.unit_variant {
+            UnitVariantRepresentation::Dictionary => {
+                self.write_start_dictionary(Some(1))?;
+                self.write_string(variant)?;
+                self.serialize_unit()?;
+                self.write_end_collection()
+            }
+            UnitVariantRepresentation::String => self.write_string(variant),
+        }
     }
 
     fn serialize_newtype_struct<T: ?Sized + ser::Serialize>(
@@ -268,7 +308,7 @@ impl<'a, W: Writer> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
         let len = len.map(|len| len as u64);
         self.write_start_array(len)?;
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
@@ -298,7 +338,7 @@ impl<'a, W: Writer> ser::Serializer for &'a mut Serializer<W> {
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
         let len = len.map(|len| len as u64);
         self.write_start_dictionary(len)?;
-        Ok(Compound { ser: self })
+        Ok(Compound::new(self))
     }
 
     fn serialize_struct(
@@ -649,6 +689,17 @@ impl<'a, W: Writer> ser::Serializer for UidSerializer<'a, W> {
 #[doc(hidden)]
 pub struct Compound<'a, W: 'a + Writer> {
     ser: &'a mut Serializer<W>,
+    /// The key of the current dictionary entry when it is written together with its value.
+    pending_key: Option<String>,
+}
+
+impl<'a, W: Writer> Compound<'a, W> {
+    fn new(ser: &'a mut Serializer<W>) -> Compound<'a, W> {
+        Compound {
+            ser,
+            pending_key: None,
+        }
+    }
 }
 
 impl<'a, W: Writer> ser::SerializeSeq for Compound<'a, W> {
@@ -713,13 +764,34 @@ impl<'a, W: Writer> ser::SerializeMap for Compound<'a, W> {
     type Error = Error;
 
     fn serialize_key<T: ?Sized + ser::Serialize>(&mut self, key: &T) -> Result<(), Error> {
-        self.ser
-            .serialize_with_option_mode(OptionMode::Explicit, key)
+        if self.ser.options.none != NoneRepresentation::Omitted {
+            return self
+                .ser
+                .serialize_with_option_mode(OptionMode::Explicit, key);
+        }
+
+        // Entries with a `None` value are omitted so the key is only written with its value.
+        match to_value(key)? {
+            Value::String(key) => {
+                self.pending_key = Some(key);
+                Ok(())
+            }
+            key => {
+                let event = key
+                    .into_events()
+                    .next()
+                    .expect("value has at least one event");
+                Err(error::unexpected_event_type(EventKind::String, &event))
+            }
+        }
     }
 
     fn serialize_value<T: ?Sized + ser::Serialize>(&mut self, value: &T) -> Result<(), Error> {
-        self.ser
-            .serialize_with_option_mode(OptionMode::Explicit, value)
+        let option_mode = match self.pending_key.take() {
+            Some(key) => OptionMode::StructField(Cow::Owned(key)),
+            None => OptionMode::Explicit,
+        };
+        self.ser.serialize_with_option_mode(option_mode, value)
     }
 
     fn end(self) -> Result<Self::Ok, Self::Error> {
@@ -739,7 +811,7 @@ impl<'a, W: Writer> ser::SerializeStruct for Compound<'a, W> {
         // We don't want to serialize None if the Option is a struct field as this is how null
         // fields are represented in plists.
         self.ser
-            .serialize_with_option_mode(OptionMode::StructField(key), value)
+            .serialize_with_option_mode(OptionMode::StructField(Cow::Borrowed(key)), value)
     }
 
     fn end(self) -> Result<(), Error> {
@@ -757,7 +829,7 @@ impl<'a, W: Writer> ser::SerializeStructVariant for Compound<'a, W> {
         value: &T,
     ) -> Result<(), Error> {
         self.ser
-            .serialize_with_option_mode(OptionMode::StructField(key), value)
+            .serialize_with_option_mode(OptionMode::StructField(Cow::Borrowed(key)), value)
     }
 
     fn end(self) -> Result<(), Error> {
@@ -795,3 +867,15 @@ pub fn to_writer_xml<W: Write, T: ser::Serialize>(writer: W, value: &T) -> Resul
     let mut ser = Serializer::new(writer);
     value.serialize(&mut ser)
 }
+
+/// Serializes the given data structure to a `Value`.
+///
+/// Dates, uids and byte buffers serialized through `serialize_bytes` are kept as their
+/// corresponding `Value` variants.
+pub fn to_value<T: ser::Serialize + ?Sized>(value: &T) -> Result<Value, Error> {
+    let mut ser = Serializer::new(stream::ValueWriter::new());
+    value.serialize(&mut ser)?;
+    ser.into_inner()
+        .take_value()
+        .ok_or_else(|| ErrorKind::UnexpectedEndOfEventStream.without_position())
+}
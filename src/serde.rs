@@ -0,0 +1,152 @@
+//! Helpers for serializing Rust types as plist-specific types using `#[serde(with = "...")]`.
+//!
+//! Without these helpers `Vec<u8>` serializes as an array of integers and `SystemTime` as a
+//! dictionary of seconds and nanoseconds.
+//!
+//! ```
+//! # #[macro_use] extern crate serde_derive;
+//! use std::time::SystemTime;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Archive {
+//!     #[serde(with = "plist::serde::data")]
+//!     payload: Vec<u8>,
+//!     #[serde(with = "plist::serde::date")]
+//!     created: SystemTime,
+//!     #[serde(with = "plist::serde::uid")]
+//!     root: u64,
+//! }
+//!
+//! # fn main() {
+//! let archive = Archive {
+//!     payload: vec![1, 2, 3],
+//!     created: SystemTime::now(),
+//!     root: 1,
+//! };
+//!
+//! let value = plist::to_value(&archive).unwrap();
+//! let dict = value.as_dictionary().unwrap();
+//! assert_eq!(dict.get("payload"), Some(&plist::Value::Data(vec![1, 2, 3])));
+//! assert_eq!(dict.get("root"), Some(&plist::Value::Uid(plist::Uid::new(1))));
+//! # }
+//! ```
+
+/// Serializes byte buffers as plist data.
+///
+/// Supports any type implementing `AsRef<[u8]>` and `TryFrom<Vec<u8>>`, including `Vec<u8>`,
+/// `[u8; N]` and `bytes::Bytes`. Arrays of integers are also accepted when deserializing from
+/// formats without a byte buffer type.
+pub mod data {
+    use serde::{
+        de::{Deserializer, Error, SeqAccess, Visitor},
+        ser::Serializer,
+    };
+    use std::{convert::TryFrom, fmt};
+
+    pub fn serialize<T, S>(data: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]> + ?Sized,
+        S: Serializer,
+    {
+        serializer.serialize_bytes(data.as_ref())
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: TryFrom<Vec<u8>>,
+        D: Deserializer<'de>,
+    {
+        let data = deserializer.deserialize_byte_buf(DataVisitor)?;
+        let len = data.len();
+        T::try_from(data)
+            .map_err(|_| D::Error::invalid_length(len, &"plist data of a fixed length"))
+    }
+
+    struct DataVisitor;
+
+    impl<'de> Visitor<'de> for DataVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("plist data")
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(v.to_owned())
+        }
+
+        fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+        where
+            E: Error,
+        {
+            Ok(v)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element()? {
+                data.push(byte);
+            }
+            Ok(data)
+        }
+    }
+}
+
+/// Serializes timestamps as plist dates.
+///
+/// Supports any type convertible to and from `Date`, including `SystemTime` and, with the `chrono`
+/// and `time` features, `chrono::DateTime<Utc>` and `time::OffsetDateTime`.
+pub mod date {
+    use serde::{
+        de::{Deserialize, Deserializer},
+        ser::{Serialize, Serializer},
+    };
+
+    use crate::Date;
+
+    pub fn serialize<T, S>(date: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Clone + Into<Date>,
+        S: Serializer,
+    {
+        date.clone().into().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: From<Date>,
+        D: Deserializer<'de>,
+    {
+        Date::deserialize(deserializer).map(T::from)
+    }
+}
+
+/// Serializes `u64`s as plist uids.
+pub mod uid {
+    use serde::{
+        de::{Deserialize, Deserializer},
+        ser::{Serialize, Serializer},
+    };
+
+    use crate::Uid;
+
+    pub fn serialize<S>(uid: &u64, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Uid::new(*uid).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Uid::deserialize(deserializer).map(|uid| uid.get())
+    }
+}
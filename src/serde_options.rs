@@ -0,0 +1,167 @@
+use serde::{de::DeserializeOwned, ser::Serialize};
+use std::io::{Cursor, Read, Seek, Write};
+
+use crate::{
+    de::Deserializer,
+    error::{Error, ErrorKind},
+    ser::Serializer,
+    stream::{self, ReaderOptions},
+    Value,
+};
+
+/// How `Option` values are represented.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NoneRepresentation {
+    /// `None` struct fields are omitted. Elsewhere options are wrapped in a single entry
+    /// dictionary, `{"None": ""}` or `{"Some": value}`. This is the default.
+    ///
+    /// Only this crate understands the wrapped representation.
+    Wrapped,
+    /// `None` struct fields and dictionary entries with a `None` value are omitted and `Some(value)`
+    /// is written as `value`. A `None` array element fails to serialize as it cannot be omitted
+    /// without changing the position of the following elements.
+    Omitted,
+    /// `None`, including as a struct field, is written as the given string and `Some(value)` as
+    /// `value`. A string equal to the sentinel is deserialized as `None`.
+    Sentinel(String),
+    /// `None`, including as a struct field, fails to serialize and `Some(value)` is written as
+    /// `value`.
+    Error,
+}
+
+/// How `()` and unit structs are represented.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitRepresentation {
+    /// An empty string. This is the default.
+    EmptyString,
+    /// An empty dictionary.
+    EmptyDictionary,
+    /// Serialization and deserialization fail.
+    Error,
+}
+
+/// How unit enum variants are represented.
+///
+/// Both representations are accepted when deserializing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnitVariantRepresentation {
+    /// A single entry dictionary mapping the variant name to `()`. This is the default.
+    Dictionary,
+    /// The variant name as a string.
+    String,
+}
+
+/// Options controlling how Rust types without a direct plist equivalent are serialized and
+/// deserialized.
+///
+/// ```
+/// use plist::{NoneRepresentation, SerdeOptions};
+///
+/// let options = SerdeOptions::new().none(NoneRepresentation::Sentinel("$null".to_owned()));
+///
+/// let value = options.to_value(&vec![Some(1), None]).unwrap();
+/// assert_eq!(value, plist::Value::Array(vec![1.into(), "$null".into()]));
+/// assert_eq!(options.from_value::<Vec<Option<i32>>>(value).unwrap(), vec![Some(1), None]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SerdeOptions {
+    pub(crate) none: NoneRepresentation,
+    pub(crate) unit: UnitRepresentation,
+    pub(crate) unit_variant: UnitVariantRepresentation,
+    pub(crate) reader_options: ReaderOptions,
+}
+
+impl Default for SerdeOptions {
+    fn default() -> Self {
+        SerdeOptions::new()
+    }
+}
+
+impl SerdeOptions {
+    /// Returns the default options.
+    pub fn new() -> SerdeOptions {
+        SerdeOptions {
+            none: NoneRepresentation::Wrapped,
+            unit: UnitRepresentation::EmptyString,
+            unit_variant: UnitVariantRepresentation::Dictionary,
+            reader_options: ReaderOptions::new(),
+        }
+    }
+
+    /// Sets how `Option` values are represented.
+    pub fn none(mut self, representation: NoneRepresentation) -> Self {
+        self.none = representation;
+        self
+    }
+
+    /// Sets how `()` and unit structs are represented.
+    pub fn unit(mut self, representation: UnitRepresentation) -> Self {
+        self.unit = representation;
+        self
+    }
+
+    /// Sets how unit enum variants are represented.
+    pub fn unit_variant(mut self, representation: UnitVariantRepresentation) -> Self {
+        self.unit_variant = representation;
+        self
+    }
+
+    /// Sets the limits on the plists accepted when deserializing, e.g. from untrusted input.
+    pub fn reader_options(mut self, options: ReaderOptions) -> Self {
+        self.reader_options = options;
+        self
+    }
+
+    /// Serializes the given data structure to a byte stream as a binary encoded plist.
+    pub fn to_writer_binary<W: Write, T: Serialize>(
+        &self,
+        writer: W,
+        value: &T,
+    ) -> Result<(), Error> {
+        let writer = stream::BinaryWriter::new(writer);
+        let mut ser = Serializer::new_with_options(writer, self.clone());
+        value.serialize(&mut ser)
+    }
+
+    /// Serializes the given data structure to a byte stream as an XML encoded plist.
+    pub fn to_writer_xml<W: Write, T: Serialize>(&self, writer: W, value: &T) -> Result<(), Error> {
+        let writer = stream::XmlWriter::new(writer);
+        let mut ser = Serializer::new_with_options(writer, self.clone());
+        value.serialize(&mut ser)
+    }
+
+    /// Serializes the given data structure to a `Value`.
+    pub fn to_value<T: Serialize + ?Sized>(&self, value: &T) -> Result<Value, Error> {
+        let mut ser = Serializer::new_with_options(stream::ValueWriter::new(), self.clone());
+        value.serialize(&mut ser)?;
+        ser.into_inner()
+            .take_value()
+            .ok_or_else(|| ErrorKind::UnexpectedEndOfEventStream.without_position())
+    }
+
+    /// Deserializes an instance of type `T` from a byte slice.
+    pub fn from_bytes<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, Error> {
+        self.from_reader(Cursor::new(bytes))
+    }
+
+    /// Deserializes an instance of type `T` from a seekable byte stream containing a plist of any
+    /// encoding.
+    pub fn from_reader<R: Read + Seek, T: DeserializeOwned>(&self, reader: R) -> Result<T, Error> {
+        let reader = stream::Reader::new_with_options(reader, self.reader_options.clone());
+        let mut de = Deserializer::from_positioned_reader(reader, self.clone());
+        T::deserialize(&mut de)
+    }
+
+    /// Deserializes an instance of type `T` from a byte stream containing an XML encoded plist.
+    pub fn from_reader_xml<R: Read, T: DeserializeOwned>(&self, reader: R) -> Result<T, Error> {
+        let reader = stream::XmlReader::new_with_options(reader, self.reader_options.clone());
+        let mut de = Deserializer::from_positioned_reader(reader, self.clone());
+        T::deserialize(&mut de)
+    }
+
+    /// Deserializes an instance of type `T` from a `Value`.
+    pub fn from_value<T: DeserializeOwned>(&self, value: Value) -> Result<T, Error> {
+        let mut de = Deserializer::new_with_options(value.into_events().map(Ok), self.clone());
+        T::deserialize(&mut de)
+    }
+}
@@ -1,13 +1,23 @@
-use serde::{de::DeserializeOwned, ser::Serialize};
-use std::{collections::BTreeMap, fmt::Debug};
+use serde::{
+    de::{Deserialize, DeserializeOwned},
+    ser::Serialize,
+};
+use std::{
+    collections::BTreeMap,
+    fmt::Debug,
+    io::Cursor,
+    time::{Duration, UNIX_EPOCH},
+};
 
 use crate::{
-    stream::{private::Sealed, Event, Writer},
-    Date, Deserializer, Error, Integer, Serializer, Uid,
+    from_value,
+    stream::{Event, OwnedEvent, ReaderOptions, Writer},
+    to_value, Date, Deserializer, Error, ErrorKind, Integer, NoneRepresentation, SerdeOptions,
+    Serializer, StreamDeserializer, Uid, UnitRepresentation, UnitVariantRepresentation, Value,
 };
 
 struct VecWriter {
-    events: Vec<Event>,
+    events: Vec<OwnedEvent>,
 }
 
 impl VecWriter {
@@ -15,7 +25,7 @@ impl VecWriter {
         VecWriter { events: Vec::new() }
     }
 
-    pub fn into_inner(self) -> Vec<Event> {
+    pub fn into_inner(self) -> Vec<OwnedEvent> {
         self.events
     }
 }
@@ -42,7 +52,7 @@ impl Writer for VecWriter {
     }
 
     fn write_data(&mut self, value: &[u8]) -> Result<(), Error> {
-        self.events.push(Event::Data(value.to_owned()));
+        self.events.push(Event::Data(value.to_owned().into()));
         Ok(())
     }
 
@@ -62,7 +72,7 @@ impl Writer for VecWriter {
     }
 
     fn write_string(&mut self, value: &str) -> Result<(), Error> {
-        self.events.push(Event::String(value.to_owned()));
+        self.events.push(Event::String(value.to_owned().into()));
         Ok(())
     }
 
@@ -72,8 +82,6 @@ impl Writer for VecWriter {
     }
 }
 
-impl Sealed for VecWriter {}
-
 fn new_serializer() -> Serializer<VecWriter> {
     Serializer::new(VecWriter::new())
 }
@@ -135,8 +143,8 @@ fn cow() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("Cow".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("Cow".into()),
+        Event::String("".into()),
         Event::EndCollection,
     ];
 
@@ -156,21 +164,21 @@ fn dog() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("Dog".to_owned()),
+        Event::String("Dog".into()),
         Event::StartDictionary(None),
-        Event::String("inner".to_owned()),
+        Event::String("inner".into()),
         Event::StartArray(Some(1)),
         Event::StartDictionary(None),
-        Event::String("a".to_owned()),
-        Event::String("".to_owned()),
-        Event::String("b".to_owned()),
+        Event::String("a".into()),
+        Event::String("".into()),
+        Event::String("b".into()),
         Event::Integer(12.into()),
-        Event::String("c".to_owned()),
+        Event::String("c".into()),
         Event::StartArray(Some(2)),
-        Event::String("a".to_owned()),
-        Event::String("b".to_owned()),
+        Event::String("a".into()),
+        Event::String("b".into()),
         Event::EndCollection,
-        Event::String("d".to_owned()),
+        Event::String("d".into()),
         Event::Uid(Uid::new(42)),
         Event::EndCollection,
         Event::EndCollection,
@@ -182,6 +190,7 @@ fn dog() {
 }
 
 #[test]
+#[allow(clippy::approx_constant)]
 fn frog() {
     let frog = Animal::Frog(
         Ok("hello".to_owned()),
@@ -190,14 +199,14 @@ fn frog() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("Frog".to_owned()),
+        Event::String("Frog".into()),
         Event::StartArray(Some(2)),
         Event::StartDictionary(Some(1)),
-        Event::String("Ok".to_owned()),
-        Event::String("hello".to_owned()),
+        Event::String("Ok".into()),
+        Event::String("hello".into()),
         Event::EndCollection,
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartArray(Some(5)),
         Event::Real(1.0),
         Event::Real(2.0),
@@ -223,13 +232,13 @@ fn cat_with_firmware() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("Cat".to_owned()),
+        Event::String("Cat".into()),
         Event::StartDictionary(None),
-        Event::String("age".to_owned()),
+        Event::String("age".into()),
         Event::Integer(12.into()),
-        Event::String("name".to_owned()),
-        Event::String("Paws".to_owned()),
-        Event::String("firmware".to_owned()),
+        Event::String("name".into()),
+        Event::String("Paws".into()),
+        Event::String("firmware".into()),
         Event::StartArray(Some(9)),
         Event::Integer(0.into()),
         Event::Integer(1.into()),
@@ -258,12 +267,12 @@ fn cat_without_firmware() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("Cat".to_owned()),
+        Event::String("Cat".into()),
         Event::StartDictionary(None),
-        Event::String("age".to_owned()),
+        Event::String("age".into()),
         Event::Integer(Integer::from(-12)),
-        Event::String("name".to_owned()),
-        Event::String("Paws".to_owned()),
+        Event::String("name".into()),
+        Event::String("Paws".into()),
         Event::EndCollection,
         Event::EndCollection,
     ];
@@ -315,18 +324,18 @@ fn type_with_options() {
 
     let comparison = &[
         Event::StartDictionary(None),
-        Event::String("a".to_owned()),
-        Event::String("hello".to_owned()),
-        Event::String("b".to_owned()),
+        Event::String("a".into()),
+        Event::String("hello".into()),
+        Event::String("b".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
-        Event::String("c".to_owned()),
+        Event::String("c".into()),
         Event::StartDictionary(None),
-        Event::String("b".to_owned()),
+        Event::String("b".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::Integer(12.into()),
         Event::EndCollection,
         Event::EndCollection,
@@ -348,14 +357,14 @@ fn type_with_date() {
 
     let obj = TypeWithDate {
         a: Some(28),
-        b: Some(date.clone()),
+        b: Some(date),
     };
 
     let comparison = &[
         Event::StartDictionary(None),
-        Event::String("a".to_owned()),
+        Event::String("a".into()),
         Event::Integer(28.into()),
-        Event::String("b".to_owned()),
+        Event::String("b".into()),
         Event::Date(date),
         Event::EndCollection,
     ];
@@ -387,7 +396,7 @@ fn option_some_some() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::Integer(12.into()),
         Event::EndCollection,
     ];
@@ -401,8 +410,8 @@ fn option_some_none() {
 
     let comparison = &[
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
     ];
 
@@ -418,24 +427,24 @@ fn option_dictionary_values() {
 
     let comparison = &[
         Event::StartDictionary(Some(3)),
-        Event::String("a".to_owned()),
+        Event::String("a".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
-        Event::String("b".to_owned()),
+        Event::String("b".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
         Event::EndCollection,
-        Event::String("c".to_owned()),
+        Event::String("c".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::Integer(144.into()),
         Event::EndCollection,
         Event::EndCollection,
@@ -455,22 +464,22 @@ fn option_dictionary_keys() {
     let comparison = &[
         Event::StartDictionary(Some(3)),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
         Event::Integer(1.into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
         Event::EndCollection,
         Event::Integer(2.into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::Integer(144.into()),
         Event::EndCollection,
         Event::EndCollection,
@@ -488,20 +497,20 @@ fn option_array() {
     let comparison = &[
         Event::StartArray(Some(3)),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("None".to_owned()),
-        Event::String("".to_owned()),
+        Event::String("None".into()),
+        Event::String("".into()),
         Event::EndCollection,
         Event::EndCollection,
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::StartDictionary(Some(1)),
-        Event::String("Some".to_owned()),
+        Event::String("Some".into()),
         Event::Integer(144.into()),
         Event::EndCollection,
         Event::EndCollection,
@@ -510,3 +519,570 @@ fn option_array() {
 
     assert_roundtrip(obj, Some(comparison));
 }
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct TypeWithPlistTypes {
+    date: Date,
+    uid: Uid,
+    data: Data,
+    names: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Data(Vec<u8>);
+
+impl Serialize for Data {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Data {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct DataVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for DataVisitor {
+            type Value = Data;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("plist data")
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Data, E> {
+                Ok(Data(v.to_owned()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Data, E> {
+                Ok(Data(v))
+            }
+        }
+
+        deserializer.deserialize_byte_buf(DataVisitor)
+    }
+}
+
+#[test]
+fn value_roundtrip() {
+    let obj = TypeWithPlistTypes {
+        date: Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap(),
+        uid: Uid::new(12),
+        data: Data(vec![1, 2, 3]),
+        names: vec!["a".to_owned(), "b".to_owned()],
+    };
+
+    let value = to_value(&obj).unwrap();
+
+    let dict = value.as_dictionary().unwrap();
+    assert_eq!(dict.get("date"), Some(&Value::Date(obj.date)));
+    assert_eq!(dict.get("uid"), Some(&Value::Uid(obj.uid)));
+    assert_eq!(dict.get("data"), Some(&Value::Data(vec![1, 2, 3])));
+    assert_eq!(
+        dict.get("names"),
+        Some(&Value::Array(vec!["a".into(), "b".into()]))
+    );
+
+    let borrowed = TypeWithPlistTypes::deserialize(&value).unwrap();
+    assert_eq!(borrowed, obj);
+    assert_eq!(from_value::<TypeWithPlistTypes>(value).unwrap(), obj);
+}
+
+#[test]
+fn serialize_value() {
+    let date = Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap();
+    let value = crate::plist!({
+        "b": [true, 1.5, -1, "s"],
+        "a": { "data": data([0u8, 1]), "date": date(date), "uid": uid(3) },
+    });
+
+    let comparison = &[
+        Event::StartDictionary(Some(2)),
+        Event::String("b".into()),
+        Event::StartArray(Some(4)),
+        Event::Boolean(true),
+        Event::Real(1.5),
+        Event::Integer((-1).into()),
+        Event::String("s".into()),
+        Event::EndCollection,
+        Event::String("a".into()),
+        Event::StartDictionary(Some(3)),
+        Event::String("data".into()),
+        Event::Data(vec![0, 1].into()),
+        Event::String("date".into()),
+        Event::Date(date),
+        Event::String("uid".into()),
+        Event::Uid(Uid::new(3)),
+        Event::EndCollection,
+        Event::EndCollection,
+    ];
+
+    let mut se = new_serializer();
+    value.serialize(&mut se).unwrap();
+    assert_eq!(&se.into_inner().into_inner()[..], comparison);
+
+    assert_eq!(to_value(&value).unwrap(), value);
+}
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    #[allow(dead_code)]
+    name: String,
+    #[allow(dead_code)]
+    servers: Vec<Server>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Server {
+    #[allow(dead_code)]
+    port: u16,
+}
+
+#[test]
+fn error_path_and_position() {
+    let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+    <key>name</key>
+    <string>example</string>
+    <key>servers</key>
+    <array>
+        <dict>
+            <key>port</key>
+            <integer>80</integer>
+        </dict>
+        <dict>
+            <key>port</key>
+            <string>eighty</string>
+        </dict>
+    </array>
+</dict>
+</plist>"#;
+
+    let err = crate::from_bytes::<Config>(plist.as_bytes()).unwrap_err();
+    assert_eq!(err.path(), Some("servers[1].port"));
+    assert_eq!(err.line_column(), Some((14, 13)));
+
+    let events = vec![
+        Event::StartDictionary(None),
+        Event::String("name".into()),
+        Event::Integer(1.into()),
+        Event::EndCollection,
+    ];
+    let err = Config::deserialize(&mut new_deserializer(events)).unwrap_err();
+    assert_eq!(err.path(), Some("name"));
+    assert_eq!(err.line_column(), None);
+
+    let events = vec![
+        Event::StartDictionary(None),
+        Event::String("outer".into()),
+        Event::StartDictionary(None),
+        Event::String("inner".to_owned().into()),
+        Event::String("one".into()),
+        Event::EndCollection,
+        Event::EndCollection,
+    ];
+    let err = BTreeMap::<String, BTreeMap<String, u32>>::deserialize(&mut new_deserializer(events))
+        .unwrap_err();
+    assert_eq!(err.path(), Some("outer.inner"));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum UntaggedPlistType {
+    Integer(u64),
+    String(String),
+    Date(Date),
+    Uid(Uid),
+}
+
+#[test]
+fn untagged_plist_types() {
+    let date = Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap();
+
+    let events = vec![
+        Event::StartArray(Some(4)),
+        Event::Integer(1.into()),
+        Event::String("s".into()),
+        Event::Date(date),
+        Event::Uid(Uid::new(7)),
+        Event::EndCollection,
+    ];
+    let obj = Vec::<UntaggedPlistType>::deserialize(&mut new_deserializer(events)).unwrap();
+    assert_eq!(
+        obj,
+        vec![
+            UntaggedPlistType::Integer(1),
+            UntaggedPlistType::String("s".into()),
+            UntaggedPlistType::Date(date),
+            UntaggedPlistType::Uid(Uid::new(7)),
+        ]
+    );
+
+    assert_roundtrip(obj, None);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Flattened {
+    name: String,
+    #[serde(flatten)]
+    inner: FlattenedInner,
+    #[serde(flatten)]
+    other: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct FlattenedInner {
+    created: Date,
+    owner: Uid,
+}
+
+#[test]
+fn flattened_plist_types() {
+    let date = Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap();
+
+    let events = vec![
+        Event::StartDictionary(None),
+        Event::String("name".into()),
+        Event::String("example".into()),
+        Event::String("created".into()),
+        Event::Date(date),
+        Event::String("owner".into()),
+        Event::Uid(Uid::new(3)),
+        Event::String("modified".into()),
+        Event::Date(date),
+        Event::String("parent".into()),
+        Event::Uid(Uid::new(4)),
+        Event::EndCollection,
+    ];
+    let obj = Flattened::deserialize(&mut new_deserializer(events)).unwrap();
+
+    let mut other = BTreeMap::new();
+    other.insert("modified".to_owned(), Value::Date(date));
+    other.insert("parent".to_owned(), Value::Uid(Uid::new(4)));
+    assert_eq!(
+        obj,
+        Flattened {
+            name: "example".to_owned(),
+            inner: FlattenedInner {
+                created: date,
+                owner: Uid::new(3),
+            },
+            other,
+        }
+    );
+
+    assert_roundtrip(obj, None);
+}
+
+#[test]
+fn deserialize_value() {
+    let date = Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap();
+    let value = crate::plist!({
+        "b": [true, 1.5, -1, "s"],
+        "a": { "data": data([0u8, 1]), "date": date(date), "uid": uid(3) },
+    });
+
+    let events = value.clone().into_events().collect();
+    let deserialized = Value::deserialize(&mut new_deserializer(events)).unwrap();
+    assert_eq!(deserialized, value);
+    assert_eq!(from_value::<Value>(value.clone()).unwrap(), value);
+}
+
+#[test]
+fn fractional_dates() {
+    let date = Date::from(UNIX_EPOCH + Duration::new(1_500_000_000, 250_000_000));
+
+    let events = vec![
+        Event::StartArray(Some(1)),
+        Event::Date(date),
+        Event::EndCollection,
+    ];
+    let obj = Vec::<UntaggedPlistType>::deserialize(&mut new_deserializer(events)).unwrap();
+    assert_eq!(obj, vec![UntaggedPlistType::Date(date)]);
+    assert_roundtrip(obj, None);
+
+    let events = vec![
+        Event::StartDictionary(None),
+        Event::String("name".into()),
+        Event::String("example".into()),
+        Event::String("created".into()),
+        Event::Date(date),
+        Event::String("owner".into()),
+        Event::Uid(Uid::new(3)),
+        Event::String("modified".into()),
+        Event::Date(date),
+        Event::EndCollection,
+    ];
+    let obj = Flattened::deserialize(&mut new_deserializer(events)).unwrap();
+    assert_eq!(obj.inner.created, date);
+    assert_eq!(obj.other["modified"], Value::Date(date));
+    assert_roundtrip(obj, None);
+
+    let value = Value::Date(date);
+    let events = vec![Event::Date(date)];
+    let deserialized = Value::deserialize(&mut new_deserializer(events)).unwrap();
+    assert_eq!(deserialized, value);
+    assert_eq!(from_value::<Date>(value.clone()).unwrap(), date);
+    assert_eq!(from_value::<Value>(value.clone()).unwrap(), value);
+    assert_eq!(to_value(&date).unwrap(), value);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TypeWithOptionalEntries {
+    a: Option<u32>,
+    b: Option<u32>,
+    map: BTreeMap<String, Option<u32>>,
+}
+
+fn type_with_optional_entries() -> TypeWithOptionalEntries {
+    let mut map = BTreeMap::new();
+    map.insert("x".to_owned(), Some(1));
+    map.insert("y".to_owned(), None);
+    TypeWithOptionalEntries {
+        a: Some(2),
+        b: None,
+        map,
+    }
+}
+
+#[test]
+fn none_omitted() {
+    let options = SerdeOptions::new().none(NoneRepresentation::Omitted);
+
+    let obj = type_with_optional_entries();
+    let value = options.to_value(&obj).unwrap();
+    assert_eq!(value, crate::plist!({ "a": 2, "map": { "x": 1 } }));
+
+    let mut expected = type_with_optional_entries();
+    expected.map.remove("y");
+    assert_eq!(
+        options
+            .from_value::<TypeWithOptionalEntries>(value)
+            .unwrap(),
+        expected
+    );
+
+    let err = options.to_value(&vec![Some(1), None]).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::NoneNotSupported));
+    assert_eq!(
+        options
+            .from_value::<Vec<Option<u32>>>(crate::plist!([1]))
+            .unwrap(),
+        vec![Some(1)]
+    );
+}
+
+#[test]
+fn none_sentinel() {
+    let options = SerdeOptions::new().none(NoneRepresentation::Sentinel("$null".to_owned()));
+
+    let obj = type_with_optional_entries();
+    let value = options.to_value(&obj).unwrap();
+    assert_eq!(
+        value,
+        crate::plist!({ "a": 2, "b": "$null", "map": { "x": 1, "y": "$null" } })
+    );
+    assert_eq!(
+        options
+            .from_value::<TypeWithOptionalEntries>(value)
+            .unwrap(),
+        obj
+    );
+
+    let value = options.to_value(&None::<u32>).unwrap();
+    assert_eq!(value, Value::String("$null".to_owned()));
+    assert_eq!(options.from_value::<Option<u32>>(value).unwrap(), None);
+}
+
+#[test]
+fn none_error() {
+    let options = SerdeOptions::new().none(NoneRepresentation::Error);
+
+    let err = options.to_value(&type_with_optional_entries()).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::NoneNotSupported));
+    assert_eq!(
+        options.to_value(&vec![Some(1)]).unwrap(),
+        crate::plist!([1])
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct UnitStruct;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TypeWithUnits {
+    unit: (),
+    unit_struct: UnitStruct,
+    variant: Animal,
+}
+
+#[test]
+fn unit_representations() {
+    let obj = TypeWithUnits {
+        unit: (),
+        unit_struct: UnitStruct,
+        variant: Animal::Cow,
+    };
+
+    let options = SerdeOptions::new()
+        .unit(UnitRepresentation::EmptyDictionary)
+        .unit_variant(UnitVariantRepresentation::String);
+    let value = options.to_value(&obj).unwrap();
+    assert_eq!(
+        value,
+        crate::plist!({ "unit": {}, "unit_struct": {}, "variant": "Cow" })
+    );
+    assert_eq!(
+        options.from_value::<TypeWithUnits>(value.clone()).unwrap(),
+        obj
+    );
+
+    // Unit variants written as strings are also accepted by the default options.
+    let value = crate::plist!({ "unit": "", "unit_struct": "", "variant": "Cow" });
+    assert_eq!(from_value::<TypeWithUnits>(value).unwrap(), obj);
+
+    let options = SerdeOptions::new().unit(UnitRepresentation::Error);
+    let err = options.to_value(&obj).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::UnitNotSupported));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct TypeWithFieldHelpers {
+    #[serde(with = "crate::serde::data")]
+    vec: Vec<u8>,
+    #[serde(with = "crate::serde::data")]
+    array: [u8; 4],
+    #[serde(with = "crate::serde::data")]
+    bytes: bytes::Bytes,
+    #[serde(with = "crate::serde::date")]
+    system_time: std::time::SystemTime,
+    #[cfg(feature = "chrono")]
+    #[serde(with = "crate::serde::date")]
+    chrono: chrono::DateTime<chrono::Utc>,
+    #[cfg(feature = "time")]
+    #[serde(with = "crate::serde::date")]
+    time: time::OffsetDateTime,
+    #[serde(with = "crate::serde::uid")]
+    uid: u64,
+}
+
+#[test]
+fn field_helpers() {
+    let date = Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap();
+    let obj = TypeWithFieldHelpers {
+        vec: vec![1, 2],
+        array: [3, 4, 5, 6],
+        bytes: bytes::Bytes::from_static(b"\x07"),
+        system_time: date.into(),
+        #[cfg(feature = "chrono")]
+        chrono: date.into(),
+        #[cfg(feature = "time")]
+        time: date.into(),
+        uid: 8,
+    };
+
+    let value = to_value(&obj).unwrap();
+    let dict = value.as_dictionary().unwrap();
+    assert_eq!(dict.get("vec"), Some(&Value::Data(vec![1, 2])));
+    assert_eq!(dict.get("array"), Some(&Value::Data(vec![3, 4, 5, 6])));
+    assert_eq!(dict.get("bytes"), Some(&Value::Data(vec![7])));
+    assert_eq!(dict.get("system_time"), Some(&Value::Date(date)));
+    #[cfg(feature = "chrono")]
+    assert_eq!(dict.get("chrono"), Some(&Value::Date(date)));
+    #[cfg(feature = "time")]
+    assert_eq!(dict.get("time"), Some(&Value::Date(date)));
+    assert_eq!(dict.get("uid"), Some(&Value::Uid(Uid::new(8))));
+
+    assert_eq!(
+        from_value::<TypeWithFieldHelpers>(value.clone()).unwrap(),
+        obj
+    );
+
+    let mut value = value;
+    value
+        .as_dictionary_mut()
+        .unwrap()
+        .insert("array".to_owned(), Value::Data(vec![1]));
+    assert!(from_value::<TypeWithFieldHelpers>(value).is_err());
+}
+
+#[test]
+fn stream_deserializer() {
+    let servers: Vec<_> = (0..100).map(|port| Server { port }).collect();
+    let mut buf = Vec::new();
+    crate::to_writer_binary(&mut buf, &servers).unwrap();
+
+    let mut count = 0;
+    for (i, server) in StreamDeserializer::<_, Server>::new(Cursor::new(&buf)).enumerate() {
+        assert_eq!(server.unwrap().port, i as u16);
+        count += 1;
+    }
+    assert_eq!(count, 100);
+
+    let plist = br#"<plist><array>
+    <dict><key>port</key><integer>80</integer></dict>
+    <dict><key>port</key><string>eighty</string></dict>
+    <dict><key>port</key><integer>82</integer></dict>
+</array></plist>"#;
+    let mut stream = StreamDeserializer::<_, Server>::new(Cursor::new(&plist[..]));
+    assert_eq!(stream.next().unwrap().unwrap().port, 80);
+    let err = stream.next().unwrap().unwrap_err();
+    assert_eq!(err.path(), Some("[1].port"));
+    assert_eq!(err.line_column(), Some((3, 26)));
+    assert!(stream.next().is_none());
+
+    let mut stream =
+        StreamDeserializer::<_, Server>::new(Cursor::new(&b"<plist><dict/></plist>"[..]));
+    assert!(stream.next().unwrap().is_err());
+    assert!(stream.next().is_none());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct BorrowedFields<'a> {
+    name: &'a str,
+    #[serde(with = "crate::serde::data")]
+    payload: Vec<u8>,
+    raw: &'a [u8],
+}
+
+#[test]
+fn borrowed_deserialization() {
+    let mut dict = crate::Dictionary::new();
+    dict.insert("name".to_owned(), "Paws".into());
+    dict.insert("payload".to_owned(), Value::Data(vec![1, 2]));
+    dict.insert("raw".to_owned(), Value::Data(vec![3, 4]));
+    let value = Value::Dictionary(dict);
+
+    let expected = BorrowedFields {
+        name: "Paws",
+        payload: vec![1, 2],
+        raw: &[3, 4],
+    };
+    assert_eq!(BorrowedFields::deserialize(&value).unwrap(), expected);
+
+    let mut buf = Vec::new();
+    value.to_writer_binary(&mut buf).unwrap();
+    let mut de = Deserializer::new(crate::stream::BinaryReader::from_slice(&buf));
+    assert_eq!(BorrowedFields::deserialize(&mut de).unwrap(), expected);
+}
+
+#[test]
+fn reader_limits() {
+    let nested: Vec<Vec<Vec<u32>>> = vec![vec![vec![1, 2]], vec![]];
+    let value = to_value(&nested).unwrap();
+    let mut xml = Vec::new();
+    value.to_writer_xml(&mut xml).unwrap();
+
+    let options = SerdeOptions::new().reader_options(ReaderOptions::new().max_depth(3));
+    let decoded: Vec<Vec<Vec<u32>>> = options.from_bytes(&xml).unwrap();
+    assert_eq!(decoded, nested);
+
+    let options = SerdeOptions::new().reader_options(ReaderOptions::new().max_depth(2));
+    let err = options.from_bytes::<Vec<Vec<Vec<u32>>>>(&xml).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::DepthLimitExceeded));
+    assert_eq!(err.path(), Some("[0][0]"));
+
+    // Limits also apply to event streams which are not read from a plist.
+    let options = SerdeOptions::new().reader_options(ReaderOptions::new().max_objects(4));
+    let err = options.from_value::<Vec<Vec<Vec<u32>>>>(value).unwrap_err();
+    assert!(matches!(err.kind(), ErrorKind::ObjectLimitExceeded));
+}
@@ -0,0 +1,507 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{Read, Seek, Write},
+    sync::Arc,
+};
+
+use crate::{
+    error::{Error, ErrorKind, EventKind},
+    stream::{BinaryReader, BinaryWriter, Event, EventValidator, Events, GraphEvent},
+    u64_to_usize, Dictionary, Value,
+};
+
+/// A plist value whose arrays and dictionaries may be shared by several parents.
+///
+/// Binary plists can refer to the same array or dictionary from many places. Reading such a plist
+/// into a `Value` copies the shared collection into each parent, which for deeply nested sharing
+/// can take time and memory exponential in the size of the file. A `SharedValue` instead holds
+/// each collection once behind an `Arc`, and writing it as a binary plist preserves the sharing.
+///
+/// ```
+/// use plist::SharedValue;
+/// use std::io::Cursor;
+///
+/// let value = SharedValue::from_reader_binary(Cursor::new(
+///     std::fs::read("tests/data/binary.plist").unwrap(),
+/// ))
+/// .unwrap();
+///
+/// let mut bytes = Vec::new();
+/// value.to_writer_binary(&mut bytes).unwrap();
+/// assert_eq!(plist::Value::from_reader(Cursor::new(bytes)).unwrap(), value.to_value());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum SharedValue {
+    Array(Arc<Vec<SharedValue>>),
+    /// The entries of a dictionary in order.
+    Dictionary(Arc<Vec<(String, SharedValue)>>),
+    /// Any value other than an array or dictionary. Values referenced more than once in a binary
+    /// plist share the same `Arc`.
+    ///
+    /// An array or dictionary held here is written as a copy of its contents, without sharing.
+    Value(Arc<Value>),
+}
+
+impl SharedValue {
+    /// Reads a `SharedValue` from a seekable byte stream containing a binary encoded plist.
+    pub fn from_reader_binary<R: Read + Seek>(reader: R) -> Result<SharedValue, Error> {
+        SharedValue::from_graph_events(BinaryReader::new(reader).into_graph())
+    }
+
+    /// Builds a single `SharedValue` from a `GraphEvent` iterator, sharing each object which is
+    /// referenced more than once. On success any excess events will remain in the iterator.
+    pub fn from_graph_events<'a, T>(events: T) -> Result<SharedValue, Error>
+    where
+        T: IntoIterator<Item = Result<GraphEvent<'a>, Error>>,
+    {
+        let mut builder = GraphBuilder::new();
+        for event in events {
+            if let Some(value) = builder.push(event?)? {
+                return Ok(value);
+            }
+        }
+        Err(ErrorKind::UnexpectedEndOfEventStream.without_position())
+    }
+
+    /// Serializes a `SharedValue` to a byte stream as a binary encoded plist, writing each shared
+    /// array and dictionary once.
+    pub fn to_writer_binary<W: Write>(&self, writer: W) -> Result<(), Error> {
+        let mut writer = BinaryWriter::new(writer);
+        for event in self.graph_events() {
+            writer.write_graph_event(&event)?;
+        }
+        Ok(())
+    }
+
+    /// Returns a `GraphEvent` iterator which borrows strings and data from the `SharedValue`.
+    ///
+    /// Arrays and dictionaries held by the same `Arc` are given the same id and appear in full
+    /// only once.
+    pub fn graph_events(&self) -> GraphEvents<'_> {
+        GraphEvents {
+            root: Some(self),
+            stack: Vec::new(),
+            ids: HashMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Converts a `SharedValue` into a `Value`, copying each shared array and dictionary into
+    /// every parent.
+    pub fn to_value(&self) -> Value {
+        match self {
+            SharedValue::Array(array) => Value::Array(array.iter().map(Self::to_value).collect()),
+            SharedValue::Dictionary(entries) => {
+                let mut dict = Dictionary::new();
+                for (key, value) in entries.iter() {
+                    dict.insert(key.clone(), value.to_value());
+                }
+                Value::Dictionary(dict)
+            }
+            SharedValue::Value(value) => Value::clone(value),
+        }
+    }
+}
+
+impl From<Value> for SharedValue {
+    fn from(value: Value) -> SharedValue {
+        match value {
+            Value::Array(array) => {
+                SharedValue::Array(Arc::new(array.into_iter().map(Into::into).collect()))
+            }
+            Value::Dictionary(dict) => SharedValue::Dictionary(Arc::new(
+                dict.into_iter().map(|(k, v)| (k, v.into())).collect(),
+            )),
+            value => SharedValue::Value(Arc::new(value)),
+        }
+    }
+}
+
+/// Builds a `SharedValue` from `GraphEvent`s pushed one at a time.
+pub(crate) struct GraphBuilder {
+    validator: EventValidator,
+    /// The objects read so far by id. Collections and values are both held behind an `Arc`, so
+    /// recording them here does not copy them.
+    objects: HashMap<u64, SharedValue>,
+    stack: Vec<(u64, Partial)>,
+}
+
+impl GraphBuilder {
+    pub fn new() -> GraphBuilder {
+        GraphBuilder {
+            validator: EventValidator::new(),
+            objects: HashMap::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    /// Adds the next event to the plist, returning the root value once it is complete.
+    pub fn push(&mut self, event: GraphEvent<'_>) -> Result<Option<SharedValue>, Error> {
+        let (id, value) = match event {
+            GraphEvent::Object { id, event } => {
+                self.validator.validate(EventKind::of_event(&event))?;
+                let value = match event {
+                    Event::StartArray(len) => {
+                        self.stack.push((id, Partial::Array(with_capacity(len))));
+                        return Ok(None);
+                    }
+                    Event::StartDictionary(len) => {
+                        let entries = with_capacity(len);
+                        self.stack.push((id, Partial::Dictionary(entries, None)));
+                        return Ok(None);
+                    }
+                    Event::EndCollection => {
+                        // Rejected by the validator.
+                        unreachable!("`EndCollection` is not the start of an object")
+                    }
+                    Event::Boolean(b) => Value::Boolean(b),
+                    Event::Data(d) => Value::Data(d.into_owned()),
+                    Event::Date(d) => Value::Date(d),
+                    Event::Integer(i) => Value::Integer(i),
+                    Event::Real(f) => Value::Real(f),
+                    Event::String(s) => Value::String(s.into_owned()),
+                    Event::Uid(u) => Value::Uid(u),
+                };
+                (id, SharedValue::Value(Arc::new(value)))
+            }
+            GraphEvent::EndCollection => {
+                self.validator.validate(EventKind::EndCollection)?;
+                let (id, partial) = self
+                    .stack
+                    .pop()
+                    .expect("the validator only accepts the end of an open collection");
+                (id, partial.into_value())
+            }
+            GraphEvent::Reference(id) => {
+                let value = self
+                    .objects
+                    .get(&id)
+                    .ok_or_else(|| ErrorKind::UnresolvedObjectReference(id).without_position())?
+                    .clone();
+                match &value {
+                    SharedValue::Array(_) => {
+                        self.validator.validate(EventKind::StartArray)?;
+                        self.validator.validate(EventKind::EndCollection)?;
+                    }
+                    SharedValue::Dictionary(_) => {
+                        self.validator.validate(EventKind::StartDictionary)?;
+                        self.validator.validate(EventKind::EndCollection)?;
+                    }
+                    SharedValue::Value(value) => {
+                        for event in value.events() {
+                            self.validator.validate(EventKind::of_event(&event))?;
+                        }
+                    }
+                }
+                (id, value)
+            }
+        };
+
+        self.objects.entry(id).or_insert_with(|| value.clone());
+        match self.stack.last_mut() {
+            Some((_, partial)) => {
+                partial.push(value);
+                Ok(None)
+            }
+            None => Ok(Some(value)),
+        }
+    }
+}
+
+/// An array or dictionary whose contents are being read.
+enum Partial {
+    Array(Vec<SharedValue>),
+    /// The entries read so far and the key of the entry being read.
+    Dictionary(Vec<(String, SharedValue)>, Option<String>),
+}
+
+impl Partial {
+    fn push(&mut self, value: SharedValue) {
+        match self {
+            Partial::Array(values) => values.push(value),
+            Partial::Dictionary(entries, key) => match (key.take(), value) {
+                (Some(key), value) => entries.push((key, value)),
+                (None, SharedValue::Value(value)) => match &*value {
+                    Value::String(s) => *key = Some(s.clone()),
+                    _ => unreachable!("the validator only accepts strings as dictionary keys"),
+                },
+                (None, _) => unreachable!("the validator only accepts strings as dictionary keys"),
+            },
+        }
+    }
+
+    fn into_value(self) -> SharedValue {
+        match self {
+            Partial::Array(values) => SharedValue::Array(Arc::new(values)),
+            Partial::Dictionary(entries, _) => SharedValue::Dictionary(Arc::new(entries)),
+        }
+    }
+}
+
+fn with_capacity<T>(len: Option<u64>) -> Vec<T> {
+    match len.and_then(u64_to_usize) {
+        Some(len) => Vec::with_capacity(len),
+        None => Vec::new(),
+    }
+}
+
+/// A `GraphEvent` iterator returned by `SharedValue::graph_events`.
+pub struct GraphEvents<'a> {
+    root: Option<&'a SharedValue>,
+    stack: Vec<StackItem<'a>>,
+    /// The ids of the arrays and dictionaries which have been started, keyed by address.
+    ids: HashMap<*const (), u64>,
+    next_id: u64,
+}
+
+enum StackItem<'a> {
+    Array(std::slice::Iter<'a, SharedValue>),
+    Dictionary(std::slice::Iter<'a, (String, SharedValue)>),
+    DictionaryValue(&'a SharedValue, std::slice::Iter<'a, (String, SharedValue)>),
+    /// The remaining events of an array or dictionary held in `SharedValue::Value` and the number
+    /// of its collections which are open.
+    Value(Events<'a>, usize),
+}
+
+impl<'a> GraphEvents<'a> {
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn start(&mut self, value: &'a SharedValue) -> GraphEvent<'a> {
+        let (address, event, item) = match value {
+            SharedValue::Array(array) => (
+                Arc::as_ptr(array) as *const (),
+                Event::StartArray(Some(array.len() as u64)),
+                StackItem::Array(array.iter()),
+            ),
+            SharedValue::Dictionary(entries) => (
+                Arc::as_ptr(entries) as *const (),
+                Event::StartDictionary(Some(entries.len() as u64)),
+                StackItem::Dictionary(entries.iter()),
+            ),
+            SharedValue::Value(value) => {
+                let mut events = value.events();
+                let event = events.next().expect("a value has at least one event");
+                if let Event::StartArray(_) | Event::StartDictionary(_) = event {
+                    self.stack.push(StackItem::Value(events, 1));
+                }
+                return GraphEvent::Object {
+                    id: self.next_id(),
+                    event,
+                };
+            }
+        };
+
+        if let Some(&id) = self.ids.get(&address) {
+            return GraphEvent::Reference(id);
+        }
+        let id = self.next_id();
+        self.ids.insert(address, id);
+        self.stack.push(item);
+        GraphEvent::Object { id, event }
+    }
+}
+
+impl<'a> Iterator for GraphEvents<'a> {
+    type Item = GraphEvent<'a>;
+
+    fn next(&mut self) -> Option<GraphEvent<'a>> {
+        if let Some(root) = self.root.take() {
+            return Some(self.start(root));
+        }
+
+        let value = match self.stack.pop()? {
+            StackItem::Array(mut values) => match values.next() {
+                Some(value) => {
+                    self.stack.push(StackItem::Array(values));
+                    value
+                }
+                None => return Some(GraphEvent::EndCollection),
+            },
+            StackItem::Dictionary(mut entries) => match entries.next() {
+                Some((key, value)) => {
+                    self.stack.push(StackItem::DictionaryValue(value, entries));
+                    return Some(GraphEvent::Object {
+                        id: self.next_id(),
+                        event: Event::String(Cow::Borrowed(key)),
+                    });
+                }
+                None => return Some(GraphEvent::EndCollection),
+            },
+            StackItem::DictionaryValue(value, entries) => {
+                self.stack.push(StackItem::Dictionary(entries));
+                value
+            }
+            StackItem::Value(mut events, depth) => {
+                let event = events
+                    .next()
+                    .expect("the events of a value end with its `EndCollection`");
+                let depth = match event {
+                    Event::StartArray(_) | Event::StartDictionary(_) => depth + 1,
+                    Event::EndCollection => depth - 1,
+                    _ => depth,
+                };
+                if depth > 0 {
+                    self.stack.push(StackItem::Value(events, depth));
+                }
+                return Some(match event {
+                    Event::EndCollection => GraphEvent::EndCollection,
+                    event => GraphEvent::Object {
+                        id: self.next_id(),
+                        event,
+                    },
+                });
+            }
+        };
+        Some(self.start(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bplist,
+        stream::{CanonicalWriter, ValueWriter},
+    };
+    use std::io::Cursor;
+
+    /// Builds an array nested `depth` deep in which each level holds the next level twice.
+    fn doubling_array(depth: usize) -> SharedValue {
+        let mut value = SharedValue::Value(Arc::new(Value::String("leaf".to_owned())));
+        for _ in 0..depth {
+            value = SharedValue::Array(Arc::new(vec![value.clone(), value]));
+        }
+        value
+    }
+
+    #[test]
+    fn shared_collections_roundtrip() {
+        let value = doubling_array(40);
+        let mut bytes = Vec::new();
+        value.to_writer_binary(&mut bytes).unwrap();
+
+        // One array per level and the leaf string.
+        let inspection = bplist::inspect(&bytes).unwrap();
+        assert_eq!(inspection.trailer.num_objects, 41);
+        assert!(inspection.issues.is_empty());
+
+        let read = SharedValue::from_reader_binary(Cursor::new(&bytes)).unwrap();
+        let mut level = &read;
+        for _ in 0..40 {
+            let array = match level {
+                SharedValue::Array(array) => array,
+                _ => panic!("expected an array"),
+            };
+            assert_eq!(array.len(), 2);
+            match (&array[0], &array[1]) {
+                (SharedValue::Array(a), SharedValue::Array(b)) => assert!(Arc::ptr_eq(a, b)),
+                (SharedValue::Value(a), SharedValue::Value(b)) => assert!(Arc::ptr_eq(a, b)),
+                _ => panic!("expected both elements to be the same"),
+            }
+            level = &array[0];
+        }
+    }
+
+    #[test]
+    fn from_value() {
+        let value = Value::from_file("tests/data/binary.plist").unwrap();
+        let shared = SharedValue::from(value.clone());
+        assert_eq!(shared.to_value(), value);
+
+        let mut bytes = Vec::new();
+        shared.to_writer_binary(&mut bytes).unwrap();
+        assert_eq!(Value::from_reader(Cursor::new(bytes)).unwrap(), value);
+    }
+
+    #[test]
+    fn collections_in_values() {
+        let mut dict = Dictionary::new();
+        dict.insert(
+            "a".to_owned(),
+            Value::Array(vec![1.into(), Value::Array(vec![])]),
+        );
+        dict.insert("b".to_owned(), true.into());
+        let nested = SharedValue::Value(Arc::new(Value::Dictionary(dict)));
+        let shared = SharedValue::Array(Arc::new(vec![
+            nested.clone(),
+            Value::from("c").into(),
+            nested,
+        ]));
+
+        let mut bytes = Vec::new();
+        shared.to_writer_binary(&mut bytes).unwrap();
+        let expected = shared.to_value();
+        assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), expected);
+
+        let read = SharedValue::from_graph_events(shared.graph_events().map(Ok)).unwrap();
+        assert_eq!(read.to_value(), expected);
+    }
+
+    #[test]
+    fn unresolved_references() {
+        let events = vec![
+            Ok(GraphEvent::Object {
+                id: 0,
+                event: Event::StartArray(None),
+            }),
+            Ok(GraphEvent::Reference(0)),
+        ];
+        let err = SharedValue::from_graph_events(events).unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::UnresolvedObjectReference(0)
+        ));
+
+        let mut writer = BinaryWriter::new(Vec::new());
+        writer
+            .write_graph_event(&GraphEvent::Object {
+                id: 0,
+                event: Event::StartArray(None),
+            })
+            .unwrap();
+        let err = writer
+            .write_graph_event(&GraphEvent::Reference(0))
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::RecursiveObject));
+        let err = writer
+            .write_graph_event(&GraphEvent::Reference(1))
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::UnresolvedObjectReference(1)
+        ));
+    }
+
+    #[test]
+    fn canonical_writer_expands_references() {
+        let mut shared = doubling_array(3);
+        if let SharedValue::Array(array) = &mut shared {
+            let key = SharedValue::Value(Arc::new(Value::String("key".to_owned())));
+            let entries = vec![("b".to_owned(), key.clone()), ("a".to_owned(), key)];
+            Arc::make_mut(array).push(SharedValue::Dictionary(Arc::new(entries)));
+        }
+
+        let mut writer = CanonicalWriter::new(ValueWriter::new());
+        for event in shared.graph_events() {
+            writer.write_graph_event(&event).unwrap();
+        }
+        let mut expected = shared.to_value();
+        expected.canonicalize();
+        assert_eq!(writer.into_inner().take_value(), Some(expected));
+
+        let mut writer = CanonicalWriter::new(ValueWriter::new());
+        let err = writer
+            .write_graph_event(&GraphEvent::Reference(0))
+            .unwrap_err();
+        assert!(matches!(
+            err.kind(),
+            ErrorKind::UnresolvedObjectReference(0)
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+use std::{fmt::Write as _, io::Write};
+
+use crate::{
+    error::{Error, EventKind},
+    stream::{TextWriter, Writer},
+    Date, Integer, Uid,
+};
+
+/// Writes events as an old-style ASCII (OpenStep) plist.
+///
+/// ```text
+/// {
+///     Author = "William Shakespeare";
+///     Lines = (
+///         "It is a tale told by an idiot,",
+///         "Full of sound and fury, signifying nothing."
+///     );
+///     Data = <0000be00>;
+/// }
+/// ```
+///
+/// The format only has strings, data, arrays and dictionaries. Booleans are written as `YES` or
+/// `NO`, numbers and dates as strings and uids as `{CF$UID = n;}` dictionaries. Each plist is
+/// followed by a line ending.
+pub struct AsciiWriter<W: Write> {
+    writer: TextWriter<W>,
+}
+
+impl<W: Write> AsciiWriter<W> {
+    /// Creates an `AsciiWriter` which writes plists using tab indentation.
+    pub fn new(writer: W) -> AsciiWriter<W> {
+        AsciiWriter {
+            writer: TextWriter::new(writer, "\t"),
+        }
+    }
+
+    /// Consumes the `AsciiWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    fn write_value(&mut self, kind: EventKind, text: &str) -> Result<(), Error> {
+        self.writer.validator.validate(kind)?;
+        self.writer.start_array_element(",")?;
+        self.writer.write_str(text)?;
+        self.writer.end_value(";")
+    }
+
+    fn start_collection(&mut self, dictionary: bool) -> Result<(), Error> {
+        self.writer.validator.validate(if dictionary {
+            EventKind::StartDictionary
+        } else {
+            EventKind::StartArray
+        })?;
+        self.writer.start_array_element(",")?;
+        self.writer
+            .push_collection(dictionary, if dictionary { "{" } else { "(" })
+    }
+}
+
+impl<W: Write> Writer for AsciiWriter<W> {
+    fn write_start_array(&mut self, _len: Option<u64>) -> Result<(), Error> {
+        self.start_collection(false)
+    }
+
+    fn write_start_dictionary(&mut self, _len: Option<u64>) -> Result<(), Error> {
+        self.start_collection(true)
+    }
+
+    fn write_end_collection(&mut self) -> Result<(), Error> {
+        self.writer.validator.validate(EventKind::EndCollection)?;
+        self.writer.pop_collection(")", "}")?;
+        self.writer.end_value(";")
+    }
+
+    fn write_boolean(&mut self, value: bool) -> Result<(), Error> {
+        self.write_value(EventKind::Boolean, if value { "YES" } else { "NO" })
+    }
+
+    fn write_data(&mut self, value: &[u8]) -> Result<(), Error> {
+        let mut text = String::with_capacity(2 + 2 * value.len());
+        text.push('<');
+        for byte in value {
+            write!(text, "{:02x}", byte).expect("writing to a string cannot fail");
+        }
+        text.push('>');
+        self.write_value(EventKind::Data, &text)
+    }
+
+    fn write_date(&mut self, value: Date) -> Result<(), Error> {
+        self.write_value(EventKind::Date, &quote(&value.to_rfc3339()))
+    }
+
+    fn write_integer(&mut self, value: Integer) -> Result<(), Error> {
+        self.write_value(EventKind::Integer, &value.to_string())
+    }
+
+    fn write_real(&mut self, value: f64) -> Result<(), Error> {
+        self.write_value(EventKind::Real, &value.to_string())
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<(), Error> {
+        if !self.writer.validator.expecting_key() {
+            return self.write_value(EventKind::String, &quote(value));
+        }
+
+        self.writer.validator.validate(EventKind::String)?;
+        self.writer.start_key()?;
+        self.writer.write_str(&quote(value))?;
+        self.writer.write_str(" = ")
+    }
+
+    fn write_uid(&mut self, value: Uid) -> Result<(), Error> {
+        self.write_value(EventKind::Uid, &format!("{{CF$UID = {};}}", value.get()))
+    }
+}
+
+/// Quotes a string unless it consists only of characters permitted in an unquoted string.
+fn quote(value: &str) -> String {
+    let unquoted = |c: char| c.is_ascii_alphanumeric() || "_$+/:.-".contains(c);
+    if !value.is_empty() && value.chars().all(unquoted) {
+        return value.to_owned();
+    }
+
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\U{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dictionary, Value};
+
+    #[test]
+    fn openstep_style() {
+        let mut inner = Dictionary::new();
+        inner.insert("uid".to_owned(), Value::Uid(Uid::new(7)));
+        inner.insert("empty".to_owned(), Value::Array(Vec::new()));
+
+        let mut dict = Dictionary::new();
+        dict.insert("name".to_owned(), "Say \"hi\"\n".into());
+        dict.insert("plain/path.txt".to_owned(), "".into());
+        dict.insert("n".to_owned(), (-3).into());
+        dict.insert("ok".to_owned(), true.into());
+        dict.insert("data".to_owned(), Value::Data(vec![0x0f, 0xbd]));
+        dict.insert(
+            "list".to_owned(),
+            Value::Array(vec!["a b".into(), Value::Dictionary(inner)]),
+        );
+
+        let mut output = Vec::new();
+        let mut writer = AsciiWriter::new(&mut output);
+        for event in Value::Dictionary(dict).events() {
+            writer.write(&event).unwrap();
+        }
+        let expected = "{
+\tname = \"Say \\\"hi\\\"\\n\";
+\tplain/path.txt = \"\";
+\tn = -3;
+\tok = YES;
+\tdata = <0fbd>;
+\tlist = (
+\t\t\"a b\",
+\t\t{
+\t\t\tuid = {CF$UID = 7;};
+\t\t\tempty = ();
+\t\t}
+\t);
+}
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -1,12 +1,14 @@
 use std::{
-    io::{self, Read, Seek, SeekFrom},
+    borrow::Cow,
+    io::{self, Cursor, Read, Seek, SeekFrom},
     mem::size_of,
+    str,
 };
 
 use crate::{
     date::{Date, InfiniteOrNanDate},
-    error::{Error, ErrorKind},
-    stream::Event,
+    error::{Error, ErrorKind, FilePosition},
+    stream::{Event, EventPosition, GraphEvent, Limiter, OwnedEvent, ReaderOptions},
     u64_to_usize, Uid,
 };
 
@@ -21,24 +23,59 @@ enum StackType {
     Dict,
 }
 
+/// Reads events from a binary encoded plist.
+///
+/// A `BinaryReader` created with `from_slice` borrows ASCII strings and data from the slice
+/// instead of copying them.
+///
+/// An object referenced from several places is read again at each reference. `into_graph`
+/// returns a reader which instead reads each object once.
 // https://opensource.apple.com/source/CF/CF-550/CFBinaryPList.c
 // https://hg.python.org/cpython/file/3.4/Lib/plistlib.py
 pub struct BinaryReader<R> {
     stack: Vec<StackItem>,
     object_offsets: Vec<u64>,
     object_on_stack: Vec<bool>,
+    /// Whether each object has been read.
+    object_read: Vec<bool>,
+    /// The object reference of the most recently read object.
+    object_ref: Option<u64>,
+    graph: bool,
     reader: PosReader<R>,
     ref_size: u8,
     root_object: u64,
     trailer_start_offset: u64,
+    event_offset: Option<u64>,
+    limiter: Limiter,
+    recover_errors: bool,
+    finished: bool,
 }
 
-struct PosReader<R> {
+pub struct PosReader<R> {
     reader: R,
     pos: u64,
 }
 
-impl<R: Read + Seek> PosReader<R> {
+/// A byte slice containing a binary encoded plist. Created by `BinaryReader::from_slice`.
+pub struct BorrowedBytes<'a> {
+    cursor: Cursor<&'a [u8]>,
+}
+
+/// The source of the bytes read by a `BinaryReader`. Strings and data can be borrowed from the
+/// source for `'a`.
+// `pub` but unreachable from outside the crate as this module is private.
+pub trait Source<'a> {
+    fn pos(&self) -> u64;
+    fn read_all(&mut self, buf: &mut [u8]) -> Result<(), Error>;
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error>;
+    fn read_bytes(&mut self, len: usize) -> Result<Cow<'a, [u8]>, Error>;
+}
+
+impl<R: Read + Seek> Source<'static> for PosReader<R> {
+    fn pos(&self) -> u64 {
+        self.pos
+    }
+
     fn read_all(&mut self, buf: &mut [u8]) -> Result<(), Error> {
         self.read_exact(buf)
             .map_err(|err| ErrorKind::Io(err).with_byte_offset(self.pos))?;
@@ -52,53 +89,177 @@ impl<R: Read + Seek> PosReader<R> {
             .map_err(|err| ErrorKind::Io(err).with_byte_offset(self.pos))?;
         Ok(self.pos)
     }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Cow<'static, [u8]>, Error> {
+        let mut data = vec![0; len];
+        self.read_all(&mut data)?;
+        Ok(Cow::Owned(data))
+    }
 }
 
 impl<R: Read> Read for PosReader<R> {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
         let count = self.reader.read(buf)?;
-        self.pos
+        self.pos = self
+            .pos
             .checked_add(count as u64)
-            .expect("file cannot be larger than `u64::max_value()` bytes");
+            .expect("file cannot be larger than `u64::MAX` bytes");
         Ok(count)
     }
 }
 
+impl<'a> Source<'a> for PosReader<BorrowedBytes<'a>> {
+    fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    fn read_all(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.reader
+            .cursor
+            .read_exact(buf)
+            .map_err(|err| ErrorKind::Io(err).with_byte_offset(self.pos))?;
+        self.pos = self.reader.cursor.position();
+        Ok(())
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64, Error> {
+        self.pos = self
+            .reader
+            .cursor
+            .seek(pos)
+            .map_err(|err| ErrorKind::Io(err).with_byte_offset(self.pos))?;
+        Ok(self.pos)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Cow<'a, [u8]>, Error> {
+        let bytes: &'a [u8] = self.reader.cursor.get_ref();
+        let start = self.reader.cursor.position();
+        let end = start.checked_add(len as u64);
+        match end.and_then(|end| bytes.get(u64_to_usize(start)?..u64_to_usize(end)?)) {
+            Some(data) => {
+                self.pos = start + len as u64;
+                self.reader.cursor.set_position(self.pos);
+                Ok(Cow::Borrowed(data))
+            }
+            None => {
+                Err(ErrorKind::Io(io::ErrorKind::UnexpectedEof.into()).with_byte_offset(self.pos))
+            }
+        }
+    }
+}
+
 impl<R: Read + Seek> BinaryReader<R> {
+    /// Creates a `BinaryReader` reading from `reader`.
     pub fn new(reader: R) -> BinaryReader<R> {
+        BinaryReader::new_with_options(reader, ReaderOptions::new())
+    }
+
+    /// Creates a `BinaryReader` reading from `reader` which stops with an error if the plist
+    /// exceeds the limits in `options`.
+    pub fn new_with_options(reader: R, options: ReaderOptions) -> BinaryReader<R> {
+        BinaryReader::from_source(reader, options)
+    }
+}
+
+impl<'a> BinaryReader<BorrowedBytes<'a>> {
+    /// Creates a `BinaryReader` reading from `bytes` which borrows ASCII strings and data from
+    /// `bytes` instead of copying them.
+    pub fn from_slice(bytes: &'a [u8]) -> BinaryReader<BorrowedBytes<'a>> {
+        BinaryReader::from_slice_with_options(bytes, ReaderOptions::new())
+    }
+
+    /// Creates a `BinaryReader` reading from `bytes`, as in `from_slice`, which stops with an
+    /// error if the plist exceeds the limits in `options`.
+    pub fn from_slice_with_options(
+        bytes: &'a [u8],
+        options: ReaderOptions,
+    ) -> BinaryReader<BorrowedBytes<'a>> {
+        BinaryReader::from_source(
+            BorrowedBytes {
+                cursor: Cursor::new(bytes),
+            },
+            options,
+        )
+    }
+}
+
+impl<R> BinaryReader<R> {
+    fn from_source(reader: R, options: ReaderOptions) -> BinaryReader<R> {
         BinaryReader {
             stack: Vec::new(),
             object_offsets: Vec::new(),
             object_on_stack: Vec::new(),
+            object_read: Vec::new(),
+            object_ref: None,
+            graph: false,
             reader: PosReader { reader, pos: 0 },
             ref_size: 0,
             root_object: 0,
             trailer_start_offset: 0,
+            event_offset: None,
+            limiter: Limiter::new(options),
+            recover_errors: false,
+            finished: false,
         }
     }
 
+    /// Returns the object reference of the object which produced the most recently returned event,
+    /// or `None` if it was an `EndCollection` event.
+    ///
+    /// Events with the same object reference were read from the same object.
+    pub fn object_ref(&self) -> Option<u64> {
+        self.object_ref
+    }
+
+    /// Returns a reader which reads each object only once.
+    ///
+    /// Later references to an object are returned as `GraphEvent::Reference` instead of
+    /// repeating the object's events, so that plists which share arrays and dictionaries between
+    /// many parents are read in time proportional to their size. Object ids are the object
+    /// references used in the plist.
+    pub fn into_graph(mut self) -> GraphReader<R> {
+        self.graph = true;
+        GraphReader { reader: self }
+    }
+
+    /// Continues reading after an invalid object instead of finishing. The invalid object is
+    /// reported as an error and then skipped. Errors in the trailer or offset table still finish
+    /// the plist.
+    pub(crate) fn set_recover_errors(&mut self, recover_errors: bool) {
+        self.recover_errors = recover_errors;
+    }
+}
+
+impl<'a, R> BinaryReader<R>
+where
+    PosReader<R>: Source<'a>,
+{
     fn allocate_vec<T>(&self, len: u64, size: usize) -> Result<Vec<T>, Error> {
-        // Check we are not reading past the start of the plist trailer
+        self.check_within_object_table(len, size)?;
+        Ok(Vec::with_capacity(len as usize))
+    }
+
+    /// Checks that reading `len` items of `size` bytes does not read past the start of the plist
+    /// trailer.
+    fn check_within_object_table(&self, len: u64, size: usize) -> Result<(), Error> {
         let inner = |len: u64, size: usize| {
             let byte_len = len.checked_mul(size as u64)?;
-            let end_offset = self.reader.pos.checked_add(byte_len)?;
+            let end_offset = self.reader.pos().checked_add(byte_len)?;
             if end_offset <= self.trailer_start_offset {
                 Some(())
             } else {
                 None
             }
         };
-        inner(len, size).ok_or_else(|| self.with_pos(ErrorKind::ObjectOffsetTooLarge))?;
-
-        Ok(Vec::with_capacity(len as usize))
+        inner(len, size).ok_or_else(|| self.with_pos(ErrorKind::ObjectOffsetTooLarge))
     }
 
     fn read_trailer(&mut self) -> Result<(), Error> {
         self.reader.seek(SeekFrom::Start(0))?;
         let mut magic = [0; 8];
         self.reader.read_all(&mut magic)?;
-        if &magic != b"bplist00" {
-            return Err(self.with_pos(ErrorKind::InvalidMagic));
+        if !is_binary_magic(&magic) {
+            return Err(ErrorKind::InvalidMagic.with_byte_offset(0));
         }
 
         self.trailer_start_offset = self.reader.seek(SeekFrom::End(-32))?;
@@ -127,6 +288,7 @@ impl<R: Read + Seek> BinaryReader<R> {
         self.reader.seek(SeekFrom::Start(offset_table_offset))?;
         self.object_offsets = self.read_ints(num_objects, offset_size)?;
         self.object_on_stack = vec![false; self.object_offsets.len()];
+        self.object_read = vec![false; self.object_offsets.len()];
 
         Ok(())
     }
@@ -169,12 +331,10 @@ impl<R: Read + Seek> BinaryReader<R> {
         }
     }
 
-    /// Reads `len` bytes from the reader.
-    fn read_data(&mut self, len: u64) -> Result<Vec<u8>, Error> {
-        let mut data = self.allocate_vec(len, size_of::<u8>())?;
-        data.resize(len as usize, 0);
-        self.reader.read_all(&mut data)?;
-        Ok(data)
+    /// Reads `len` bytes from the reader, borrowing them if possible.
+    fn read_data(&mut self, len: u64) -> Result<Cow<'a, [u8]>, Error> {
+        self.check_within_object_table(len, size_of::<u8>())?;
+        self.reader.read_bytes(len as usize)
     }
 
     fn seek_to_object(&mut self, object_ref: u64) -> Result<u64, Error> {
@@ -187,7 +347,7 @@ impl<R: Read + Seek> BinaryReader<R> {
         if offset >= self.trailer_start_offset {
             return Err(self.with_pos(ErrorKind::ObjectOffsetTooLarge));
         }
-        Ok(self.reader.seek(SeekFrom::Start(offset))?)
+        self.reader.seek(SeekFrom::Start(offset))
     }
 
     fn push_stack_item_and_check_for_recursion(&mut self, item: StackItem) -> Result<(), Error> {
@@ -208,7 +368,7 @@ impl<R: Read + Seek> BinaryReader<R> {
         item
     }
 
-    fn read_next(&mut self) -> Result<Option<Event>, Error> {
+    fn read_next(&mut self) -> Result<Option<GraphEvent<'a>>, Error> {
         let object_ref = if self.ref_size == 0 {
             // Initialise here rather than in new
             self.read_trailer()?;
@@ -226,13 +386,23 @@ impl<R: Read + Seek> BinaryReader<R> {
             } else {
                 // We're at the end of an array or dict. Pop the top stack item and return.
                 let stack_item = self.pop_stack_item();
+                self.object_ref = None;
                 match stack_item.ty {
-                    StackType::Array | StackType::Dict => return Ok(Some(Event::EndCollection)),
+                    StackType::Array | StackType::Dict => {
+                        return Ok(Some(GraphEvent::EndCollection))
+                    }
                 }
             }
         };
 
-        self.seek_to_object(object_ref)?;
+        self.event_offset = Some(self.seek_to_object(object_ref)?);
+        self.object_ref = Some(object_ref);
+
+        // `seek_to_object` has checked that `object_ref` is in the offset table.
+        let index = object_ref as usize;
+        if self.graph && self.object_read[index] && !self.object_on_stack[index] {
+            return Ok(Some(GraphEvent::Reference(object_ref)));
+        }
 
         let token = self.read_u8()?;
         let ty = (token & 0xf0) >> 4;
@@ -249,7 +419,7 @@ impl<R: Read + Seek> BinaryReader<R> {
             (0x1, 3) => Some(Event::Integer(self.read_be_i64()?.into())),
             (0x1, 4) => {
                 let value = self.read_be_i128()?;
-                if value < 0 || value > u64::max_value().into() {
+                if value < 0 || value > u64::MAX.into() {
                     return Err(self.with_pos(ErrorKind::IntegerOutOfRange));
                 }
                 Some(Event::Integer((value as u64).into()))
@@ -268,19 +438,25 @@ impl<R: Read + Seek> BinaryReader<R> {
             (0x4, n) => {
                 // Data
                 let len = self.read_object_len(n)?;
+                self.check_limit(self.limiter.check_data_len(len))?;
                 Some(Event::Data(self.read_data(len)?))
             }
             (0x5, n) => {
                 // ASCII string
                 let len = self.read_object_len(n)?;
-                let raw = self.read_data(len)?;
-                let string = String::from_utf8(raw)
-                    .map_err(|_| self.with_pos(ErrorKind::InvalidUtf8String))?;
+                self.check_limit(self.limiter.check_string_len(len))?;
+                let string = match self.read_data(len)? {
+                    Cow::Borrowed(raw) => str::from_utf8(raw).map(Cow::Borrowed).ok(),
+                    Cow::Owned(raw) => String::from_utf8(raw).map(Cow::Owned).ok(),
+                };
+                let string = string.ok_or_else(|| self.with_pos(ErrorKind::InvalidUtf8String))?;
                 Some(Event::String(string))
             }
             (0x6, n) => {
                 // UTF-16 string
                 let len_utf16_codepoints = self.read_object_len(n)?;
+                // Each UTF-16 code unit is at least one byte when decoded as UTF-8.
+                self.check_limit(self.limiter.check_string_len(len_utf16_codepoints))?;
                 let mut raw_utf16 = self.allocate_vec(len_utf16_codepoints, size_of::<u16>())?;
 
                 for _ in 0..len_utf16_codepoints {
@@ -289,7 +465,7 @@ impl<R: Read + Seek> BinaryReader<R> {
 
                 let string = String::from_utf16(&raw_utf16)
                     .map_err(|_| self.with_pos(ErrorKind::InvalidUtf16String))?;
-                Some(Event::String(string))
+                Some(Event::String(Cow::Owned(string)))
             }
             (0x8, n) if n < 8 => {
                 // Uid
@@ -347,7 +523,11 @@ impl<R: Read + Seek> BinaryReader<R> {
             (_, _) => return Err(self.with_pos(ErrorKind::UnknownObjectType(token))),
         };
 
-        Ok(result)
+        self.object_read[index] = true;
+        Ok(result.map(|event| GraphEvent::Object {
+            id: object_ref,
+            event,
+        }))
     }
 
     fn read_u8(&mut self) -> Result<u8, Error> {
@@ -387,19 +567,52 @@ impl<R: Read + Seek> BinaryReader<R> {
     }
 
     fn with_pos(&self, kind: ErrorKind) -> Error {
-        kind.with_byte_offset(self.reader.pos)
+        kind.with_byte_offset(self.reader.pos())
     }
-}
 
-impl<R: Read + Seek> Iterator for BinaryReader<R> {
-    type Item = Result<Event, Error>;
+    fn check_limit(&self, result: Result<(), ErrorKind>) -> Result<(), Error> {
+        result.map_err(|kind| self.with_pos(kind))
+    }
+
+    /// Records an event in the limiter, returning an error if it exceeds a limit.
+    fn check_graph_event(&mut self, event: &GraphEvent<'_>) -> Result<(), Error> {
+        let result = match event {
+            GraphEvent::Object { event, .. } => self.limiter.check_event(event),
+            GraphEvent::EndCollection => self.limiter.check_event(&Event::EndCollection),
+            GraphEvent::Reference(_) => self.limiter.check_object(),
+        };
+        result.map_err(|kind| kind.with_byte_offset(self.event_offset.unwrap_or(0)))
+    }
+
+    fn next_event(&mut self) -> Option<Result<Event<'a>, Error>> {
+        let event = self.next_graph_event()?.map(|event| match event {
+            GraphEvent::Object { event, .. } => event,
+            GraphEvent::EndCollection => Event::EndCollection,
+            GraphEvent::Reference(_) => {
+                unreachable!("references are only returned by a `GraphReader`")
+            }
+        });
+        Some(event)
+    }
 
-    fn next(&mut self) -> Option<Result<Event, Error>> {
-        match self.read_next() {
+    fn next_graph_event(&mut self) -> Option<Result<GraphEvent<'a>, Error>> {
+        if self.finished {
+            return None;
+        }
+
+        let reading_trailer = self.ref_size == 0;
+        match self.read_next().and_then(|event| match event {
+            Some(event) => self.check_graph_event(&event).map(|()| Some(event)),
+            None => Ok(None),
+        }) {
             Ok(Some(event)) => Some(Ok(event)),
             Err(err) => {
-                // Mark the plist as finished
-                self.stack.clear();
+                // Exceeding a limit always finishes the plist as the input is not trusted.
+                if reading_trailer || !self.recover_errors || err.kind().is_limit_exceeded() {
+                    // Mark the plist as finished
+                    self.stack.clear();
+                    self.finished = true;
+                }
                 Some(Err(err))
             }
             Ok(None) => None,
@@ -407,9 +620,81 @@ impl<R: Read + Seek> Iterator for BinaryReader<R> {
     }
 }
 
+impl<R> EventPosition for BinaryReader<R> {
+    fn event_position(&self) -> Option<FilePosition> {
+        self.event_offset.map(FilePosition::Offset)
+    }
+}
+
+impl<R: Read + Seek> Iterator for BinaryReader<R> {
+    type Item = Result<OwnedEvent, Error>;
+
+    fn next(&mut self) -> Option<Result<OwnedEvent, Error>> {
+        self.next_event()
+    }
+}
+
+impl<'a> Iterator for BinaryReader<BorrowedBytes<'a>> {
+    type Item = Result<Event<'a>, Error>;
+
+    fn next(&mut self) -> Option<Result<Event<'a>, Error>> {
+        self.next_event()
+    }
+}
+
+/// Reads `GraphEvent`s from a binary encoded plist. Created by `BinaryReader::into_graph`.
+pub struct GraphReader<R> {
+    reader: BinaryReader<R>,
+}
+
+impl<R> GraphReader<R> {
+    /// Returns the reference of the object which produced the most recent event, as in
+    /// `BinaryReader::object_ref`.
+    pub fn object_ref(&self) -> Option<u64> {
+        self.reader.object_ref()
+    }
+
+    /// Consumes the `GraphReader`, returning the underlying `BinaryReader`.
+    pub fn into_inner(self) -> BinaryReader<R> {
+        self.reader
+    }
+}
+
+impl<R> EventPosition for GraphReader<R> {
+    fn event_position(&self) -> Option<FilePosition> {
+        self.reader.event_position()
+    }
+}
+
+impl<R: Read + Seek> Iterator for GraphReader<R> {
+    type Item = Result<GraphEvent<'static>, Error>;
+
+    fn next(&mut self) -> Option<Result<GraphEvent<'static>, Error>> {
+        self.reader.next_graph_event()
+    }
+}
+
+impl<'a> Iterator for GraphReader<BorrowedBytes<'a>> {
+    type Item = Result<GraphEvent<'a>, Error>;
+
+    fn next(&mut self) -> Option<Result<GraphEvent<'a>, Error>> {
+        self.reader.next_graph_event()
+    }
+}
+
+/// Returns whether `magic` is the start of a binary plist: `bplist` followed by a two digit format
+/// version starting with `0`, which is what CoreFoundation accepts.
+pub(crate) fn is_binary_magic(magic: &[u8]) -> bool {
+    magic.len() >= 8 && magic.starts_with(b"bplist0") && magic[7].is_ascii_digit()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{fs::File, path::Path};
+    use std::{
+        fs::{self, File},
+        io::Cursor,
+        path::Path,
+    };
 
     use super::*;
     use crate::{stream::Event, Uid};
@@ -418,7 +703,7 @@ mod tests {
     fn streaming_parser() {
         use crate::stream::Event::*;
 
-        let reader = File::open(&Path::new("./tests/data/binary.plist")).unwrap();
+        let reader = File::open(Path::new("./tests/data/binary.plist")).unwrap();
         let streaming_parser = BinaryReader::new(reader);
         let events: Vec<Event> = streaming_parser.map(|e| e.unwrap()).collect();
 
@@ -454,20 +739,64 @@ mod tests {
             String("IsTrue".into()),
             Boolean(true),
             String("Data".into()),
-            Data(vec![0, 0, 0, 190, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0]),
+            Data(vec![0, 0, 0, 190, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0].into()),
             EndCollection,
         ];
 
         assert_eq!(events, &comparison[..]);
     }
 
+    #[test]
+    fn error_byte_offsets() {
+        let mut bytes = fs::read("./tests/data/binary.plist").unwrap();
+        let start = bytes.windows(6).position(|w| w == b"Author").unwrap();
+        bytes[start] = 0xff;
+        // The error is reported after the string has been read.
+        let expected = Some(start as u64 + 6);
+
+        let err = BinaryReader::new(Cursor::new(&bytes))
+            .find_map(Result::err)
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::InvalidUtf8String));
+        assert_eq!(err.byte_offset(), expected);
+
+        let err = BinaryReader::from_slice(&bytes)
+            .find_map(Result::err)
+            .unwrap();
+        assert_eq!(err.byte_offset(), expected);
+    }
+
+    #[test]
+    fn borrowed_slice() {
+        let bytes = fs::read("./tests/data/binary.plist").unwrap();
+        let owned: Vec<Event> = BinaryReader::new(Cursor::new(&bytes))
+            .map(|e| e.unwrap())
+            .collect();
+        let borrowed: Vec<Event> = BinaryReader::from_slice(&bytes)
+            .map(|e| e.unwrap())
+            .collect();
+        assert_eq!(borrowed, owned);
+
+        for event in &borrowed {
+            match event {
+                Event::String(value) => assert!(matches!(value, Cow::Borrowed(_))),
+                Event::Data(value) => assert!(matches!(value, Cow::Borrowed(_))),
+                _ => (),
+            }
+        }
+
+        let mut truncated = BinaryReader::from_slice(&bytes[..bytes.len() - 8]);
+        assert!(truncated.next().unwrap().is_err());
+        assert!(truncated.next().is_none());
+    }
+
     #[test]
     fn utf16_plist() {
-        let reader = File::open(&Path::new("./tests/data/utf16_bplist.plist")).unwrap();
+        let reader = File::open(Path::new("./tests/data/utf16_bplist.plist")).unwrap();
         let streaming_parser = BinaryReader::new(reader);
         let mut events: Vec<Event> = streaming_parser.map(|e| e.unwrap()).collect();
 
-        assert_eq!(events[2], Event::String("\u{2605} or better".to_owned()));
+        assert_eq!(events[2], Event::String("\u{2605} or better".into()));
 
         let poem = if let Event::String(ref mut poem) = events[4] {
             poem
@@ -475,12 +804,12 @@ mod tests {
             panic!("not a string")
         };
         assert_eq!(poem.len(), 643);
-        assert_eq!(poem.pop().unwrap(), '\u{2605}');
+        assert_eq!(poem.to_mut().pop().unwrap(), '\u{2605}');
     }
 
     #[test]
     fn nskeyedarchiver_plist() {
-        let reader = File::open(&Path::new("./tests/data/binary_NSKeyedArchiver.plist")).unwrap();
+        let reader = File::open(Path::new("./tests/data/binary_NSKeyedArchiver.plist")).unwrap();
         let streaming_parser = BinaryReader::new(reader);
         let events: Vec<Event> = streaming_parser.map(|e| e.unwrap()).collect();
 
@@ -489,4 +818,124 @@ mod tests {
         assert_eq!(events[18], Event::Uid(Uid::new(3)));
         assert_eq!(events[46], Event::Uid(Uid::new(1)));
     }
+
+    #[test]
+    fn error_byte_offset() {
+        let mut streaming_parser = BinaryReader::new(Cursor::new(&b"notaplist"[..]));
+        let err = streaming_parser.next().unwrap().unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::InvalidMagic));
+        assert_eq!(err.byte_offset(), Some(0));
+        assert_eq!(err.line_column(), None);
+    }
+
+    /// Builds a binary plist with one byte offsets and object references from the given objects.
+    fn build_plist(objects: &[&[u8]]) -> Vec<u8> {
+        let mut bytes = b"bplist00".to_vec();
+        let mut offsets = Vec::new();
+        for object in objects {
+            offsets.push(bytes.len() as u8);
+            bytes.extend_from_slice(object);
+        }
+        let offset_table_offset = bytes.len() as u64;
+        bytes.extend_from_slice(&offsets);
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+        bytes.extend_from_slice(&(objects.len() as u64).to_be_bytes());
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        bytes.extend_from_slice(&offset_table_offset.to_be_bytes());
+        bytes
+    }
+
+    #[test]
+    fn shared_objects() {
+        // [[x], [x], x] where the inner array and string are single shared objects.
+        let bytes = build_plist(&[&[0xa3, 1, 1, 2], &[0xa1, 2], &[0x51, b'x']]);
+
+        let events: Vec<Event> = BinaryReader::from_slice(&bytes)
+            .map(|e| e.unwrap())
+            .collect();
+        assert_eq!(events.len(), 9);
+
+        let mut reader = BinaryReader::from_slice(&bytes).into_graph();
+        let mut events = Vec::new();
+        let mut refs = Vec::new();
+        while let Some(event) = reader.next() {
+            events.push(event.unwrap());
+            refs.push(reader.object_ref());
+        }
+        let object = |id, event| GraphEvent::Object { id, event };
+        assert_eq!(
+            events,
+            [
+                object(0, Event::StartArray(Some(3))),
+                object(1, Event::StartArray(Some(1))),
+                object(2, Event::String("x".into())),
+                GraphEvent::EndCollection,
+                GraphEvent::Reference(1),
+                GraphEvent::Reference(2),
+                GraphEvent::EndCollection,
+            ]
+        );
+        assert_eq!(
+            refs,
+            [Some(0), Some(1), Some(2), None, Some(1), Some(2), None]
+        );
+    }
+
+    #[test]
+    fn recursive_graph() {
+        // [[<self>]]
+        let bytes = build_plist(&[&[0xa1, 1], &[0xa1, 0]]);
+        let err = BinaryReader::from_slice(&bytes)
+            .into_graph()
+            .find_map(Result::err)
+            .unwrap();
+        assert!(matches!(err.kind(), ErrorKind::RecursiveObject));
+    }
+
+    #[test]
+    fn limits() {
+        // [[x, x], [x, x]] where the inner array and string are single shared objects.
+        let bytes = build_plist(&[&[0xa2, 1, 1], &[0xa2, 2, 2], &[0x51, b'x']]);
+        let read = |options: ReaderOptions| {
+            BinaryReader::from_slice_with_options(&bytes, options)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|err| err.kind().is_limit_exceeded())
+        };
+
+        assert_eq!(read(ReaderOptions::new().max_objects(7)).unwrap().len(), 10);
+        assert_eq!(read(ReaderOptions::new().max_objects(6)), Err(true));
+        assert!(read(ReaderOptions::new().max_depth(2)).is_ok());
+        assert_eq!(read(ReaderOptions::new().max_depth(1)), Err(true));
+        assert!(read(ReaderOptions::new().max_total_bytes(4)).is_ok());
+        assert_eq!(read(ReaderOptions::new().max_total_bytes(3)), Err(true));
+
+        // When read as a graph the contents of shared objects are not counted again.
+        let graph =
+            BinaryReader::from_slice_with_options(&bytes, ReaderOptions::new().max_objects(5))
+                .into_graph()
+                .collect::<Result<Vec<_>, _>>();
+        assert_eq!(graph.unwrap().len(), 7);
+
+        // Strings are rejected before they are read.
+        let bytes = build_plist(&[&[0x6f, 0x10, 0x80]]);
+        let err =
+            BinaryReader::from_slice_with_options(&bytes, ReaderOptions::new().max_string_len(64))
+                .next()
+                .unwrap()
+                .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::StringLengthLimitExceeded));
+
+        // Limits are not recoverable errors.
+        let bytes = build_plist(&[&[0xa2, 1, 1], &[0x42, 0, 0]]);
+        let mut reader =
+            BinaryReader::from_slice_with_options(&bytes, ReaderOptions::new().max_data_len(1));
+        reader.set_recover_errors(true);
+        let events: Vec<_> = reader.collect();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[1].as_ref().unwrap_err().kind(),
+            ErrorKind::DataLengthLimitExceeded
+        ));
+    }
 }
@@ -4,6 +4,7 @@
 use indexmap::IndexMap;
 use std::{
     borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
     io::{self, Write},
     mem,
     num::NonZeroUsize,
@@ -11,19 +12,175 @@ use std::{
 
 use crate::{
     error::{self, Error, ErrorKind, EventKind},
-    stream::Writer,
+    stream::{CanonicalWriter, Event as StreamEvent, EventValidator, GraphEvent, Writer},
     Date, Integer, Uid,
 };
 
 use std::collections::hash_map::RandomState;
 
+/// Options controlling how a `BinaryWriter` encodes plists.
+///
+/// The defaults produce the smallest encoding which Apple's parsers read, deduplicating values but
+/// not collections.
+#[derive(Clone, Debug)]
+pub struct BinaryWriteOptions {
+    deduplication: Deduplication,
+    deduplicate_collections: bool,
+    min_object_ref_size: u8,
+    min_offset_size: u8,
+    utf16_strings: bool,
+    reject_large_unsigned: bool,
+    apple_compatible: bool,
+    version: [u8; 2],
+}
+
+/// Which values a `BinaryWriter` writes once and refers to from every occurrence.
+///
+/// New variants may be added in minor releases.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Deduplication {
+    /// Equal values of every type are written once.
+    Values,
+    /// Equal strings, including dictionary keys, are written once. Other values are written at
+    /// each occurrence.
+    Strings,
+    /// Every occurrence of a value is written separately.
+    Off,
+}
+
+impl Default for BinaryWriteOptions {
+    fn default() -> Self {
+        BinaryWriteOptions {
+            deduplication: Deduplication::Values,
+            deduplicate_collections: false,
+            min_object_ref_size: 1,
+            min_offset_size: 1,
+            utf16_strings: false,
+            reject_large_unsigned: false,
+            apple_compatible: false,
+            version: *b"00",
+        }
+    }
+}
+
+impl BinaryWriteOptions {
+    /// Returns the default options.
+    pub fn new() -> BinaryWriteOptions {
+        BinaryWriteOptions::default()
+    }
+
+    /// Sets which values are deduplicated.
+    pub fn deduplication(mut self, deduplication: Deduplication) -> Self {
+        self.deduplication = deduplication;
+        self
+    }
+
+    /// Sets whether arrays and dictionaries are deduplicated in addition to values.
+    ///
+    /// When enabled, each closed array or dictionary with the same elements or entries, in the same
+    /// order, as one written earlier in the same plist is written as a reference to the earlier
+    /// object. This can shrink plists with repeated structures considerably but requires hashing
+    /// the contents of every collection, so it is disabled by default.
+    pub fn deduplicate_collections(mut self, deduplicate_collections: bool) -> Self {
+        self.deduplicate_collections = deduplicate_collections;
+        self
+    }
+
+    /// Sets the minimum size in bytes of the object references in arrays, dictionaries and the
+    /// trailer. Larger references are used if there are too many objects for `size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not 1, 2, 4 or 8.
+    pub fn min_object_ref_size(mut self, size: u8) -> Self {
+        assert!(matches!(size, 1 | 2 | 4 | 8), "size must be 1, 2, 4 or 8");
+        self.min_object_ref_size = size;
+        self
+    }
+
+    /// Sets the minimum size in bytes of the object offsets in the offset table. Larger offsets
+    /// are used if the object table is too large for `size` bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is not 1, 2, 4 or 8.
+    pub fn min_offset_size(mut self, size: u8) -> Self {
+        assert!(matches!(size, 1 | 2 | 4 | 8), "size must be 1, 2, 4 or 8");
+        self.min_offset_size = size;
+        self
+    }
+
+    /// Sets whether every string is encoded as UTF-16. By default strings consisting only of
+    /// ASCII characters are encoded as ASCII.
+    pub fn utf16_strings(mut self, utf16_strings: bool) -> Self {
+        self.utf16_strings = utf16_strings;
+        self
+    }
+
+    /// Sets whether writing an unsigned integer larger than `i64::MAX` fails with
+    /// `ErrorKind::IntegerOutOfRange`, for parsers which only support integers of up to 8 bytes.
+    /// By default such integers are encoded as 16 byte integers, as Apple's parsers expect.
+    pub fn reject_large_unsigned(mut self, reject_large_unsigned: bool) -> Self {
+        self.reject_large_unsigned = reject_large_unsigned;
+        self
+    }
+
+    /// Sets whether objects are numbered and encoded as by CoreFoundation's
+    /// `CFPropertyListCreateData`, so that a plist read from an Apple-produced binary plist is
+    /// written back byte for byte.
+    ///
+    /// Objects are numbered depth first in the order they appear, with the keys of each dictionary
+    /// before its values, and written in that order. Uids are not deduplicated, and object
+    /// references are sized to hold the number of objects.
+    ///
+    /// Dictionary entries are written in the order CoreFoundation enumerates a dictionary created
+    /// from the entries in the order they are given, which is determined by the hashes of the keys.
+    /// Keys whose hashes collide are ordered by when they were inserted, so the output only matches
+    /// CoreFoundation's if colliding keys are given in the same relative order.
+    pub fn apple_compatible(mut self, apple_compatible: bool) -> Self {
+        self.apple_compatible = apple_compatible;
+        self
+    }
+
+    /// Sets the two digit format version written after `bplist` at the start of the plist.
+    /// Defaults to `00`, the only version written by Apple's encoders, although their parsers
+    /// accept any version starting with `0`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `version` is not `0` followed by an ASCII digit.
+    pub fn version(mut self, version: [u8; 2]) -> Self {
+        assert!(
+            version[0] == b'0' && version[1].is_ascii_digit(),
+            "version must be `0` followed by an ASCII digit"
+        );
+        self.version = version;
+        self
+    }
+}
+
+/// Writes events as a binary encoded plist.
+///
+/// Events are buffered until a complete plist has been written as the object table and offset
+/// table can only be written once all objects are known.
 pub struct BinaryWriter<W: Write> {
     writer: PosWriter<W>,
+    validator: EventValidator,
     events: Vec<Event>,
     dictionary_key_events: Vec<usize>,
-    values: IndexMap<Value<'static>, ValueState, RandomState>,
+    /// The values of the plist, keyed by the value and an instance number which is zero for
+    /// deduplicated values and unique otherwise.
+    values: IndexMap<(Value<'static>, usize), ValueState, RandomState>,
     /// Pointers into `events` for each of the currently unclosed `Collection` events.
     collection_stack: Vec<usize>,
+    /// The object reference assigned to each `Collection`, indexed by `Collection::index`.
+    collection_refs: Vec<CollectionRef>,
+    options: BinaryWriteOptions,
+    /// The index of the first closed collection with each type and contents.
+    collections: HashMap<(CollectionType, Vec<ObjectId>), usize>,
+    /// The objects written by `write_graph_event`, indexed by their ids.
+    graph_objects: HashMap<u64, GraphObject>,
     /// The number of `Collection` and unique `Value` events in `events`.
     num_objects: usize,
 }
@@ -71,6 +228,8 @@ enum Event {
     Collection(Collection),
     /// Index of the value in the `values` map.
     Value(usize),
+    /// A further reference to the collection with the given `Collection::index`.
+    Reference(usize),
     /// The number of dictionary keys following this event.
     DictionaryKeys(usize),
 }
@@ -83,10 +242,40 @@ struct Collection {
     len: usize,
     /// The number of events to skip to get to the next element after the collection.
     skip: usize,
-    object_ref: Option<ObjectRef>,
+    /// The position of the collection in `collection_refs`.
+    index: usize,
+}
+
+enum CollectionRef {
+    /// The collection has not been assigned an object reference.
+    Unassigned,
+    Assigned(ObjectRef),
+    /// The collection is identical to the collection with the given index and shares its object
+    /// reference.
+    Duplicate(usize),
+}
+
+/// An object written by `write_graph_event` which may be referenced by later graph events.
+enum GraphObject {
+    Collection {
+        /// The position of the collection in `collection_refs`.
+        index: usize,
+        ty: CollectionType,
+    },
+    /// Index of the value in the `values` map.
+    Value(usize),
 }
 
-#[derive(Eq, PartialEq)]
+/// The identity of a child object, used to find identical collections.
+#[derive(Eq, Hash, PartialEq)]
+enum ObjectId {
+    /// Index of the value in the `values` map.
+    Value(usize),
+    /// Index of the collection in `collection_refs`.
+    Collection(usize),
+}
+
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 enum CollectionType {
     Array,
     Dictionary,
@@ -114,49 +303,173 @@ enum ValueState {
 }
 
 impl<W: Write> BinaryWriter<W> {
+    /// Creates a `BinaryWriter` which deduplicates repeated values.
     pub fn new(writer: W) -> BinaryWriter<W> {
+        BinaryWriter::new_with_options(writer, BinaryWriteOptions::new())
+    }
+
+    /// Creates a `BinaryWriter` using the given options.
+    ///
+    /// ```
+    /// use plist::{
+    ///     stream::{BinaryWriteOptions, BinaryWriter, Deduplication, Writer},
+    ///     Value,
+    /// };
+    ///
+    /// let options = BinaryWriteOptions::new()
+    ///     .deduplication(Deduplication::Strings)
+    ///     .min_object_ref_size(2)
+    ///     .utf16_strings(true);
+    /// let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+    /// for event in Value::Array(vec!["a".into(), "a".into()]).events() {
+    ///     writer.write(&event).unwrap();
+    /// }
+    ///
+    /// let bytes = writer.into_inner();
+    /// assert_eq!(bytes[bytes.len() - 25], 2);
+    /// ```
+    pub fn new_with_options(writer: W, options: BinaryWriteOptions) -> BinaryWriter<W> {
         BinaryWriter {
             writer: PosWriter { writer, pos: 0 },
+            validator: EventValidator::new(),
             events: Vec::new(),
             dictionary_key_events: Vec::new(),
             values: IndexMap::with_capacity_and_hasher(0, <_>::default()),
             collection_stack: Vec::new(),
+            collection_refs: Vec::new(),
+            options,
+            collections: HashMap::new(),
+            graph_objects: HashMap::new(),
             num_objects: 0,
         }
     }
 
-    fn write_start_collection(&mut self, ty: CollectionType) -> Result<(), Error> {
-        if self.expecting_dictionary_key() {
-            let ty_event_kind = match ty {
-                CollectionType::Array => EventKind::StartArray,
-                CollectionType::Dictionary => EventKind::StartDictionary,
-            };
-            return Err(ErrorKind::UnexpectedEventType {
-                expected: EventKind::DictionaryKeyOrEndCollection,
-                found: ty_event_kind,
+    /// Creates a `CanonicalWriter` which writes plists to a `BinaryWriter` in a canonical form.
+    ///
+    /// Each plist is canonicalized with `Value::canonicalize` before being written so that
+    /// dictionary keys are sorted. As objects are numbered in the order they are first
+    /// encountered, semantically equal plists are always written with identical object numbering
+    /// and deduplication.
+    pub fn new_canonical(writer: W) -> CanonicalWriter<BinaryWriter<W>> {
+        CanonicalWriter::new(BinaryWriter::new(writer))
+    }
+
+    /// Consumes the `BinaryWriter`, returning the underlying writer.
+    ///
+    /// Any events of an incomplete plist are discarded.
+    pub fn into_inner(self) -> W {
+        self.writer.writer
+    }
+
+    /// Writes a `GraphEvent`, preserving the sharing of objects.
+    ///
+    /// A `Reference` is written as a reference to the object written for the earlier `Object`
+    /// event with the same id, so arrays and dictionaries shared by several parents are written
+    /// once. Values are deduplicated as usual.
+    ///
+    /// ```
+    /// use plist::stream::{BinaryReader, BinaryWriter};
+    ///
+    /// let bytes = std::fs::read("tests/data/binary.plist").unwrap();
+    /// let mut writer = BinaryWriter::new(Vec::new());
+    /// for event in BinaryReader::from_slice(&bytes).into_graph() {
+    ///     writer.write_graph_event(&event.unwrap()).unwrap();
+    /// }
+    ///
+    /// let copy = plist::Value::from_reader(std::io::Cursor::new(writer.into_inner())).unwrap();
+    /// assert_eq!(copy, plist::Value::from_file("tests/data/binary.plist").unwrap());
+    /// ```
+    pub fn write_graph_event(&mut self, event: &GraphEvent<'_>) -> Result<(), Error> {
+        let (id, event) = match event {
+            GraphEvent::Object { id, event } => (*id, event),
+            GraphEvent::EndCollection => return self.write(&StreamEvent::EndCollection),
+            GraphEvent::Reference(id) => return self.write_reference(*id),
+        };
+
+        let object = match event {
+            StreamEvent::StartArray(_) | StreamEvent::StartDictionary(_) => {
+                let index = self.collection_refs.len();
+                let ty = match event {
+                    StreamEvent::StartArray(_) => CollectionType::Array,
+                    _ => CollectionType::Dictionary,
+                };
+                self.write(event)?;
+                GraphObject::Collection { index, ty }
+            }
+            StreamEvent::EndCollection => {
+                return Err(error::unexpected_event_type(
+                    EventKind::ValueOrStartCollection,
+                    event,
+                ))
             }
-            .without_position());
+            _ => {
+                let value = Value::from_event(event).expect("collection events are handled above");
+                GraphObject::Value(self.write_value_and_get_index(value)?)
+            }
+        };
+
+        // A plist which consists of a single value has already been written.
+        if !self.collection_stack.is_empty() {
+            self.graph_objects.insert(id, object);
         }
+        Ok(())
+    }
+
+    fn write_reference(&mut self, id: u64) -> Result<(), Error> {
+        let unresolved = || ErrorKind::UnresolvedObjectReference(id).without_position();
+
+        match *self.graph_objects.get(&id).ok_or_else(unresolved)? {
+            GraphObject::Collection { index, ty } => {
+                let is_open = self.collection_stack.iter().any(|&event_index| {
+                    matches!(&self.events[event_index], Event::Collection(c) if c.index == index)
+                });
+                if is_open {
+                    return Err(ErrorKind::RecursiveObject.without_position());
+                }
+                let kind = match ty {
+                    CollectionType::Array => EventKind::StartArray,
+                    CollectionType::Dictionary => EventKind::StartDictionary,
+                };
+                // A reference is a complete collection.
+                self.validator.validate(kind)?;
+                self.validator.validate(EventKind::EndCollection)?;
+                self.increment_current_collection_len();
+                self.events.push(Event::Reference(index));
+                Ok(())
+            }
+            GraphObject::Value(value_index) => {
+                let expecting_dictionary_key = self.validator.expecting_key();
+                let (value, _) = value_mut(&mut self.values, value_index);
+                self.validator.validate(value.event_kind())?;
+                self.push_value(value_index, expecting_dictionary_key)
+            }
+        }
+    }
+
+    fn write_start_collection(&mut self, ty: CollectionType) -> Result<(), Error> {
+        self.validator.validate(match ty {
+            CollectionType::Array => EventKind::StartArray,
+            CollectionType::Dictionary => EventKind::StartDictionary,
+        })?;
         self.increment_current_collection_len();
         self.collection_stack.push(self.events.len());
         self.events.push(Event::Collection(Collection {
             ty,
             len: 0,
             skip: 0,
-            object_ref: None,
+            index: self.collection_refs.len(),
         }));
+        self.collection_refs.push(CollectionRef::Unassigned);
         self.num_objects += 1;
         Ok(())
     }
 
     fn write_end_collection(&mut self) -> Result<(), Error> {
-        let collection_event_index = self.collection_stack.pop().ok_or_else(|| {
-            ErrorKind::UnexpectedEventType {
-                expected: EventKind::ValueOrStartCollection,
-                found: EventKind::EndCollection,
-            }
-            .without_position()
-        })?;
+        self.validator.validate(EventKind::EndCollection)?;
+        let collection_event_index = self
+            .collection_stack
+            .pop()
+            .expect("the validator only accepts the end of an open collection");
 
         let current_event_index = self.events.len() - 1;
         let c = if let Event::Collection(c) = &mut self.events[collection_event_index] {
@@ -168,15 +481,6 @@ impl<W: Write> BinaryWriter<W> {
         c.skip = current_event_index - collection_event_index;
 
         if let CollectionType::Dictionary = c.ty {
-            // Ensure that every dictionary key is paired with a value.
-            if !is_even(c.len) {
-                return Err(ErrorKind::UnexpectedEventType {
-                    expected: EventKind::DictionaryKeyOrEndCollection,
-                    found: EventKind::EndCollection,
-                }
-                .without_position());
-            }
-
             // Fix up the dictionary length. It should contain the number of key-value pairs,
             // not the number of keys and values.
             c.len /= 2;
@@ -198,37 +502,117 @@ impl<W: Write> BinaryWriter<W> {
 
         if self.collection_stack.is_empty() {
             self.write_plist()?;
+        } else if self.options.deduplicate_collections {
+            self.deduplicate_collection(collection_event_index);
         }
 
         Ok(())
     }
 
-    fn write_value(&mut self, value: Value) -> Result<(), Error> {
-        let expecting_dictionary_key = self.expecting_dictionary_key();
-
-        // Ensure that all dictionary keys are strings.
-        match (&value, expecting_dictionary_key) {
-            (Value::String(_), true) | (_, false) => (),
-            (_, true) => {
-                return Err(ErrorKind::UnexpectedEventType {
-                    expected: EventKind::DictionaryKeyOrEndCollection,
-                    found: value.event_kind(),
+    /// Replaces the collection closed at `event_index` with a reference to an earlier collection
+    /// of the same type and contents, if there is one.
+    fn deduplicate_collection(&mut self, event_index: usize) {
+        let (ty, index) = if let Event::Collection(c) = &self.events[event_index] {
+            (c.ty, c.index)
+        } else {
+            unreachable!("items in `collection_stack` always point to a collection event");
+        };
+
+        // Nested collections have already been deduplicated so the contents can be identified by
+        // the indices of their values and collections.
+        let mut children = Vec::new();
+        let mut i = event_index + 1;
+        while let Some(event) = self.events.get(i) {
+            match event {
+                Event::Collection(c) => {
+                    children.push(ObjectId::Collection(c.index));
+                    i += c.skip;
+                }
+                Event::Reference(index) => {
+                    children.push(ObjectId::Collection(self.resolve_collection(*index)));
                 }
-                .without_position())
+                Event::Value(value_index) => children.push(ObjectId::Value(*value_index)),
+                // Keys follow the values and both are counted by the collection length.
+                Event::DictionaryKeys(_) => (),
             }
+            i += 1;
         }
 
-        // Deduplicate `value`. There is one entry in `values` for each unqiue `Value` in the
-        // plist.
-        let value_index = if let Some((value_index, _, _)) = self.values.get_full(&value) {
+        match self.collections.entry((ty, children)) {
+            Entry::Occupied(entry) => {
+                // Any nested collections are already references, so only the collection itself
+                // is removed from the plist.
+                let original = *entry.get();
+                self.events.truncate(event_index);
+                self.events.push(Event::Reference(original));
+                self.collection_refs[index] = CollectionRef::Duplicate(original);
+                self.num_objects -= 1;
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+        }
+    }
+
+    /// Returns the index of the collection which `index` is a duplicate of, or `index` itself.
+    fn resolve_collection(&self, index: usize) -> usize {
+        match self.collection_refs[index] {
+            CollectionRef::Duplicate(original) => original,
+            _ => index,
+        }
+    }
+
+    fn write_value(&mut self, value: Value) -> Result<(), Error> {
+        self.write_value_and_get_index(value).map(|_| ())
+    }
+
+    /// Writes `value`, returning its index in the `values` map.
+    fn write_value_and_get_index(&mut self, value: Value) -> Result<usize, Error> {
+        if let Value::Integer(v) = value {
+            if self.options.reject_large_unsigned && v.as_signed().is_none() {
+                return Err(ErrorKind::IntegerOutOfRange.without_position());
+            }
+        }
+
+        let expecting_dictionary_key = self.validator.expecting_key();
+        self.validator.validate(value.event_kind())?;
+
+        // Deduplicate `value`. There is one entry in `values` for each unique deduplicated `Value`
+        // in the plist and one for each occurrence of other values.
+        let deduplicate = match self.options.deduplication {
+            // CoreFoundation does not deduplicate uids.
+            Deduplication::Values => {
+                !(self.options.apple_compatible && matches!(value, Value::Uid(_)))
+            }
+            Deduplication::Strings => matches!(value, Value::String(_)),
+            Deduplication::Off => false,
+        };
+        let key = (
+            value,
+            if deduplicate {
+                0
+            } else {
+                self.values.len() + 1
+            },
+        );
+        let value_index = if let Some((value_index, _, _)) = self.values.get_full(&key) {
             value_index
         } else {
             self.num_objects += 1;
-            let value = value.into_owned();
-            let (value_index, _) = self.values.insert_full(value, ValueState::Unassigned);
+            let key = (key.0.into_owned(), key.1);
+            let (value_index, _) = self.values.insert_full(key, ValueState::Unassigned);
             value_index
         };
 
+        self.push_value(value_index, expecting_dictionary_key)?;
+        Ok(value_index)
+    }
+
+    fn push_value(
+        &mut self,
+        value_index: usize,
+        expecting_dictionary_key: bool,
+    ) -> Result<(), Error> {
         // Dictionary keys are buffered in `dictionary_key_events` until the dictionary is closed
         // in `write_end_collection` when they are moved to the end of the `events` array.
         if expecting_dictionary_key {
@@ -246,18 +630,6 @@ impl<W: Write> BinaryWriter<W> {
         Ok(())
     }
 
-    fn expecting_dictionary_key(&self) -> bool {
-        if let Some(&event_index) = self.collection_stack.last() {
-            if let Event::Collection(c) = &self.events[event_index] {
-                c.ty == CollectionType::Dictionary && is_even(c.len)
-            } else {
-                unreachable!("items in `collection_stack` always point to a collection event");
-            }
-        } else {
-            false
-        }
-    }
-
     fn increment_current_collection_len(&mut self) {
         if let Some(&event_index) = self.collection_stack.last() {
             if let Event::Collection(c) = &mut self.events[event_index] {
@@ -272,14 +644,74 @@ impl<W: Write> BinaryWriter<W> {
         assert!(self.collection_stack.is_empty());
 
         // Write header
-        self.writer.write_exact(b"bplist00")?;
+        self.writer.write_exact(b"bplist")?;
+        self.writer.write_exact(&self.options.version)?;
 
         // Write objects
-        let mut events_vec = mem::replace(&mut self.events, Vec::new());
-        let mut events = &mut events_vec[..];
-        let ref_size = plist_ref_size(self.num_objects - 1);
+        let mut events_vec = mem::take(&mut self.events);
+        // CoreFoundation sizes object references to hold the number of objects rather than the
+        // largest reference.
+        let ref_size = if self.options.apple_compatible {
+            plist_ref_size(self.num_objects)
+        } else {
+            plist_ref_size(self.num_objects - 1)
+        }
+        .max(self.options.min_object_ref_size);
         let mut offset_table = vec![0; self.num_objects];
 
+        if self.options.apple_compatible {
+            self.write_plist_objects_in_ref_order(&events_vec, ref_size, &mut offset_table)?;
+        } else {
+            self.write_plist_objects(&mut events_vec, ref_size, &mut offset_table)?;
+        }
+
+        // Write object offset table
+        let offset_table_offset = self.writer.pos;
+        let offset_size = plist_ref_size(offset_table_offset).max(self.options.min_offset_size);
+        for &offset in &offset_table {
+            write_plist_ref(&mut self.writer, offset_size, offset)?;
+        }
+
+        // Write trailer
+        // 6 zero bytes padding
+        // 1 byte offset size
+        // 1 byte object ref size
+        // 8 bytes number of objects
+        // 8 bytes root object ref (always zero)
+        // 8 bytes file offset of the object offset table
+        let mut trailer = [0; 32];
+        trailer[6] = offset_size;
+        trailer[7] = ref_size;
+        trailer[8..16].copy_from_slice(&(self.num_objects as u64).to_be_bytes());
+        trailer[24..32].copy_from_slice(&(offset_table_offset as u64).to_be_bytes());
+        self.writer.write_exact(&trailer)?;
+
+        self.writer
+            .flush()
+            .map_err(error::from_io_without_position)?;
+
+        // Reset plist writer
+        self.writer.pos = 0;
+        events_vec.clear();
+        self.events = events_vec;
+        self.values.clear();
+        self.collection_refs.clear();
+        self.collections.clear();
+        self.graph_objects.clear();
+        self.num_objects = 0;
+
+        Ok(())
+    }
+
+    /// Writes the objects of a plist in the order of the `events` array, assigning object
+    /// references to the children of each collection as it is written.
+    fn write_plist_objects(
+        &mut self,
+        events: &mut [Event],
+        ref_size: u8,
+        offset_table: &mut [usize],
+    ) -> Result<(), Error> {
+        let mut events = events;
         // Assign the first (root) event an object reference of zero.
         let mut next_object_ref = ObjectRef::zero();
         match &mut events[0] {
@@ -288,9 +720,10 @@ impl<W: Write> BinaryWriter<W> {
                 *value_state = ValueState::Unwritten(next_object_ref.clone_and_increment_self());
             }
             Event::Collection(c) => {
-                c.object_ref = Some(next_object_ref.clone_and_increment_self());
+                self.collection_refs[c.index] =
+                    CollectionRef::Assigned(next_object_ref.clone_and_increment_self());
             }
-            Event::DictionaryKeys(_) => {
+            Event::DictionaryKeys(_) | Event::Reference(_) => {
                 unreachable!("`events` starts with a value or collection event")
             }
         }
@@ -305,12 +738,14 @@ impl<W: Write> BinaryWriter<W> {
                         collection_events,
                         ref_size,
                         &mut next_object_ref,
-                        &mut offset_table,
+                        offset_table,
                     )?;
                 }
                 Event::Value(value_index) => {
-                    self.write_plist_value(*value_index, &mut offset_table)?;
+                    self.write_plist_value(*value_index, offset_table)?;
                 }
+                // The referenced collection is written at its own `Collection` event.
+                Event::Reference(_) => (),
                 // Dictionary keys will have already been written in `write_plist_collection` so we
                 // skip over them here.
                 Event::DictionaryKeys(len) => {
@@ -319,37 +754,83 @@ impl<W: Write> BinaryWriter<W> {
             }
         }
 
-        // Write object offset table
-        let offset_table_offset = self.writer.pos;
-        let offset_size = plist_ref_size(offset_table_offset);
-        for &offset in &offset_table {
-            write_plist_ref(&mut self.writer, offset_size, offset)?;
-        }
+        Ok(())
+    }
 
-        // Write trailer
-        // 6 zero bytes padding
-        // 1 byte offset size
-        // 1 byte object ref size
-        // 8 bytes number of objects
-        // 8 bytes root object ref (always zero)
-        // 8 bytes file offset of the object offset table
-        let mut trailer = [0; 32];
-        trailer[6] = offset_size;
-        trailer[7] = ref_size;
-        trailer[8..16].copy_from_slice(&(self.num_objects as u64).to_be_bytes());
-        trailer[24..32].copy_from_slice(&(offset_table_offset as u64).to_be_bytes());
-        self.writer.write_exact(&trailer)?;
+    /// Writes the objects of a plist in the order used by CoreFoundation. Object references are
+    /// assigned in preorder, visiting the keys of a dictionary before its values, and objects are
+    /// written in the order of their references.
+    fn write_plist_objects_in_ref_order(
+        &mut self,
+        events: &[Event],
+        ref_size: u8,
+        offset_table: &mut [usize],
+    ) -> Result<(), Error> {
+        let mut next_object_ref = ObjectRef::zero();
+        let mut objects = Vec::with_capacity(self.num_objects);
+        let mut stack = vec![0];
+        while let Some(event_index) = stack.pop() {
+            match &events[event_index] {
+                Event::Collection(c) => {
+                    self.collection_ref(c.index, &mut next_object_ref);
+                    objects.push(event_index);
+                    let children = self.apple_collection_children(events, event_index);
+                    stack.extend(children.into_iter().rev());
+                }
+                Event::Value(value_index) => {
+                    let (_, value_state) = value_mut(&mut self.values, *value_index);
+                    if let ValueState::Unassigned = value_state {
+                        *value_state =
+                            ValueState::Unwritten(next_object_ref.clone_and_increment_self());
+                        objects.push(event_index);
+                    }
+                }
+                // The `Collection` event of a referenced collection precedes any references to it
+                // so it has already been assigned an object reference.
+                Event::Reference(_) => (),
+                Event::DictionaryKeys(_) => {
+                    unreachable!("`DictionaryKeys` events are not children of a collection")
+                }
+            }
+        }
 
-        self.writer
-            .flush()
-            .map_err(error::from_io_without_position)?;
+        for event_index in objects {
+            let c = match &events[event_index] {
+                Event::Collection(c) => c,
+                Event::Value(value_index) => {
+                    self.write_plist_value(*value_index, offset_table)?;
+                    continue;
+                }
+                _ => unreachable!("only collections and values are objects"),
+            };
 
-        // Reset plist writer
-        self.writer.pos = 0;
-        events_vec.clear();
-        self.events = events_vec;
-        self.values.clear();
-        self.num_objects = 0;
+            let object_ref = self.collection_ref(c.index, &mut next_object_ref);
+            offset_table[object_ref.value()] = self.writer.pos;
+            let ty = match c.ty {
+                CollectionType::Array => 0xa0,
+                CollectionType::Dictionary => 0xd0,
+            };
+            write_plist_value_ty_and_size(&mut self.writer, ty, c.len)?;
+            for child in self.apple_collection_children(events, event_index) {
+                let object_ref = match &events[child] {
+                    Event::Collection(c) => self.collection_ref(c.index, &mut next_object_ref),
+                    Event::Reference(index) => self.collection_ref(*index, &mut next_object_ref),
+                    Event::Value(value_index) => match value_mut(&mut self.values, *value_index) {
+                        (
+                            _,
+                            ValueState::Unwritten(object_ref) | ValueState::Written(object_ref),
+                        ) => object_ref.clone(),
+                        (_, ValueState::Unassigned) => {
+                            unreachable!("all objects are assigned references before writing")
+                        }
+                    },
+                    Event::DictionaryKeys(_) => {
+                        unreachable!("`DictionaryKeys` events are not children of a collection")
+                    }
+                };
+                write_plist_ref(&mut self.writer, ref_size, object_ref.value())?;
+            }
+        }
 
         Ok(())
     }
@@ -360,9 +841,9 @@ impl<W: Write> BinaryWriter<W> {
         events: &mut [Event],
         ref_size: u8,
         next_object_ref: &mut ObjectRef,
-        offset_table: &mut Vec<usize>,
+        offset_table: &mut [usize],
     ) -> Result<(), Error> {
-        if let Some(object_ref) = &collection.object_ref {
+        if let CollectionRef::Assigned(object_ref) = &self.collection_refs[collection.index] {
             offset_table[object_ref.value()] = self.writer.pos;
         } else {
             unreachable!("collection object refs are assigned before this function is called");
@@ -394,13 +875,11 @@ impl<W: Write> BinaryWriter<W> {
                         let _ = collection_events.nth(c.skip - 1);
                     }
 
-                    // Collections are not deduplicated so they must be assigned an object
-                    // reference here.
-                    assert!(c.object_ref.is_none());
-                    let object_ref = next_object_ref.clone_and_increment_self();
-                    c.object_ref = Some(object_ref.clone());
-                    object_ref
+                    self.collection_ref(c.index, next_object_ref)
                 }
+                // A referenced collection is assigned an object reference at its first
+                // occurrence in the plist, which may be this reference.
+                Event::Reference(index) => self.collection_ref(*index, next_object_ref),
                 Event::Value(value_index) => {
                     // Values are deduplicated so we only assign an object reference if we have not
                     // already done so previously.
@@ -436,10 +915,54 @@ impl<W: Write> BinaryWriter<W> {
         Ok(())
     }
 
+    /// Returns the indices in `events` of the elements of the collection at `event_index`, with the
+    /// entries of a dictionary in the order CoreFoundation enumerates them.
+    fn apple_collection_children(&self, events: &[Event], event_index: usize) -> Vec<usize> {
+        let children = collection_children(events, event_index);
+        let len = match &events[event_index] {
+            Event::Collection(c) if c.ty == CollectionType::Dictionary => c.len,
+            _ => return children,
+        };
+
+        let keys: Vec<&str> = children[..len]
+            .iter()
+            .map(|&key| match &events[key] {
+                Event::Value(value_index) => match self.values.get_index(*value_index) {
+                    Some(((Value::String(key), _), _)) => &**key,
+                    _ => unreachable!("the validator only accepts strings as dictionary keys"),
+                },
+                _ => {
+                    unreachable!("dictionary keys are assigned as values in `write_end_collection`")
+                }
+            })
+            .collect();
+        let order = cf_dictionary_order(&keys);
+        let keys = order.iter().map(|&i| children[i]);
+        let values = order.iter().map(|&i| children[len + i]);
+        keys.chain(values).collect()
+    }
+
+    /// Returns the object reference of a collection, assigning one if it does not yet have one.
+    fn collection_ref(&mut self, index: usize, next_object_ref: &mut ObjectRef) -> ObjectRef {
+        let index = self.resolve_collection(index);
+        let collection_ref = &mut self.collection_refs[index];
+        match collection_ref {
+            CollectionRef::Unassigned => {
+                let object_ref = next_object_ref.clone_and_increment_self();
+                *collection_ref = CollectionRef::Assigned(object_ref.clone());
+                object_ref
+            }
+            CollectionRef::Assigned(object_ref) => object_ref.clone(),
+            CollectionRef::Duplicate(_) => {
+                unreachable!("duplicates refer to an original collection")
+            }
+        }
+    }
+
     fn write_plist_value(
         &mut self,
         value_index: usize,
-        offset_table: &mut Vec<usize>,
+        offset_table: &mut [usize],
     ) -> Result<(), Error> {
         let (value, value_state) = value_mut(&mut self.values, value_index);
 
@@ -473,13 +996,13 @@ impl<W: Write> BinaryWriter<W> {
             }
             Value::Integer(v) => {
                 if let Some(v) = v.as_signed() {
-                    if v >= 0 && v <= i64::from(u8::max_value()) {
+                    if v >= 0 && v <= i64::from(u8::MAX) {
                         self.writer.write_exact(&[0x10, v as u8])?;
-                    } else if v >= 0 && v <= i64::from(u16::max_value()) {
+                    } else if v >= 0 && v <= i64::from(u16::MAX) {
                         let mut buf: [_; 3] = [0x11, 0, 0];
                         buf[1..].copy_from_slice(&(v as u16).to_be_bytes());
                         self.writer.write_exact(&buf)?;
-                    } else if v >= 0 && v <= i64::from(u32::max_value()) {
+                    } else if v >= 0 && v <= i64::from(u32::MAX) {
                         let mut buf: [_; 5] = [0x12, 0, 0, 0, 0];
                         buf[1..].copy_from_slice(&(v as u32).to_be_bytes());
                         self.writer.write_exact(&buf)?;
@@ -489,7 +1012,7 @@ impl<W: Write> BinaryWriter<W> {
                         self.writer.write_exact(&buf)?;
                     }
                 } else if let Some(v) = v.as_unsigned() {
-                    // `u64`s larger than `i64::max_value()` are stored as signed 128 bit
+                    // `u64`s larger than `i64::MAX` are stored as signed 128 bit
                     // integers.
                     let mut buf: [_; 17] = [0x14, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
                     buf[1..].copy_from_slice(&i128::from(v).to_be_bytes());
@@ -503,7 +1026,7 @@ impl<W: Write> BinaryWriter<W> {
                 buf[1..].copy_from_slice(&v.to_be_bytes());
                 self.writer.write_exact(&buf)?;
             }
-            Value::String(v) if v.is_ascii() => {
+            Value::String(v) if v.is_ascii() && !self.options.utf16_strings => {
                 let ascii = v.as_bytes();
                 write_plist_value_ty_and_size(&mut self.writer, 0x50, ascii.len())?;
                 self.writer.write_exact(ascii)?;
@@ -517,19 +1040,19 @@ impl<W: Write> BinaryWriter<W> {
             }
             Value::Uid(v) => {
                 let v = v.get();
-                if v <= u64::from(u8::max_value()) {
+                if v <= u64::from(u8::MAX) {
                     self.writer.write_exact(&[0x80, v as u8])?;
-                } else if v <= u64::from(u16::max_value()) {
+                } else if v <= u64::from(u16::MAX) {
                     let mut buf: [_; 3] = [0x81, 0, 0];
                     buf[1..].copy_from_slice(&(v as u16).to_be_bytes());
                     self.writer.write_exact(&buf)?;
-                } else if v <= u64::from(u32::max_value()) {
+                } else if v <= u64::from(u32::MAX) {
                     let mut buf: [_; 5] = [0x83, 0, 0, 0, 0];
                     buf[1..].copy_from_slice(&(v as u32).to_be_bytes());
                     self.writer.write_exact(&buf)?;
                 } else {
                     let mut buf: [_; 9] = [0x87, 0, 0, 0, 0, 0, 0, 0, 0];
-                    buf[1..].copy_from_slice(&(v as u64).to_be_bytes());
+                    buf[1..].copy_from_slice(&v.to_be_bytes());
                     self.writer.write_exact(&buf)?;
                 }
             }
@@ -572,17 +1095,113 @@ impl<W: Write> Writer for BinaryWriter<W> {
     }
 }
 
-fn is_even(value: usize) -> bool {
-    value & 1 == 0
-}
-
 fn value_mut<'a>(
-    values: &'a mut IndexMap<Value<'static>, ValueState, RandomState>,
+    values: &'a mut IndexMap<(Value<'static>, usize), ValueState, RandomState>,
     value_index: usize,
-) -> (&'a mut Value<'static>, &'a mut ValueState) {
-    values
+) -> (&'a Value<'static>, &'a mut ValueState) {
+    let ((value, _), value_state) = values
         .get_index_mut(value_index)
-        .expect("internal consistency error")
+        .expect("internal consistency error");
+    (value, value_state)
+}
+
+/// Returns the indices in `events` of the elements of the collection at `event_index`, or of the
+/// keys followed by the values of a dictionary.
+fn collection_children(events: &[Event], event_index: usize) -> Vec<usize> {
+    let skip = match &events[event_index] {
+        Event::Collection(c) => c.skip,
+        _ => unreachable!("`event_index` points to a collection event"),
+    };
+    let end = event_index + 1 + skip;
+
+    let mut children = Vec::new();
+    let mut i = event_index + 1;
+    while i < end {
+        match &events[i] {
+            Event::Collection(c) => {
+                children.push(i);
+                i += c.skip;
+            }
+            Event::DictionaryKeys(len) => {
+                let keys = (i + 1..i + 1 + len).collect::<Vec<_>>();
+                return keys.into_iter().chain(children).collect();
+            }
+            Event::Value(_) | Event::Reference(_) => children.push(i),
+        }
+        i += 1;
+    }
+    children
+}
+
+/// The number of buckets in each size of CoreFoundation hash table.
+const CF_HASH_TABLE_SIZES: [usize; 40] = [
+    0, 3, 7, 13, 23, 41, 71, 127, 191, 251, 383, 631, 1087, 1723, 2803, 4523, 7351, 11959, 19447,
+    31231, 50683, 81919, 132607, 214519, 346607, 561109, 907759, 1468927, 2376191, 3845119,
+    6221311, 10066421, 16287743, 26354171, 42641881, 68996069, 111638519, 180634607, 292272623,
+    472907251,
+];
+
+/// The maximum number of entries held by each size of CoreFoundation hash table.
+const CF_HASH_TABLE_CAPACITIES: [usize; 40] = [
+    0, 3, 6, 11, 19, 32, 52, 85, 118, 155, 237, 390, 672, 1065, 1732, 2795, 4543, 7391, 12019,
+    19302, 31324, 50629, 81956, 132580, 214215, 346784, 561026, 907847, 1468567, 2376414, 3844982,
+    6221390, 10066379, 16287773, 26354132, 42641916, 68996399, 111638327, 180634415, 292272755,
+];
+
+/// Returns the order in which CoreFoundation enumerates the entries of a dictionary created with
+/// `keys` in order: by their buckets in a linearly probed hash table sized for the number of keys.
+/// Dictionaries too large for the known table sizes keep their order.
+fn cf_dictionary_order(keys: &[&str]) -> Vec<usize> {
+    let num_buckets = match CF_HASH_TABLE_CAPACITIES
+        .iter()
+        .position(|&capacity| capacity >= keys.len())
+    {
+        Some(size_index) => CF_HASH_TABLE_SIZES[size_index],
+        None => return (0..keys.len()).collect(),
+    };
+
+    let mut buckets = vec![None; num_buckets];
+    for (i, key) in keys.iter().enumerate() {
+        let mut bucket = (cf_string_hash(key) % num_buckets as u64) as usize;
+        while buckets[bucket].is_some() {
+            bucket = (bucket + 1) % num_buckets;
+        }
+        buckets[bucket] = Some(i);
+    }
+    buckets.into_iter().flatten().collect()
+}
+
+/// Computes CoreFoundation's `CFStringHash` of `s` on a 64 bit platform. Strings of up to 96
+/// UTF-16 code units are hashed entirely, longer strings by their first, middle and last 32.
+fn cf_string_hash(s: &str) -> u64 {
+    fn hash_four(result: u64, c: &[u16]) -> u64 {
+        result
+            .wrapping_mul(67503105)
+            .wrapping_add(u64::from(c[0]).wrapping_mul(16974593))
+            .wrapping_add(u64::from(c[1]).wrapping_mul(66049))
+            .wrapping_add(u64::from(c[2]).wrapping_mul(257))
+            .wrapping_add(u64::from(c[3]))
+    }
+
+    let chars: Vec<u16> = s.encode_utf16().collect();
+    let len = chars.len();
+    let mut result = len as u64;
+    if len <= 96 {
+        let mut fours = chars.chunks_exact(4);
+        for c in &mut fours {
+            result = hash_four(result, c);
+        }
+        for &c in fours.remainder() {
+            result = result.wrapping_mul(257).wrapping_add(u64::from(c));
+        }
+    } else {
+        for start in [0, len / 2 - 16, len - 32] {
+            for c in chars[start..start + 32].chunks_exact(4) {
+                result = hash_four(result, c);
+            }
+        }
+    }
+    result.wrapping_add(result << (len & 31))
 }
 
 fn write_plist_value_ty_and_size(
@@ -592,13 +1211,13 @@ fn write_plist_value_ty_and_size(
 ) -> Result<(), Error> {
     if size < 0x0f {
         writer.write_exact(&[token | (size as u8)])?;
-    } else if size <= u8::max_value() as usize {
+    } else if size <= u8::MAX as usize {
         writer.write_exact(&[token | 0x0f, 0x10, size as u8])?;
-    } else if size <= u16::max_value() as usize {
+    } else if size <= u16::MAX as usize {
         let mut buf: [_; 4] = [token | 0x0f, 0x11, 0, 0];
         buf[2..].copy_from_slice(&(size as u16).to_be_bytes());
         writer.write_exact(&buf)?;
-    } else if size <= u32::max_value() as usize {
+    } else if size <= u32::MAX as usize {
         let mut buf: [_; 6] = [token | 0x0f, 0x12, 0, 0, 0, 0];
         buf[2..].copy_from_slice(&(size as u32).to_be_bytes());
         writer.write_exact(&buf)?;
@@ -611,9 +1230,8 @@ fn write_plist_value_ty_and_size(
 }
 
 fn plist_ref_size(max_value: usize) -> u8 {
-    let significant_bits = 64 - (max_value as u64).leading_zeros() as u8;
-    // Convert to number of bytes
-    let significant_bytes = (significant_bits + 7) / 8;
+    // The number of significant bits, rounded up to a whole number of bytes.
+    let significant_bytes = (71 - (max_value as u64).leading_zeros() as u8) / 8;
     // Round up to the next integer byte size which must be power of two.
     significant_bytes.next_power_of_two()
 }
@@ -646,7 +1264,7 @@ impl<W: Write> Write for PosWriter<W> {
         self.pos = self
             .pos
             .checked_add(count)
-            .expect("binary plist cannot be larger than `usize::max_value()` bytes");
+            .expect("binary plist cannot be larger than `usize::MAX` bytes");
         Ok(count)
     }
 
@@ -672,6 +1290,22 @@ impl ObjectRef {
 }
 
 impl<'a> Value<'a> {
+    /// Returns the value of an event, or `None` for collection events.
+    fn from_event(event: &'a StreamEvent<'_>) -> Option<Value<'a>> {
+        Some(match event {
+            StreamEvent::StartArray(_)
+            | StreamEvent::StartDictionary(_)
+            | StreamEvent::EndCollection => return None,
+            StreamEvent::Boolean(v) => Value::Boolean(*v),
+            StreamEvent::Data(v) => Value::Data(Cow::Borrowed(v)),
+            StreamEvent::Date(v) => Value::Date(*v),
+            StreamEvent::Integer(v) => Value::Integer(*v),
+            StreamEvent::Real(v) => Value::Real(v.to_bits()),
+            StreamEvent::String(v) => Value::String(Cow::Borrowed(v)),
+            StreamEvent::Uid(v) => Value::Uid(*v),
+        })
+    }
+
     fn into_owned(self) -> Value<'static> {
         match self {
             Value::Boolean(v) => Value::Boolean(v),
@@ -701,7 +1335,12 @@ impl<'a> Value<'a> {
 mod tests {
     use std::{fs::File, io::Cursor, path::Path};
 
-    use crate::{stream::BinaryReader, Value};
+    use super::*;
+    use crate::{
+        bplist::{Issue, ObjectKind},
+        stream::BinaryReader,
+        Dictionary, Value,
+    };
 
     fn test_roundtrip(path: &Path) {
         let reader = File::open(path).unwrap();
@@ -716,23 +1355,220 @@ mod tests {
         let streaming_parser = BinaryReader::new(Cursor::new(buf_inner));
 
         let events: Vec<Result<_, _>> = streaming_parser.collect();
-        let value_decoded_from_encode = Value::from_events(events.into_iter()).unwrap();
+        let value_decoded_from_encode = Value::from_events(events).unwrap();
 
         assert_eq!(value_to_encode, value_decoded_from_encode);
     }
 
     #[test]
     fn bplist_roundtrip() {
-        test_roundtrip(&Path::new("./tests/data/binary.plist"))
+        test_roundtrip(Path::new("./tests/data/binary.plist"))
     }
 
     #[test]
     fn utf16_roundtrip() {
-        test_roundtrip(&Path::new("./tests/data/utf16_bplist.plist"))
+        test_roundtrip(Path::new("./tests/data/utf16_bplist.plist"))
     }
 
     #[test]
     fn nskeyedarchiver_roundtrip() {
-        test_roundtrip(&Path::new("./tests/data/binary_NSKeyedArchiver.plist"))
+        test_roundtrip(Path::new("./tests/data/binary_NSKeyedArchiver.plist"))
+    }
+
+    #[test]
+    fn canonical_output_is_independent_of_key_order() {
+        fn write_canonical(value: &Value) -> Vec<u8> {
+            let mut writer = BinaryWriter::new_canonical(Vec::new());
+            for event in value.clone().into_events() {
+                writer.write(&event).unwrap();
+            }
+            writer.into_inner().into_inner()
+        }
+
+        let mut a = Dictionary::new();
+        a.insert("one".to_owned(), 1.into());
+        a.insert("two".to_owned(), Value::Array(vec!["one".into(), 2.into()]));
+        let mut b = Dictionary::new();
+        b.insert("two".to_owned(), Value::Array(vec!["one".into(), 2.into()]));
+        b.insert("one".to_owned(), 1.into());
+
+        let a_bytes = write_canonical(&Value::Dictionary(a.clone()));
+        assert_eq!(a_bytes, write_canonical(&Value::Dictionary(b)));
+
+        let decoded = Value::from_reader(Cursor::new(a_bytes)).unwrap();
+        assert_eq!(decoded, Value::Dictionary(a));
+    }
+
+    #[test]
+    fn deduplicate_collections() {
+        fn write(value: &Value, deduplicate_collections: bool) -> Vec<u8> {
+            let options =
+                BinaryWriteOptions::new().deduplicate_collections(deduplicate_collections);
+            let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+            for event in value.events() {
+                writer.write(&event).unwrap();
+            }
+            writer.into_inner()
+        }
+
+        let mut entry = Dictionary::new();
+        entry.insert("name".to_owned(), "a".into());
+        entry.insert("tags".to_owned(), Value::Array(vec![1.into(), 2.into()]));
+        let mut reordered = Dictionary::new();
+        reordered.insert("tags".to_owned(), Value::Array(vec![1.into(), 2.into()]));
+        reordered.insert("name".to_owned(), "a".into());
+        let value = Value::Array(vec![
+            Value::Dictionary(entry.clone()),
+            Value::Array(vec![1.into(), 2.into()]),
+            Value::Dictionary(entry),
+            Value::Dictionary(reordered),
+            Value::Array(vec![2.into(), 1.into()]),
+        ]);
+
+        for deduplicate_collections in [false, true] {
+            let bytes = write(&value, deduplicate_collections);
+            assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), value);
+
+            let inspection = crate::bplist::inspect(&bytes).unwrap();
+            assert!(!inspection
+                .issues
+                .iter()
+                .any(|issue| matches!(issue, Issue::UnreferencedObject { .. })));
+            // The root, the strings "a", "name" and "tags" and the integers 1 and 2 are always
+            // written once. Without collection deduplication each of the five elements and three
+            // nested arrays are written. With it the dictionaries with differently ordered keys,
+            // the array `[1, 2]` and the array `[2, 1]` remain.
+            let expected_objects = if deduplicate_collections { 10 } else { 14 };
+            assert_eq!(inspection.trailer.num_objects, expected_objects);
+        }
+    }
+
+    #[test]
+    fn deduplicated_graph_references() {
+        let options = BinaryWriteOptions::new().deduplicate_collections(true);
+        let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+        let object = |id, event| GraphEvent::Object { id, event };
+        let events = [
+            object(0, StreamEvent::StartArray(None)),
+            object(1, StreamEvent::StartArray(None)),
+            object(2, StreamEvent::Integer(1.into())),
+            GraphEvent::EndCollection,
+            object(3, StreamEvent::StartArray(None)),
+            GraphEvent::Reference(2),
+            GraphEvent::EndCollection,
+            GraphEvent::Reference(3),
+            GraphEvent::EndCollection,
+        ];
+        for event in &events {
+            writer.write_graph_event(event).unwrap();
+        }
+
+        let bytes = writer.into_inner();
+        let inner = Value::Array(vec![1.into()]);
+        let expected = Value::Array(vec![inner.clone(), inner.clone(), inner]);
+        assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), expected);
+        let inspection = crate::bplist::inspect(&bytes).unwrap();
+        assert_eq!(inspection.trailer.num_objects, 3);
+    }
+
+    #[test]
+    fn write_options() {
+        fn write(value: &Value, options: BinaryWriteOptions) -> Vec<u8> {
+            let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+            for event in value.events() {
+                writer.write(&event).unwrap();
+            }
+            writer.into_inner()
+        }
+
+        let mut dict = Dictionary::new();
+        dict.insert("a".to_owned(), "a".into());
+        dict.insert("b".to_owned(), 1.into());
+        let value = Value::Array(vec![
+            Value::Dictionary(dict),
+            "a".into(),
+            1.into(),
+            Value::Integer(u64::MAX.into()),
+        ]);
+
+        for (deduplication, num_objects) in [
+            (Deduplication::Values, 6),
+            (Deduplication::Strings, 7),
+            (Deduplication::Off, 9),
+        ] {
+            let bytes = write(
+                &value,
+                BinaryWriteOptions::new().deduplication(deduplication),
+            );
+            assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), value);
+            let inspection = crate::bplist::inspect(&bytes).unwrap();
+            assert_eq!(inspection.trailer.num_objects, num_objects);
+        }
+
+        let options = BinaryWriteOptions::new()
+            .min_object_ref_size(4)
+            .min_offset_size(2)
+            .utf16_strings(true);
+        let bytes = write(&value, options);
+        assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), value);
+        let inspection = crate::bplist::inspect(&bytes).unwrap();
+        assert_eq!(inspection.trailer.object_ref_size, 4);
+        assert_eq!(inspection.trailer.offset_size, 2);
+        assert!(inspection
+            .objects
+            .iter()
+            .all(|object| object.kind != Some(ObjectKind::AsciiString)));
+
+        let options = BinaryWriteOptions::new().reject_large_unsigned(true);
+        let value = Value::Array(vec![i64::MAX.into(), (i64::MAX as u64).into()]);
+        let bytes = write(&value, options.clone());
+        assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), value);
+        let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+        writer.write_start_array(None).unwrap();
+        let err = writer.write_integer(u64::MAX.into()).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::IntegerOutOfRange));
+
+        let options = BinaryWriteOptions::new().version(*b"01");
+        let bytes = write(&value, options);
+        assert!(bytes.starts_with(b"bplist01"));
+        assert_eq!(Value::from_reader(Cursor::new(&bytes)).unwrap(), value);
+        assert!(crate::bplist::inspect(&bytes).is_ok());
+    }
+
+    #[test]
+    fn apple_compatible_output_matches_apple_plists() {
+        // `binary_NSKeyedArchiver.plist` is not included as the archiver wrote one of its class
+        // names as two separate objects, which cannot be expressed by a `Value`.
+        for path in [
+            "./tests/data/binary.plist",
+            "./tests/data/utf16_bplist.plist",
+        ] {
+            let expected = std::fs::read(path).unwrap();
+            let value = Value::from_reader(Cursor::new(&expected)).unwrap();
+
+            let options = BinaryWriteOptions::new().apple_compatible(true);
+            let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+            for event in value.events() {
+                writer.write(&event).unwrap();
+            }
+            assert!(writer.into_inner() == expected, "{} differs", path);
+        }
+    }
+
+    #[test]
+    fn apple_compatible_dictionary_order() {
+        let expected = std::fs::read("./tests/data/binary.plist").unwrap();
+        let mut value = Value::from_reader(Cursor::new(&expected)).unwrap();
+        let dict = value.as_dictionary_mut().unwrap();
+        let original_keys: Vec<String> = dict.keys().cloned().collect();
+        dict.sort_keys();
+        assert!(dict.keys().ne(original_keys.iter()));
+
+        let options = BinaryWriteOptions::new().apple_compatible(true);
+        let mut writer = BinaryWriter::new_with_options(Vec::new(), options);
+        for event in value.events() {
+            writer.write(&event).unwrap();
+        }
+        assert!(writer.into_inner() == expected);
     }
 }
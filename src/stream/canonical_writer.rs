@@ -0,0 +1,143 @@
+use crate::{
+    error::Error,
+    shared_value::GraphBuilder,
+    stream::{GraphEvent, ValueWriter, Writer},
+    Date, Integer, Uid, Value,
+};
+
+/// Writes plists to another `Writer` in a canonical form.
+///
+/// Each plist is buffered in memory until it is complete. It is then canonicalized with
+/// `Value::canonicalize` before being written to the wrapped writer so that dictionary keys are
+/// sorted. Created by `BinaryWriter::new_canonical` and `XmlWriter::new_canonical`, or with
+/// `CanonicalWriter::new` to wrap any other `Writer`.
+///
+/// ```
+/// use plist::stream::{CanonicalWriter, Writer, XmlWriter};
+///
+/// let mut writer = CanonicalWriter::new(XmlWriter::new(Vec::new()));
+/// writer.write_start_dictionary(None).unwrap();
+/// writer.write_string("b").unwrap();
+/// writer.write_boolean(true).unwrap();
+/// writer.write_string("a").unwrap();
+/// writer.write_boolean(false).unwrap();
+/// writer.write_end_collection().unwrap();
+///
+/// let xml = String::from_utf8(writer.into_inner().into_inner()).unwrap();
+/// assert!(xml.find("<key>a</key>") < xml.find("<key>b</key>"));
+/// ```
+pub struct CanonicalWriter<W> {
+    writer: W,
+    buffer: ValueWriter,
+    graph: Option<GraphBuilder>,
+}
+
+impl<W: Writer> CanonicalWriter<W> {
+    /// Creates a `CanonicalWriter` which writes canonicalized plists to `writer`.
+    pub fn new(writer: W) -> CanonicalWriter<W> {
+        CanonicalWriter {
+            writer,
+            buffer: ValueWriter::new(),
+            graph: None,
+        }
+    }
+
+    /// Consumes the `CanonicalWriter`, returning the wrapped writer.
+    ///
+    /// Any events of an incomplete plist are discarded.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Writes a `GraphEvent`, for example one read by `BinaryReader::into_graph`.
+    ///
+    /// The canonical form does not share objects, so each `Reference` is expanded into a copy of
+    /// the object it refers to. The events of a single plist must all be written either with this
+    /// method or with the `Writer` methods.
+    ///
+    /// ```
+    /// use plist::stream::{BinaryReader, CanonicalWriter, XmlWriter};
+    ///
+    /// let bytes = std::fs::read("tests/data/binary.plist").unwrap();
+    /// let mut writer = CanonicalWriter::new(XmlWriter::new(Vec::new()));
+    /// for event in BinaryReader::from_slice(&bytes).into_graph() {
+    ///     writer.write_graph_event(&event.unwrap()).unwrap();
+    /// }
+    ///
+    /// let mut value = plist::Value::from_file("tests/data/binary.plist").unwrap();
+    /// value.canonicalize();
+    /// let xml = writer.into_inner().into_inner();
+    /// assert_eq!(plist::Value::from_reader_xml(&xml[..]).unwrap(), value);
+    /// ```
+    pub fn write_graph_event(&mut self, event: &GraphEvent<'_>) -> Result<(), Error> {
+        let graph = self.graph.get_or_insert_with(GraphBuilder::new);
+        if let Some(root) = graph.push(event.clone())? {
+            self.graph = None;
+            self.write_value(root.to_value())?;
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, mut value: Value) -> Result<(), Error> {
+        value.canonicalize();
+        for event in value.events() {
+            self.writer.write(&event)?;
+        }
+        Ok(())
+    }
+
+    /// Buffers a single `Writer` call. Once the root value is complete it is canonicalized and
+    /// written to the wrapped writer.
+    fn write_buffered<F>(&mut self, f: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut ValueWriter) -> Result<(), Error>,
+    {
+        f(&mut self.buffer)?;
+        match self.buffer.take_value() {
+            Some(value) => self.write_value(value),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<W: Writer> Writer for CanonicalWriter<W> {
+    fn write_start_array(&mut self, len: Option<u64>) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_start_array(len))
+    }
+
+    fn write_start_dictionary(&mut self, len: Option<u64>) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_start_dictionary(len))
+    }
+
+    fn write_end_collection(&mut self) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_end_collection())
+    }
+
+    fn write_boolean(&mut self, value: bool) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_boolean(value))
+    }
+
+    fn write_data(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_data(value))
+    }
+
+    fn write_date(&mut self, value: Date) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_date(value))
+    }
+
+    fn write_integer(&mut self, value: Integer) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_integer(value))
+    }
+
+    fn write_real(&mut self, value: f64) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_real(value))
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_string(value))
+    }
+
+    fn write_uid(&mut self, value: Uid) -> Result<(), Error> {
+        self.write_buffered(|buffer| buffer.write_uid(value))
+    }
+}
@@ -0,0 +1,164 @@
+use crate::error::{Error, ErrorKind, EventKind};
+
+/// Checks that a sequence of events forms valid plists.
+///
+/// `Writer` implementations call `validate` with the kind of each event before encoding it. The
+/// validator rejects unbalanced collections, dictionary keys which are not strings and
+/// dictionaries which end with a key but no value. A root value completes a plist, after which
+/// the next event starts a new plist.
+///
+/// ```
+/// use plist::{stream::EventValidator, EventKind};
+///
+/// let mut validator = EventValidator::new();
+/// validator.validate(EventKind::StartDictionary).unwrap();
+/// assert!(validator.expecting_key());
+/// assert!(validator.validate(EventKind::Integer).is_err());
+///
+/// validator.validate(EventKind::String).unwrap();
+/// validator.validate(EventKind::Integer).unwrap();
+/// validator.validate(EventKind::EndCollection).unwrap();
+/// assert_eq!(validator.depth(), 0);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct EventValidator {
+    stack: Vec<Collection>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Collection {
+    Array,
+    Dictionary { expecting_key: bool },
+}
+
+impl EventValidator {
+    /// Creates an `EventValidator` expecting the start of a plist.
+    pub fn new() -> EventValidator {
+        EventValidator { stack: Vec::new() }
+    }
+
+    /// Returns `true` if the next event must be a dictionary key or the end of a dictionary.
+    pub fn expecting_key(&self) -> bool {
+        matches!(
+            self.stack.last(),
+            Some(Collection::Dictionary {
+                expecting_key: true
+            })
+        )
+    }
+
+    /// Returns `true` if the innermost unclosed collection is a dictionary.
+    pub fn in_dictionary(&self) -> bool {
+        matches!(self.stack.last(), Some(Collection::Dictionary { .. }))
+    }
+
+    /// Returns the number of unclosed arrays and dictionaries. After a successful call to
+    /// `validate` a depth of zero means a plist has been completed.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Checks that an event of the given kind may come next and records it.
+    ///
+    /// On error the state of the validator is unchanged. `ValueOrStartCollection` and
+    /// `DictionaryKeyOrEndCollection` do not describe a single event and are always rejected.
+    pub fn validate(&mut self, kind: EventKind) -> Result<(), Error> {
+        match kind {
+            EventKind::EndCollection => match self.stack.last() {
+                Some(Collection::Array)
+                | Some(Collection::Dictionary {
+                    expecting_key: true,
+                }) => {
+                    self.stack.pop();
+                    self.value_completed();
+                }
+                _ => return Err(unexpected(EventKind::ValueOrStartCollection, kind)),
+            },
+            EventKind::String if self.expecting_key() => self.set_expecting_key(false),
+            _ if self.expecting_key() => {
+                return Err(unexpected(EventKind::DictionaryKeyOrEndCollection, kind))
+            }
+            EventKind::ValueOrStartCollection | EventKind::DictionaryKeyOrEndCollection => {
+                return Err(unexpected(EventKind::ValueOrStartCollection, kind))
+            }
+            EventKind::StartArray => self.stack.push(Collection::Array),
+            EventKind::StartDictionary => self.stack.push(Collection::Dictionary {
+                expecting_key: true,
+            }),
+            _ => self.value_completed(),
+        }
+        Ok(())
+    }
+
+    fn value_completed(&mut self) {
+        self.set_expecting_key(true);
+    }
+
+    fn set_expecting_key(&mut self, value: bool) {
+        if let Some(Collection::Dictionary { expecting_key }) = self.stack.last_mut() {
+            *expecting_key = value;
+        }
+    }
+}
+
+fn unexpected(expected: EventKind, found: EventKind) -> Error {
+    ErrorKind::UnexpectedEventType { expected, found }.without_position()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate_all(kinds: &[EventKind]) -> Result<EventValidator, Error> {
+        let mut validator = EventValidator::new();
+        for &kind in kinds {
+            validator.validate(kind)?;
+        }
+        Ok(validator)
+    }
+
+    #[test]
+    fn valid_sequences() {
+        use crate::EventKind::*;
+
+        let validator = validate_all(&[
+            StartDictionary,
+            String,
+            StartArray,
+            Integer,
+            StartDictionary,
+            EndCollection,
+            EndCollection,
+            String,
+            Data,
+            EndCollection,
+        ])
+        .unwrap();
+        assert_eq!(validator.depth(), 0);
+
+        // Multiple plists may be written one after another.
+        let validator = validate_all(&[Integer, StartArray, EndCollection, String]).unwrap();
+        assert_eq!(validator.depth(), 0);
+    }
+
+    #[test]
+    fn invalid_sequences() {
+        use crate::EventKind::*;
+
+        for kinds in &[
+            &[EndCollection][..],
+            &[StartDictionary, Integer],
+            &[StartDictionary, String, EndCollection],
+            &[StartDictionary, StartArray],
+            &[StartArray, ValueOrStartCollection],
+        ] {
+            assert!(validate_all(kinds).is_err(), "{:?}", kinds);
+        }
+
+        let mut validator = validate_all(&[StartDictionary, String]).unwrap();
+        assert!(validator.validate(EndCollection).is_err());
+        assert!(!validator.expecting_key());
+        validator.validate(Boolean).unwrap();
+        assert!(validator.expecting_key());
+    }
+}
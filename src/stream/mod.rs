@@ -1,10 +1,48 @@
 //! An abstraction of a plist file as a stream of events. Used to support multiple encodings.
+//!
+//! ```
+//! use std::io::Cursor;
+//! use plist::stream::{Event, Writer, XmlReader, XmlWriter};
+//!
+//! let plist = b"<plist><array><string>a</string><integer>1</integer></array></plist>";
+//! let mut writer = XmlWriter::new(Vec::new());
+//!
+//! for event in XmlReader::new(Cursor::new(&plist[..])) {
+//!     match event.unwrap() {
+//!         Event::String(s) => writer.write_string(&s.to_uppercase()).unwrap(),
+//!         event => writer.write(&event).unwrap(),
+//!     }
+//! }
+//!
+//! let xml = String::from_utf8(writer.into_inner()).unwrap();
+//! assert!(xml.contains("<string>A</string>"));
+//! ```
+
+mod ascii_writer;
+pub use self::ascii_writer::AsciiWriter;
 
 mod binary_reader;
-pub use self::binary_reader::BinaryReader;
+pub(crate) use self::binary_reader::is_binary_magic;
+pub use self::binary_reader::{BinaryReader, BorrowedBytes, GraphReader};
 
 mod binary_writer;
-pub use self::binary_writer::BinaryWriter;
+pub use self::binary_writer::{BinaryWriteOptions, BinaryWriter, Deduplication};
+
+mod canonical_writer;
+pub use self::canonical_writer::CanonicalWriter;
+
+mod event_validator;
+pub use self::event_validator::EventValidator;
+
+mod pretty_writer;
+pub use self::pretty_writer::{PrettyOptions, PrettyWriter};
+
+mod reader_options;
+pub(crate) use self::reader_options::Limiter;
+pub use self::reader_options::ReaderOptions;
+
+mod text_writer;
+pub(crate) use self::text_writer::TextWriter;
 
 mod xml_reader;
 pub use self::xml_reader::XmlReader;
@@ -12,14 +50,18 @@ pub use self::xml_reader::XmlReader;
 mod xml_writer;
 pub use self::xml_writer::XmlWriter;
 
+mod value_writer;
+pub(crate) use self::value_writer::ValueWriter;
+
 use std::{
+    borrow::Cow,
     io::{self, Read, Seek, SeekFrom},
-    vec,
+    slice, vec,
 };
 
 use crate::{
     dictionary,
-    error::{Error, ErrorKind},
+    error::{Error, ErrorKind, FilePosition},
     Date, Integer, Uid, Value,
 };
 
@@ -35,26 +77,91 @@ use crate::{
 /// Real(181.2)      // Value
 /// String("Age")    // Key
 /// Integer(28)      // Value
-/// EndDictionary
+/// EndCollection
 /// ```
+///
+/// Strings and data are borrowed from the source of the event where possible, for example by
+/// `Value::events` or a `BinaryReader` created with `BinaryReader::from_slice`. Use `into_owned`
+/// to detach an event from its source.
+///
+/// New variants may be added in minor releases.
 #[derive(Clone, Debug, PartialEq)]
-pub enum Event {
+#[non_exhaustive]
+pub enum Event<'a> {
     // While the length of an array or dict cannot be feasably greater than max(usize) this better
     // conveys the concept of an effectively unbounded event stream.
+    /// The start of an array with the given number of elements if known.
     StartArray(Option<u64>),
+    /// The start of a dictionary with the given number of entries if known.
     StartDictionary(Option<u64>),
+    /// The end of the most recently started array or dictionary.
     EndCollection,
 
     Boolean(bool),
-    Data(Vec<u8>),
+    Data(Cow<'a, [u8]>),
     Date(Date),
     Integer(Integer),
     Real(f64),
-    String(String),
+    String(Cow<'a, str>),
     Uid(Uid),
+}
 
-    #[doc(hidden)]
-    __Nonexhaustive,
+/// An `Event` which owns its strings and data.
+pub type OwnedEvent = Event<'static>;
+
+impl Event<'_> {
+    /// Converts the event into one which owns its strings and data, copying them if they are
+    /// borrowed.
+    pub fn into_owned(self) -> OwnedEvent {
+        match self {
+            Event::StartArray(len) => Event::StartArray(len),
+            Event::StartDictionary(len) => Event::StartDictionary(len),
+            Event::EndCollection => Event::EndCollection,
+            Event::Boolean(value) => Event::Boolean(value),
+            Event::Data(value) => Event::Data(Cow::Owned(value.into_owned())),
+            Event::Date(value) => Event::Date(value),
+            Event::Integer(value) => Event::Integer(value),
+            Event::Real(value) => Event::Real(value),
+            Event::String(value) => Event::String(Cow::Owned(value.into_owned())),
+            Event::Uid(value) => Event::Uid(value),
+        }
+    }
+}
+
+/// An event from a plist whose arrays, dictionaries and values may be shared by several parents,
+/// as in a binary plist.
+///
+/// Returned by `BinaryReader::into_graph` and `SharedValue::graph_events` and accepted by
+/// `BinaryWriter::write_graph_event` and `CanonicalWriter::write_graph_event`. The events of each
+/// object appear once. Later occurrences of the object are represented by a `Reference` to its id.
+/// Ids are only meaningful within a single plist.
+///
+/// New variants may be added in minor releases.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum GraphEvent<'a> {
+    /// The first occurrence of the object `id`. The start of an array or dictionary is followed by
+    /// the events of its contents and an `EndCollection` event.
+    Object { id: u64, event: Event<'a> },
+    /// The end of the most recently started array or dictionary.
+    EndCollection,
+    /// A further occurrence of the object `id`, whose events have already appeared in full.
+    Reference(u64),
+}
+
+impl GraphEvent<'_> {
+    /// Converts the event into one which owns its strings and data, copying them if they are
+    /// borrowed.
+    pub fn into_owned(self) -> GraphEvent<'static> {
+        match self {
+            GraphEvent::Object { id, event } => GraphEvent::Object {
+                id,
+                event: event.into_owned(),
+            },
+            GraphEvent::EndCollection => GraphEvent::EndCollection,
+            GraphEvent::Reference(id) => GraphEvent::Reference(id),
+        }
+    }
 }
 
 /// An `Event` stream returned by `Value::into_events`.
@@ -78,10 +185,10 @@ impl IntoEvents {
 }
 
 impl Iterator for IntoEvents {
-    type Item = Event;
+    type Item = OwnedEvent;
 
-    fn next(&mut self) -> Option<Event> {
-        fn handle_value(value: Value, stack: &mut Vec<StackItem>) -> Event {
+    fn next(&mut self) -> Option<OwnedEvent> {
+        fn handle_value(value: Value, stack: &mut Vec<StackItem>) -> OwnedEvent {
             match value {
                 Value::Array(array) => {
                     let len = array.len();
@@ -96,13 +203,12 @@ impl Iterator for IntoEvents {
                     Event::StartDictionary(Some(len as u64))
                 }
                 Value::Boolean(value) => Event::Boolean(value),
-                Value::Data(value) => Event::Data(value),
+                Value::Data(value) => Event::Data(Cow::Owned(value)),
                 Value::Date(value) => Event::Date(value),
                 Value::Real(value) => Event::Real(value),
                 Value::Integer(value) => Event::Integer(value),
-                Value::String(value) => Event::String(value),
+                Value::String(value) => Event::String(Cow::Owned(value)),
                 Value::Uid(value) => Event::Uid(value),
-                Value::__Nonexhaustive => unreachable!(),
             }
         }
 
@@ -124,7 +230,7 @@ impl Iterator for IntoEvents {
                     // The next event to be returned must be the dictionary value.
                     self.stack.push(StackItem::DictValue(value));
                     // Return the key event now.
-                    Event::String(key)
+                    Event::String(Cow::Owned(key))
                 } else {
                     Event::EndCollection
                 }
@@ -134,17 +240,115 @@ impl Iterator for IntoEvents {
     }
 }
 
-pub struct Reader<R: Read + Seek>(ReaderInner<R>);
+/// An `Event` stream returned by `Value::events` which borrows strings and data from the value.
+pub struct Events<'a> {
+    stack: Vec<BorrowedStackItem<'a>>,
+}
+
+enum BorrowedStackItem<'a> {
+    Root(&'a Value),
+    Array(slice::Iter<'a, Value>),
+    Dict(dictionary::Iter<'a>),
+    DictValue(&'a Value),
+}
+
+impl<'a> Events<'a> {
+    pub(crate) fn new(value: &'a Value) -> Events<'a> {
+        Events {
+            stack: vec![BorrowedStackItem::Root(value)],
+        }
+    }
+}
+
+impl<'a> Iterator for Events<'a> {
+    type Item = Event<'a>;
+
+    fn next(&mut self) -> Option<Event<'a>> {
+        fn handle_value<'a>(value: &'a Value, stack: &mut Vec<BorrowedStackItem<'a>>) -> Event<'a> {
+            match value {
+                Value::Array(array) => {
+                    stack.push(BorrowedStackItem::Array(array.iter()));
+                    Event::StartArray(Some(array.len() as u64))
+                }
+                Value::Dictionary(dict) => {
+                    stack.push(BorrowedStackItem::Dict(dict.iter()));
+                    Event::StartDictionary(Some(dict.len() as u64))
+                }
+                Value::Boolean(value) => Event::Boolean(*value),
+                Value::Data(value) => Event::Data(Cow::Borrowed(value)),
+                Value::Date(value) => Event::Date(*value),
+                Value::Real(value) => Event::Real(*value),
+                Value::Integer(value) => Event::Integer(*value),
+                Value::String(value) => Event::String(Cow::Borrowed(value)),
+                Value::Uid(value) => Event::Uid(*value),
+            }
+        }
+
+        Some(match self.stack.pop()? {
+            BorrowedStackItem::Root(value) => handle_value(value, &mut self.stack),
+            BorrowedStackItem::Array(mut array) => {
+                if let Some(value) = array.next() {
+                    self.stack.push(BorrowedStackItem::Array(array));
+                    handle_value(value, &mut self.stack)
+                } else {
+                    Event::EndCollection
+                }
+            }
+            BorrowedStackItem::Dict(mut dict) => {
+                if let Some((key, value)) = dict.next() {
+                    self.stack.push(BorrowedStackItem::Dict(dict));
+                    self.stack.push(BorrowedStackItem::DictValue(value));
+                    Event::String(Cow::Borrowed(key))
+                } else {
+                    Event::EndCollection
+                }
+            }
+            BorrowedStackItem::DictValue(value) => handle_value(value, &mut self.stack),
+        })
+    }
+}
+
+/// Reads events from a plist of any encoding.
+///
+/// The encoding is detected when the first event is read. Binary plists are read with a
+/// `BinaryReader` and all others with an `XmlReader`.
+pub struct Reader<R: Read + Seek> {
+    inner: ReaderInner<R>,
+    options: ReaderOptions,
+    recover_errors: bool,
+}
 
 enum ReaderInner<R: Read + Seek> {
     Uninitialized(Option<R>),
-    Xml(XmlReader<R>),
-    Binary(BinaryReader<R>),
+    Xml(Box<XmlReader<R>>),
+    Binary(Box<BinaryReader<R>>),
 }
 
 impl<R: Read + Seek> Reader<R> {
+    /// Creates a `Reader` which detects the encoding of the plist in `reader`.
     pub fn new(reader: R) -> Reader<R> {
-        Reader(ReaderInner::Uninitialized(Some(reader)))
+        Reader::new_with_options(reader, ReaderOptions::new())
+    }
+
+    /// Creates a `Reader` which detects the encoding of the plist in `reader` and stops with an
+    /// error if the plist exceeds the limits in `options`.
+    pub fn new_with_options(reader: R, options: ReaderOptions) -> Reader<R> {
+        Reader {
+            inner: ReaderInner::Uninitialized(Some(reader)),
+            options,
+            recover_errors: false,
+        }
+    }
+
+    /// Continues reading after recoverable errors instead of finishing. See
+    /// `XmlReader::set_recover_errors` and `BinaryReader::set_recover_errors`.
+    pub(crate) fn set_recover_errors(&mut self, recover_errors: bool) {
+        self.recover_errors = recover_errors;
+        match &mut self.inner {
+            ReaderInner::Uninitialized(_) => (),
+            ReaderInner::Xml(parser) => parser.set_recover_errors(recover_errors),
+            ReaderInner::Binary(parser) => parser.set_recover_errors(recover_errors),
+        }
     }
 
     fn is_binary(reader: &mut R) -> Result<bool, Error> {
@@ -157,38 +361,136 @@ impl<R: Read + Seek> Reader<R> {
         reader.read_exact(&mut magic).map_err(from_io_offset_0)?;
         reader.seek(SeekFrom::Start(0)).map_err(from_io_offset_0)?;
 
-        Ok(&magic == b"bplist00")
+        Ok(is_binary_magic(&magic))
     }
 }
 
 impl<R: Read + Seek> Iterator for Reader<R> {
-    type Item = Result<Event, Error>;
+    type Item = Result<OwnedEvent, Error>;
 
-    fn next(&mut self) -> Option<Result<Event, Error>> {
-        let mut reader = match self.0 {
+    fn next(&mut self) -> Option<Result<OwnedEvent, Error>> {
+        let mut reader = match self.inner {
             ReaderInner::Xml(ref mut parser) => return parser.next(),
             ReaderInner::Binary(ref mut parser) => return parser.next(),
-            ReaderInner::Uninitialized(ref mut reader) => reader.take().unwrap(),
+            ReaderInner::Uninitialized(ref mut reader) => reader.take()?,
         };
 
-        let event_reader = match Reader::is_binary(&mut reader) {
-            Ok(true) => ReaderInner::Binary(BinaryReader::new(reader)),
-            Ok(false) => ReaderInner::Xml(XmlReader::new(reader)),
+        let mut event_reader = match Reader::is_binary(&mut reader) {
+            Ok(true) => ReaderInner::Binary(Box::new(BinaryReader::new_with_options(
+                reader,
+                self.options.clone(),
+            ))),
+            Ok(false) => ReaderInner::Xml(Box::new(XmlReader::new_with_options(
+                reader,
+                self.options.clone(),
+            ))),
             Err(err) => {
-                ::std::mem::replace(&mut self.0, ReaderInner::Uninitialized(Some(reader)));
+                // When recovering from errors the reader is finished as retrying would report the
+                // same error forever.
+                if !self.recover_errors {
+                    self.inner = ReaderInner::Uninitialized(Some(reader));
+                }
                 return Some(Err(err));
             }
         };
 
-        ::std::mem::replace(&mut self.0, event_reader);
+        match &mut event_reader {
+            ReaderInner::Uninitialized(_) => unreachable!(),
+            ReaderInner::Xml(parser) => parser.set_recover_errors(self.recover_errors),
+            ReaderInner::Binary(parser) => parser.set_recover_errors(self.recover_errors),
+        }
+        self.inner = event_reader;
 
         self.next()
     }
 }
 
+impl<R: Read + Seek> EventPosition for Reader<R> {
+    fn event_position(&self) -> Option<FilePosition> {
+        match &self.inner {
+            ReaderInner::Uninitialized(_) => None,
+            ReaderInner::Xml(parser) => parser.event_position(),
+            ReaderInner::Binary(parser) => parser.event_position(),
+        }
+    }
+}
+
+/// Reports where in the input the most recently read event started.
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+pub(crate) trait EventPosition {
+    fn event_position(&self) -> Option<FilePosition>;
+}
+
 /// Supports writing event streams in different plist encodings.
-pub trait Writer: private::Sealed {
-    fn write(&mut self, event: &Event) -> Result<(), Error> {
+///
+/// Implementations can be used with `Serializer` or fed the events of a `Value` with
+/// `Value::events`. Use an `EventValidator` to reject event sequences which do not form a valid
+/// plist.
+///
+/// ```
+/// use plist::{
+///     stream::{EventValidator, Writer},
+///     Date, Error, EventKind, Integer, Uid, Value,
+/// };
+///
+/// /// Counts the values in a plist.
+/// #[derive(Default)]
+/// struct CountingWriter {
+///     validator: EventValidator,
+///     values: usize,
+/// }
+///
+/// impl CountingWriter {
+///     fn count(&mut self, kind: EventKind) -> Result<(), Error> {
+///         self.validator.validate(kind)?;
+///         self.values += 1;
+///         Ok(())
+///     }
+/// }
+///
+/// impl Writer for CountingWriter {
+///     fn write_start_array(&mut self, _len: Option<u64>) -> Result<(), Error> {
+///         self.count(EventKind::StartArray)
+///     }
+///     fn write_start_dictionary(&mut self, _len: Option<u64>) -> Result<(), Error> {
+///         self.count(EventKind::StartDictionary)
+///     }
+///     fn write_end_collection(&mut self) -> Result<(), Error> {
+///         self.validator.validate(EventKind::EndCollection)
+///     }
+///     fn write_boolean(&mut self, _value: bool) -> Result<(), Error> {
+///         self.count(EventKind::Boolean)
+///     }
+///     fn write_data(&mut self, _value: &[u8]) -> Result<(), Error> {
+///         self.count(EventKind::Data)
+///     }
+///     fn write_date(&mut self, _value: Date) -> Result<(), Error> {
+///         self.count(EventKind::Date)
+///     }
+///     fn write_integer(&mut self, _value: Integer) -> Result<(), Error> {
+///         self.count(EventKind::Integer)
+///     }
+///     fn write_real(&mut self, _value: f64) -> Result<(), Error> {
+///         self.count(EventKind::Real)
+///     }
+///     fn write_string(&mut self, _value: &str) -> Result<(), Error> {
+///         self.count(EventKind::String)
+///     }
+///     fn write_uid(&mut self, _value: Uid) -> Result<(), Error> {
+///         self.count(EventKind::Uid)
+///     }
+/// }
+///
+/// let value = Value::Array(vec![1.into(), "a".into()]);
+/// let mut writer = CountingWriter::default();
+/// for event in value.events() {
+///     writer.write(&event).unwrap();
+/// }
+/// assert_eq!(writer.values, 3);
+/// assert!(writer.write_end_collection().is_err());
+/// ```
+pub trait Writer {
+    fn write(&mut self, event: &Event<'_>) -> Result<(), Error> {
         match event {
             Event::StartArray(len) => self.write_start_array(*len),
             Event::StartDictionary(len) => self.write_start_dictionary(*len),
@@ -200,7 +502,6 @@ pub trait Writer: private::Sealed {
             Event::Real(value) => self.write_real(*value),
             Event::String(value) => self.write_string(value),
             Event::Uid(value) => self.write_uid(*value),
-            Event::__Nonexhaustive => unreachable!(),
         }
     }
 
@@ -216,12 +517,3 @@ pub trait Writer: private::Sealed {
     fn write_string(&mut self, value: &str) -> Result<(), Error>;
     fn write_uid(&mut self, value: Uid) -> Result<(), Error>;
 }
-
-pub(crate) mod private {
-    use std::io::Write;
-
-    pub trait Sealed {}
-
-    impl<W: Write> Sealed for super::BinaryWriter<W> {}
-    impl<W: Write> Sealed for super::XmlWriter<W> {}
-}
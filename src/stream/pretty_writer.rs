@@ -0,0 +1,296 @@
+use std::{fmt::Write as _, io::Write};
+
+use crate::{
+    error::{Error, EventKind},
+    stream::{TextWriter, Writer},
+    Date, Integer, Uid,
+};
+
+/// Options controlling how much of a plist a `PrettyWriter` prints.
+///
+/// By default nothing is truncated.
+#[derive(Clone, Debug, Default)]
+pub struct PrettyOptions {
+    max_string_len: Option<usize>,
+    max_data_len: Option<usize>,
+    max_depth: Option<usize>,
+}
+
+impl PrettyOptions {
+    /// Returns the default options.
+    pub fn new() -> PrettyOptions {
+        PrettyOptions::default()
+    }
+
+    /// Sets the number of characters of a string or dictionary key printed before it is
+    /// truncated with `…`.
+    pub fn max_string_len(mut self, len: usize) -> Self {
+        self.max_string_len = Some(len);
+        self
+    }
+
+    /// Sets the number of bytes of data printed before it is truncated with `…`.
+    pub fn max_data_len(mut self, len: usize) -> Self {
+        self.max_data_len = Some(len);
+        self
+    }
+
+    /// Sets the number of nested arrays and dictionaries printed. Deeper collections are printed
+    /// as `[…]` or `{…}`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+}
+
+/// Writes events as human readable text in the style of `plutil -p`.
+///
+/// ```text
+/// {
+///   "Author" => "William Shakespeare"
+///   "Lines" => [
+///     0 => "It is a tale told by an idiot,"
+///     1 => "Full of sound and fury, signifying nothing."
+///   ]
+///   "Data" => <0000be00>
+///   "Birthdate" => 1981-05-16T11:32:06Z
+/// }
+/// ```
+///
+/// Each plist is followed by a line ending. The output is intended for people and cannot be read
+/// back.
+pub struct PrettyWriter<W: Write> {
+    writer: TextWriter<W>,
+    options: PrettyOptions,
+    /// The number of unclosed collections nested too deeply to be printed.
+    skipping: usize,
+}
+
+impl<W: Write> PrettyWriter<W> {
+    /// Creates a `PrettyWriter` which prints plists in full.
+    pub fn new(writer: W) -> PrettyWriter<W> {
+        PrettyWriter::new_with_options(writer, PrettyOptions::new())
+    }
+
+    /// Creates a `PrettyWriter` using the given options.
+    pub fn new_with_options(writer: W, options: PrettyOptions) -> PrettyWriter<W> {
+        PrettyWriter {
+            writer: TextWriter::new(writer, "  "),
+            options,
+            skipping: 0,
+        }
+    }
+
+    /// Consumes the `PrettyWriter`, returning the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner()
+    }
+
+    /// Writes the array index preceding a value. Dictionary keys are written by `write_string`.
+    fn start_value(&mut self) -> Result<(), Error> {
+        match self.writer.start_array_element("")? {
+            Some(index) => self.writer.write_str(&format!("{} => ", index)),
+            None => Ok(()),
+        }
+    }
+
+    fn write_value(&mut self, kind: EventKind, text: &str) -> Result<(), Error> {
+        self.writer.validator.validate(kind)?;
+        if self.skipping > 0 {
+            return Ok(());
+        }
+        self.start_value()?;
+        self.writer.write_str(text)?;
+        self.writer.end_value("")
+    }
+
+    fn start_collection(&mut self, dictionary: bool) -> Result<(), Error> {
+        self.writer.validator.validate(if dictionary {
+            EventKind::StartDictionary
+        } else {
+            EventKind::StartArray
+        })?;
+        if self.skipping > 0 {
+            self.skipping += 1;
+            return Ok(());
+        }
+
+        self.start_value()?;
+        if self.options.max_depth == Some(self.writer.depth()) {
+            self.skipping = 1;
+            return self
+                .writer
+                .write_str(if dictionary { "{…}" } else { "[…]" });
+        }
+        self.writer
+            .push_collection(dictionary, if dictionary { "{" } else { "[" })
+    }
+
+    fn quote(&self, value: &str) -> String {
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        let max_len = self.options.max_string_len.unwrap_or(usize::MAX);
+        for (i, c) in value.chars().enumerate() {
+            if i == max_len {
+                quoted.push('…');
+                break;
+            }
+            quoted.extend(c.escape_debug());
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
+impl<W: Write> Writer for PrettyWriter<W> {
+    fn write_start_array(&mut self, _len: Option<u64>) -> Result<(), Error> {
+        self.start_collection(false)
+    }
+
+    fn write_start_dictionary(&mut self, _len: Option<u64>) -> Result<(), Error> {
+        self.start_collection(true)
+    }
+
+    fn write_end_collection(&mut self) -> Result<(), Error> {
+        self.writer.validator.validate(EventKind::EndCollection)?;
+        if self.skipping > 0 {
+            self.skipping -= 1;
+            if self.skipping == 0 {
+                self.writer.end_value("")?;
+            }
+            return Ok(());
+        }
+
+        self.writer.pop_collection("]", "}")?;
+        self.writer.end_value("")
+    }
+
+    fn write_boolean(&mut self, value: bool) -> Result<(), Error> {
+        self.write_value(EventKind::Boolean, if value { "true" } else { "false" })
+    }
+
+    fn write_data(&mut self, value: &[u8]) -> Result<(), Error> {
+        let max_len = self.options.max_data_len.unwrap_or(usize::MAX);
+        let mut text = String::with_capacity(2 + 2 * value.len().min(max_len));
+        text.push('<');
+        for byte in value.iter().take(max_len) {
+            write!(text, "{:02x}", byte).expect("writing to a string cannot fail");
+        }
+        if value.len() > max_len {
+            text.push('…');
+        }
+        text.push('>');
+        self.write_value(EventKind::Data, &text)
+    }
+
+    fn write_date(&mut self, value: Date) -> Result<(), Error> {
+        self.write_value(EventKind::Date, &value.to_rfc3339())
+    }
+
+    fn write_integer(&mut self, value: Integer) -> Result<(), Error> {
+        self.write_value(EventKind::Integer, &value.to_string())
+    }
+
+    fn write_real(&mut self, value: f64) -> Result<(), Error> {
+        self.write_value(EventKind::Real, &value.to_string())
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<(), Error> {
+        if !self.writer.validator.expecting_key() {
+            return self.write_value(EventKind::String, &self.quote(value));
+        }
+
+        self.writer.validator.validate(EventKind::String)?;
+        if self.skipping > 0 {
+            return Ok(());
+        }
+        self.writer.start_key()?;
+        let key = self.quote(value);
+        self.writer.write_str(&key)?;
+        self.writer.write_str(" => ")
+    }
+
+    fn write_uid(&mut self, value: Uid) -> Result<(), Error> {
+        self.write_value(EventKind::Uid, &format!("Uid({})", value.get()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dictionary, Value};
+
+    fn pretty(value: &Value, options: PrettyOptions) -> String {
+        let mut writer = PrettyWriter::new_with_options(Vec::new(), options);
+        for event in value.events() {
+            writer.write(&event).unwrap();
+        }
+        String::from_utf8(writer.into_inner()).unwrap()
+    }
+
+    fn sample() -> Value {
+        let mut inner = Dictionary::new();
+        inner.insert("uid".to_owned(), Value::Uid(Uid::new(7)));
+        inner.insert("empty".to_owned(), Value::Array(Vec::new()));
+
+        let mut dict = Dictionary::new();
+        dict.insert("name".to_owned(), "Say \"hi\"\n".into());
+        dict.insert("n".to_owned(), 3.into());
+        dict.insert("real".to_owned(), 1.5.into());
+        dict.insert("ok".to_owned(), true.into());
+        dict.insert("data".to_owned(), Value::Data(vec![0x0f, 0xbd, 0x01, 0x02]));
+        dict.insert(
+            "date".to_owned(),
+            Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap().into(),
+        );
+        dict.insert(
+            "list".to_owned(),
+            Value::Array(vec!["a".into(), Value::Dictionary(inner)]),
+        );
+        Value::Dictionary(dict)
+    }
+
+    #[test]
+    fn plutil_style() {
+        let expected = r#"{
+  "name" => "Say \"hi\"\n"
+  "n" => 3
+  "real" => 1.5
+  "ok" => true
+  "data" => <0fbd0102>
+  "date" => 1981-05-16T11:32:06Z
+  "list" => [
+    0 => "a"
+    1 => {
+      "uid" => Uid(7)
+      "empty" => []
+    }
+  ]
+}
+"#;
+        assert_eq!(pretty(&sample(), PrettyOptions::new()), expected);
+    }
+
+    #[test]
+    fn truncation() {
+        let options = PrettyOptions::new()
+            .max_string_len(3)
+            .max_data_len(2)
+            .max_depth(1);
+        let expected = r#"{
+  "nam…" => "Say…"
+  "n" => 3
+  "rea…" => 1.5
+  "ok" => true
+  "dat…" => <0fbd…>
+  "dat…" => 1981-05-16T11:32:06Z
+  "lis…" => […]
+}
+"#;
+        assert_eq!(pretty(&sample(), options), expected);
+
+        let options = PrettyOptions::new().max_depth(0);
+        assert_eq!(pretty(&sample(), options), "{…}\n");
+        assert_eq!(pretty(&Value::from(1), PrettyOptions::new()), "1\n");
+    }
+}
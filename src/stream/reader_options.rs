@@ -0,0 +1,178 @@
+use crate::{error::ErrorKind, stream::Event};
+
+/// Limits on the plists accepted by a reader, for reading untrusted input.
+///
+/// By default only the nesting depth is limited, to `DEFAULT_MAX_DEPTH`. A reader stops with an
+/// error as soon as a limit is exceeded.
+///
+/// A `BinaryReader` checks the length of each string and data object before reading it. An
+/// `XmlReader` can only check the length of a string or data value once the XML parser has read
+/// the whole text of the element into memory, so when reading untrusted XML also limit the size of
+/// the input itself, for example with `Read::take`.
+///
+/// ```
+/// use plist::{stream::ReaderOptions, ErrorKind, Value};
+/// use std::io::Cursor;
+///
+/// let plist = b"<plist><array><array><array/></array></array></plist>";
+/// let options = ReaderOptions::new().max_depth(2);
+/// let err = Value::from_reader_with_options(Cursor::new(&plist[..]), options).unwrap_err();
+/// assert!(matches!(err.kind(), ErrorKind::DepthLimitExceeded));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReaderOptions {
+    max_depth: usize,
+    max_objects: Option<u64>,
+    max_string_len: Option<u64>,
+    max_data_len: Option<u64>,
+    max_total_bytes: Option<u64>,
+}
+
+impl Default for ReaderOptions {
+    fn default() -> Self {
+        ReaderOptions {
+            max_depth: ReaderOptions::DEFAULT_MAX_DEPTH,
+            max_objects: None,
+            max_string_len: None,
+            max_data_len: None,
+            max_total_bytes: None,
+        }
+    }
+}
+
+impl ReaderOptions {
+    /// The number of arrays and dictionaries which may be nested inside each other by default.
+    /// Building a `Value` recurses once for each level of nesting, so deeper plists could
+    /// overflow the stack.
+    pub const DEFAULT_MAX_DEPTH: usize = 256;
+
+    /// Returns the default options, which only limit the nesting depth.
+    pub fn new() -> ReaderOptions {
+        ReaderOptions::default()
+    }
+
+    /// Sets the number of arrays and dictionaries which may be nested inside each other. Defaults
+    /// to `DEFAULT_MAX_DEPTH`.
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Sets the number of values, arrays and dictionaries, including dictionary keys, which may be
+    /// read. An object referenced from several places in a binary plist is counted each time it is
+    /// read, or once for each further reference when read by a `GraphReader`.
+    pub fn max_objects(mut self, count: u64) -> Self {
+        self.max_objects = Some(count);
+        self
+    }
+
+    /// Sets the length in bytes, once decoded as UTF-8, of the longest string which may be read.
+    pub fn max_string_len(mut self, len: u64) -> Self {
+        self.max_string_len = Some(len);
+        self
+    }
+
+    /// Sets the length in bytes of the longest data which may be read.
+    pub fn max_data_len(mut self, len: u64) -> Self {
+        self.max_data_len = Some(len);
+        self
+    }
+
+    /// Sets the total length in bytes of all strings and data which may be read.
+    pub fn max_total_bytes(mut self, len: u64) -> Self {
+        self.max_total_bytes = Some(len);
+        self
+    }
+}
+
+impl ReaderOptions {
+    pub(crate) fn depth_limit(&self) -> usize {
+        self.max_depth
+    }
+}
+
+/// Tracks the events read from a plist and checks them against the limits in `ReaderOptions`.
+pub(crate) struct Limiter {
+    options: ReaderOptions,
+    depth: usize,
+    objects: u64,
+    total_bytes: u64,
+}
+
+impl Limiter {
+    pub(crate) fn new(options: ReaderOptions) -> Limiter {
+        Limiter {
+            options,
+            depth: 0,
+            objects: 0,
+            total_bytes: 0,
+        }
+    }
+
+    /// Checks that a string of at least `len` bytes may be read.
+    pub(crate) fn check_string_len(&self, len: u64) -> Result<(), ErrorKind> {
+        if exceeds(len, self.options.max_string_len) {
+            return Err(ErrorKind::StringLengthLimitExceeded);
+        }
+        self.check_total_bytes(len)
+    }
+
+    /// Checks that data of `len` bytes may be read.
+    pub(crate) fn check_data_len(&self, len: u64) -> Result<(), ErrorKind> {
+        if exceeds(len, self.options.max_data_len) {
+            return Err(ErrorKind::DataLengthLimitExceeded);
+        }
+        self.check_total_bytes(len)
+    }
+
+    fn check_total_bytes(&self, len: u64) -> Result<(), ErrorKind> {
+        if exceeds(
+            self.total_bytes.saturating_add(len),
+            self.options.max_total_bytes,
+        ) {
+            return Err(ErrorKind::TotalBytesLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Records an event which has been read, checking that it is within the limits.
+    pub(crate) fn check_event(&mut self, event: &Event<'_>) -> Result<(), ErrorKind> {
+        if let Event::EndCollection = event {
+            self.depth = self.depth.saturating_sub(1);
+            return Ok(());
+        }
+
+        self.check_object()?;
+        match event {
+            Event::StartArray(_) | Event::StartDictionary(_) => {
+                self.depth += 1;
+                if self.depth > self.options.max_depth {
+                    return Err(ErrorKind::DepthLimitExceeded);
+                }
+            }
+            Event::String(s) => {
+                self.check_string_len(s.len() as u64)?;
+                self.total_bytes += s.len() as u64;
+            }
+            Event::Data(d) => {
+                self.check_data_len(d.len() as u64)?;
+                self.total_bytes += d.len() as u64;
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Records an object which has been read, checking that it is within the object limit.
+    pub(crate) fn check_object(&mut self) -> Result<(), ErrorKind> {
+        self.objects += 1;
+        if exceeds(self.objects, self.options.max_objects) {
+            return Err(ErrorKind::ObjectLimitExceeded);
+        }
+        Ok(())
+    }
+}
+
+fn exceeds(value: u64, limit: Option<u64>) -> bool {
+    matches!(limit, Some(limit) if value > limit)
+}
@@ -0,0 +1,121 @@
+use std::io::Write;
+
+use crate::{
+    error::{self, Error},
+    stream::EventValidator,
+};
+
+struct Level {
+    dictionary: bool,
+    /// The number of array elements or dictionary keys written so far.
+    len: u64,
+}
+
+/// The indentation and collection tracking shared by the writers which print plists as indented
+/// text, one array element or dictionary entry per line.
+pub(crate) struct TextWriter<W: Write> {
+    writer: W,
+    indent: &'static str,
+    pub validator: EventValidator,
+    stack: Vec<Level>,
+}
+
+impl<W: Write> TextWriter<W> {
+    pub fn new(writer: W, indent: &'static str) -> TextWriter<W> {
+        TextWriter {
+            writer,
+            indent,
+            validator: EventValidator::new(),
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Returns the number of open collections.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn write_str(&mut self, s: &str) -> Result<(), Error> {
+        self.writer
+            .write_all(s.as_bytes())
+            .map_err(error::from_io_without_position)
+    }
+
+    fn write_line_start(&mut self, depth: usize) -> Result<(), Error> {
+        self.write_str("\n")?;
+        for _ in 0..depth {
+            self.write_str(self.indent)?;
+        }
+        Ok(())
+    }
+
+    /// Starts a new line for the next array element, preceded by `separator` unless it is the
+    /// first element. Returns the index of the element, or `None` if the value is not in an array.
+    pub fn start_array_element(&mut self, separator: &str) -> Result<Option<u64>, Error> {
+        let depth = self.stack.len();
+        match self.stack.last_mut() {
+            Some(level) if !level.dictionary => {
+                let index = level.len;
+                level.len += 1;
+                if index > 0 {
+                    self.write_str(separator)?;
+                }
+                self.write_line_start(depth)?;
+                Ok(Some(index))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Starts a new line for the next dictionary key.
+    pub fn start_key(&mut self) -> Result<(), Error> {
+        let depth = self.stack.len();
+        if let Some(level) = self.stack.last_mut() {
+            level.len += 1;
+        }
+        self.write_line_start(depth)
+    }
+
+    /// Writes `terminator` after a dictionary value, or a line ending after a root value.
+    pub fn end_value(&mut self, terminator: &str) -> Result<(), Error> {
+        match self.stack.last() {
+            Some(level) if level.dictionary => self.write_str(terminator),
+            Some(_) => Ok(()),
+            None => {
+                self.write_str("\n")?;
+                self.writer.flush().map_err(error::from_io_without_position)
+            }
+        }
+    }
+
+    /// Opens a collection with `open` once its start event has been validated.
+    pub fn push_collection(&mut self, dictionary: bool, open: &str) -> Result<(), Error> {
+        self.stack.push(Level { dictionary, len: 0 });
+        self.write_str(open)
+    }
+
+    /// Closes the innermost collection with `array_close` or `dictionary_close`, written on a new
+    /// line unless the collection is empty.
+    pub fn pop_collection(
+        &mut self,
+        array_close: &str,
+        dictionary_close: &str,
+    ) -> Result<(), Error> {
+        let level = self
+            .stack
+            .pop()
+            .expect("the validator only accepts the end of an open collection");
+        if level.len > 0 {
+            self.write_line_start(self.stack.len())?;
+        }
+        self.write_str(if level.dictionary {
+            dictionary_close
+        } else {
+            array_close
+        })
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    error::{Error, EventKind},
+    stream::{EventValidator, Writer},
+    Date, Dictionary, Integer, Uid, Value,
+};
+
+/// Builds a `Value` from a stream of `Writer` calls.
+///
+/// The completed value can be retrieved with `take_value` once the root value has been written.
+pub(crate) struct ValueWriter {
+    validator: EventValidator,
+    stack: Vec<Collection>,
+    value: Option<Value>,
+}
+
+enum Collection {
+    Array(Vec<Value>),
+    Dictionary {
+        dict: Dictionary,
+        pending_key: Option<String>,
+    },
+}
+
+impl ValueWriter {
+    pub fn new() -> ValueWriter {
+        ValueWriter {
+            validator: EventValidator::new(),
+            stack: Vec::new(),
+            value: None,
+        }
+    }
+
+    /// Returns the root value if it has been completely written.
+    pub fn take_value(&mut self) -> Option<Value> {
+        self.value.take()
+    }
+
+    fn start_collection(&mut self, collection: Collection, kind: EventKind) -> Result<(), Error> {
+        self.validator.validate(kind)?;
+        self.stack.push(collection);
+        Ok(())
+    }
+
+    fn write_leaf(&mut self, value: Value) -> Result<(), Error> {
+        self.validator.validate(value_event_kind(&value))?;
+        self.write_value(value);
+        Ok(())
+    }
+
+    fn write_value(&mut self, value: Value) {
+        match self.stack.last_mut() {
+            Some(Collection::Array(array)) => array.push(value),
+            Some(Collection::Dictionary { dict, pending_key }) => match (pending_key.take(), value)
+            {
+                (Some(key), value) => {
+                    dict.insert(key, value);
+                }
+                (None, Value::String(key)) => *pending_key = Some(key),
+                (None, _) => unreachable!("the validator only accepts string dictionary keys"),
+            },
+            None => self.value = Some(value),
+        }
+    }
+}
+
+impl Writer for ValueWriter {
+    fn write_start_array(&mut self, len: Option<u64>) -> Result<(), Error> {
+        let capacity = len.and_then(crate::u64_to_usize).unwrap_or(0);
+        self.start_collection(
+            Collection::Array(Vec::with_capacity(capacity)),
+            EventKind::StartArray,
+        )
+    }
+
+    fn write_start_dictionary(&mut self, _len: Option<u64>) -> Result<(), Error> {
+        self.start_collection(
+            Collection::Dictionary {
+                dict: Dictionary::new(),
+                pending_key: None,
+            },
+            EventKind::StartDictionary,
+        )
+    }
+
+    fn write_end_collection(&mut self) -> Result<(), Error> {
+        self.validator.validate(EventKind::EndCollection)?;
+        let value = match self.stack.pop() {
+            Some(Collection::Array(array)) => Value::Array(array),
+            Some(Collection::Dictionary { dict, .. }) => Value::Dictionary(dict),
+            None => unreachable!("the validator only accepts the end of an open collection"),
+        };
+        self.write_value(value);
+        Ok(())
+    }
+
+    fn write_boolean(&mut self, value: bool) -> Result<(), Error> {
+        self.write_leaf(Value::Boolean(value))
+    }
+
+    fn write_data(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.write_leaf(Value::Data(value.to_owned()))
+    }
+
+    fn write_date(&mut self, value: Date) -> Result<(), Error> {
+        self.write_leaf(Value::Date(value))
+    }
+
+    fn write_integer(&mut self, value: Integer) -> Result<(), Error> {
+        self.write_leaf(Value::Integer(value))
+    }
+
+    fn write_real(&mut self, value: f64) -> Result<(), Error> {
+        self.write_leaf(Value::Real(value))
+    }
+
+    fn write_string(&mut self, value: &str) -> Result<(), Error> {
+        self.write_leaf(Value::String(value.to_owned()))
+    }
+
+    fn write_uid(&mut self, value: Uid) -> Result<(), Error> {
+        self.write_leaf(Value::Uid(value))
+    }
+}
+
+fn value_event_kind(value: &Value) -> EventKind {
+    match value {
+        Value::Array(_) => EventKind::StartArray,
+        Value::Dictionary(_) => EventKind::StartDictionary,
+        Value::Boolean(_) => EventKind::Boolean,
+        Value::Data(_) => EventKind::Data,
+        Value::Date(_) => EventKind::Date,
+        Value::Integer(_) => EventKind::Integer,
+        Value::Real(_) => EventKind::Real,
+        Value::String(_) => EventKind::String,
+        Value::Uid(_) => EventKind::Uid,
+    }
+}
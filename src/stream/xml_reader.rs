@@ -1,10 +1,9 @@
-use base64;
 use std::{
     io::{self, Read},
     str::FromStr,
 };
 use xml_rs::{
-    common::{is_whitespace_str, Position},
+    common::{is_whitespace_str, Position, TextPosition},
     reader::{
         Error as XmlReaderError, ErrorKind as XmlReaderErrorKind, EventReader, ParserConfig,
         XmlEvent,
@@ -13,19 +12,32 @@ use xml_rs::{
 
 use crate::{
     error::{Error, ErrorKind, FilePosition},
-    stream::Event,
+    stream::{Event, EventPosition, Limiter, OwnedEvent, ReaderOptions},
     Date, Integer,
 };
 
+/// Reads events from an XML encoded plist.
 pub struct XmlReader<R: Read> {
     xml_reader: EventReader<R>,
     queued_event: Option<XmlEvent>,
     element_stack: Vec<String>,
+    event_position: Option<TextPosition>,
+    recover_errors: bool,
+    skip_unknown_element: bool,
+    limiter: Limiter,
     finished: bool,
 }
 
 impl<R: Read> XmlReader<R> {
+    /// Creates an `XmlReader` reading from `reader`.
     pub fn new(reader: R) -> XmlReader<R> {
+        XmlReader::new_with_options(reader, ReaderOptions::new())
+    }
+
+    /// Creates an `XmlReader` reading from `reader` which stops with an error if the plist
+    /// exceeds the limits in `options`. Strings and data are checked once the text of their
+    /// element has been read.
+    pub fn new_with_options(reader: R, options: ReaderOptions) -> XmlReader<R> {
         let config = ParserConfig::new()
             .trim_whitespace(false)
             .whitespace_to_characters(true)
@@ -37,10 +49,41 @@ impl<R: Read> XmlReader<R> {
             xml_reader: EventReader::new_with_config(reader, config),
             queued_event: None,
             element_stack: Vec::new(),
+            event_position: None,
+            recover_errors: false,
+            skip_unknown_element: false,
+            limiter: Limiter::new(options),
             finished: false,
         }
     }
 
+    /// Continues reading after an invalid value, an unknown element or unexpected characters
+    /// instead of finishing. The problem is reported as an error and the offending content is
+    /// skipped. Malformed XML still finishes the plist.
+    pub(crate) fn set_recover_errors(&mut self, recover_errors: bool) {
+        self.recover_errors = recover_errors;
+    }
+
+    /// Skips the remainder of the most recently opened element including any children.
+    fn skip_element(&mut self) -> Result<(), Error> {
+        let mut depth = 0;
+        loop {
+            match self.next_event() {
+                Ok(XmlEvent::StartElement { .. }) => depth += 1,
+                Ok(XmlEvent::EndElement { .. }) if depth == 0 => {
+                    self.element_stack.pop();
+                    return Ok(());
+                }
+                Ok(XmlEvent::EndElement { .. }) => depth -= 1,
+                Ok(XmlEvent::EndDocument) => {
+                    return Err(self.with_pos(ErrorKind::UnclosedXmlElement))
+                }
+                Ok(_) => (),
+                Err(err) => return Err(from_xml_error(err)),
+            }
+        }
+    }
+
     fn read_content(&mut self) -> Result<String, Error> {
         loop {
             match self.xml_reader.next() {
@@ -75,11 +118,13 @@ impl<R: Read> XmlReader<R> {
         }
     }
 
-    fn read_next(&mut self) -> Result<Option<Event>, Error> {
+    fn read_next(&mut self) -> Result<Option<OwnedEvent>, Error> {
         loop {
             match self.next_event() {
                 Ok(XmlEvent::StartDocument { .. }) => {}
                 Ok(XmlEvent::StartElement { name, .. }) => {
+                    self.event_position = Some(self.xml_reader.position());
+
                     // Add the current element to the element stack
                     self.element_stack.push(name.local_name.clone());
 
@@ -87,7 +132,7 @@ impl<R: Read> XmlReader<R> {
                         "plist" => (),
                         "array" => return Ok(Some(Event::StartArray(None))),
                         "dict" => return Ok(Some(Event::StartDictionary(None))),
-                        "key" => return Ok(Some(Event::String(self.read_content()?))),
+                        "key" => return Ok(Some(Event::String(self.read_content()?.into()))),
                         "true" => return Ok(Some(Event::Boolean(true))),
                         "false" => return Ok(Some(Event::Boolean(false))),
                         "data" => {
@@ -96,7 +141,7 @@ impl<R: Read> XmlReader<R> {
                             s.retain(|c| !c.is_ascii_whitespace());
                             let data = base64::decode(&s)
                                 .map_err(|_| self.with_pos(ErrorKind::InvalidDataString))?;
-                            return Ok(Some(Event::Data(data)));
+                            return Ok(Some(Event::Data(data.into())));
                         }
                         "date" => {
                             let s = self.read_content()?;
@@ -120,11 +165,13 @@ impl<R: Read> XmlReader<R> {
                                 Err(_) => return Err(self.with_pos(ErrorKind::InvalidRealString)),
                             }
                         }
-                        "string" => return Ok(Some(Event::String(self.read_content()?))),
+                        "string" => return Ok(Some(Event::String(self.read_content()?.into()))),
                         _ => return Err(self.with_pos(ErrorKind::UnknownXmlElement)),
                     }
                 }
                 Ok(XmlEvent::EndElement { name, .. }) => {
+                    self.event_position = Some(self.xml_reader.position());
+
                     // Check the corrent element is being closed
                     match self.element_stack.pop() {
                         Some(ref open_name) if &name.local_name == open_name => (),
@@ -136,7 +183,7 @@ impl<R: Read> XmlReader<R> {
 
                     match &name.local_name[..] {
                         "array" | "dict" => return Ok(Some(Event::EndCollection)),
-                        "plist" | _ => (),
+                        _ => (),
                     }
                 }
                 Ok(XmlEvent::EndDocument) => {
@@ -168,31 +215,64 @@ impl<R: Read> XmlReader<R> {
     }
 }
 
+impl<R: Read> EventPosition for XmlReader<R> {
+    fn event_position(&self) -> Option<FilePosition> {
+        self.event_position.map(convert_xml_pos)
+    }
+}
+
 impl<R: Read> Iterator for XmlReader<R> {
-    type Item = Result<Event, Error>;
+    type Item = Result<OwnedEvent, Error>;
 
-    fn next(&mut self) -> Option<Result<Event, Error>> {
+    fn next(&mut self) -> Option<Result<OwnedEvent, Error>> {
         if self.finished {
-            None
-        } else {
-            match self.read_next() {
-                Ok(Some(event)) => Some(Ok(event)),
-                Ok(None) => {
+            return None;
+        }
+
+        if self.skip_unknown_element {
+            self.skip_unknown_element = false;
+            if let Err(err) = self.skip_element() {
+                self.finished = true;
+                return Some(Err(err));
+            }
+        }
+
+        match self.read_next() {
+            Ok(Some(event)) => match self.limiter.check_event(&event) {
+                Ok(()) => Some(Ok(event)),
+                Err(kind) => {
                     self.finished = true;
-                    None
+                    let pos = self.event_position.expect("an event has been read");
+                    Some(Err(kind.with_position(convert_xml_pos(pos))))
                 }
-                Err(err) => {
-                    self.finished = true;
-                    Some(Err(err))
+            },
+            Ok(None) => {
+                self.finished = true;
+                None
+            }
+            Err(err) => {
+                match err.kind() {
+                    ErrorKind::UnknownXmlElement if self.recover_errors => {
+                        self.skip_unknown_element = true
+                    }
+                    ErrorKind::InvalidDataString
+                    | ErrorKind::InvalidDateString
+                    | ErrorKind::InvalidIntegerString
+                    | ErrorKind::InvalidRealString
+                    | ErrorKind::UnexpectedXmlCharactersExpectedElement
+                        if self.recover_errors => {}
+                    _ => self.finished = true,
                 }
+                Some(Err(err))
             }
         }
     }
 }
 
-fn convert_xml_pos(pos: xml_rs::common::TextPosition) -> FilePosition {
-    // TODO: pos.row and pos.column counts from 0. what do we want to do?
-    FilePosition::LineColumn(pos.row, pos.column)
+/// Converts an `xml_rs` position, which counts lines and columns from 0, into a `FilePosition`
+/// with 1-based lines and columns as reported by text editors.
+fn convert_xml_pos(pos: TextPosition) -> FilePosition {
+    FilePosition::LineColumn(pos.row + 1, pos.column + 1)
 }
 
 fn from_xml_error(err: XmlReaderError) -> Error {
@@ -218,41 +298,41 @@ fn from_xml_error(err: XmlReaderError) -> Error {
 
 #[cfg(test)]
 mod tests {
-    use std::{fs::File, path::Path};
+    use std::{fs::File, io::Cursor, path::Path};
 
     use super::*;
     use crate::stream::Event::{self, *};
 
     #[test]
     fn streaming_parser() {
-        let reader = File::open(&Path::new("./tests/data/xml.plist")).unwrap();
+        let reader = File::open(Path::new("./tests/data/xml.plist")).unwrap();
         let streaming_parser = XmlReader::new(reader);
         let events: Vec<Event> = streaming_parser.map(|e| e.unwrap()).collect();
 
         let comparison = &[
             StartDictionary(None),
-            String("Author".to_owned()),
-            String("William Shakespeare".to_owned()),
-            String("Lines".to_owned()),
+            String("Author".into()),
+            String("William Shakespeare".into()),
+            String("Lines".into()),
             StartArray(None),
-            String("It is a tale told by an idiot,".to_owned()),
-            String("Full of sound and fury, signifying nothing.".to_owned()),
+            String("It is a tale told by an idiot,".into()),
+            String("Full of sound and fury, signifying nothing.".into()),
             EndCollection,
-            String("Death".to_owned()),
+            String("Death".into()),
             Integer(1564.into()),
-            String("Height".to_owned()),
+            String("Height".into()),
             Real(1.60),
-            String("Data".to_owned()),
-            Data(vec![0, 0, 0, 190, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0]),
-            String("Birthdate".to_owned()),
+            String("Data".into()),
+            Data(vec![0, 0, 0, 190, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0].into()),
+            String("Birthdate".into()),
             Date(super::Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap()),
-            String("Blank".to_owned()),
-            String("".to_owned()),
-            String("BiggestNumber".to_owned()),
+            String("Blank".into()),
+            String("".into()),
+            String("BiggestNumber".into()),
             Integer(18446744073709551615u64.into()),
-            String("SmallestNumber".to_owned()),
+            String("SmallestNumber".into()),
             Integer((-9223372036854775808i64).into()),
-            String("HexademicalNumber".to_owned()),
+            String("HexademicalNumber".into()),
             Integer(0xdead_beef_u64.into()),
             String("IsTrue".into()),
             Boolean(true),
@@ -266,10 +346,67 @@ mod tests {
 
     #[test]
     fn bad_data() {
-        let reader = File::open(&Path::new("./tests/data/xml_error.plist")).unwrap();
+        let reader = File::open(Path::new("./tests/data/xml_error.plist")).unwrap();
         let streaming_parser = XmlReader::new(reader);
         let events: Vec<_> = streaming_parser.collect();
 
         assert!(events.last().unwrap().is_err());
     }
+
+    #[test]
+    fn error_position_is_one_based() {
+        let plist = "<plist>\n<array>\n\t<integer>nope</integer>\n</array>\n</plist>";
+        let streaming_parser = XmlReader::new(Cursor::new(plist.as_bytes()));
+        let err = streaming_parser
+            .filter_map(Result::err)
+            .next()
+            .expect("should fail");
+
+        assert!(matches!(err.kind(), ErrorKind::InvalidIntegerString));
+        assert_eq!(err.line_column(), Some((3, 11)));
+        assert_eq!(err.byte_offset(), None);
+    }
+
+    #[test]
+    fn limits() {
+        let plist =
+            "<plist><array><data>AAAA</data><array><string>abc</string></array></array></plist>";
+        let read = |options: ReaderOptions| {
+            XmlReader::new_with_options(Cursor::new(plist.as_bytes()), options)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        assert_eq!(read(ReaderOptions::new().max_depth(2)).unwrap().len(), 6);
+        let err = read(ReaderOptions::new().max_depth(1)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DepthLimitExceeded));
+        assert_eq!(err.line_column(), Some((1, 32)));
+
+        let err = read(ReaderOptions::new().max_data_len(2)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DataLengthLimitExceeded));
+        let err = read(ReaderOptions::new().max_string_len(2)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::StringLengthLimitExceeded));
+        let err = read(ReaderOptions::new().max_total_bytes(5)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::TotalBytesLimitExceeded));
+        let err = read(ReaderOptions::new().max_objects(3)).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::ObjectLimitExceeded));
+    }
+
+    #[test]
+    fn length_limits_are_checked_after_reading_the_text() {
+        let plist = format!("<plist><string>{}</string></plist>", "a".repeat(1 << 16));
+        let options = ReaderOptions::new().max_string_len(16);
+
+        // The whole string is read before its length is checked.
+        let mut reader =
+            XmlReader::new_with_options(Cursor::new(plist.as_bytes()), options.clone());
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::StringLengthLimitExceeded));
+        assert_eq!(err.line_column(), Some((1, 8)));
+
+        // Limiting the input bounds the text which is read.
+        let input = Cursor::new(plist.as_bytes()).take(1024);
+        let mut reader = XmlReader::new_with_options(input, options);
+        let err = reader.next().unwrap().unwrap_err();
+        assert!(!matches!(err.kind(), ErrorKind::StringLengthLimitExceeded));
+    }
 }
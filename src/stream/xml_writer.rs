@@ -1,5 +1,3 @@
-use base64;
-use line_wrap;
 use std::{borrow::Cow, io::Write};
 use xml_rs::{
     name::Name,
@@ -9,7 +7,7 @@ use xml_rs::{
 
 use crate::{
     error::{self, Error, ErrorKind, EventKind},
-    stream::Writer,
+    stream::{CanonicalWriter, EventValidator, Writer},
     Date, Integer, Uid,
 };
 
@@ -18,22 +16,17 @@ static XML_PROLOGUE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
 <plist version="1.0">
 "#;
 
-#[derive(PartialEq)]
-enum Element {
-    Dictionary,
-    Array,
-}
-
+/// Writes events as an XML encoded plist.
 pub struct XmlWriter<W: Write> {
     xml_writer: EventWriter<W>,
-    stack: Vec<Element>,
-    expecting_key: bool,
+    validator: EventValidator,
     written_prologue: bool,
     // Not very nice
     empty_namespace: Namespace,
 }
 
 impl<W: Write> XmlWriter<W> {
+    /// Creates an `XmlWriter` which writes plists using tab indentation.
     pub fn new(writer: W) -> XmlWriter<W> {
         let config = EmitterConfig::new()
             .line_separator("\n")
@@ -47,13 +40,21 @@ impl<W: Write> XmlWriter<W> {
 
         XmlWriter {
             xml_writer: EventWriter::new_with_config(writer, config),
-            stack: Vec::new(),
-            expecting_key: false,
+            validator: EventValidator::new(),
             written_prologue: false,
             empty_namespace: Namespace::empty(),
         }
     }
 
+    /// Creates a `CanonicalWriter` which writes plists to an `XmlWriter` in a canonical form.
+    ///
+    /// Each plist is buffered in memory until it is complete. It is then canonicalized with
+    /// `Value::canonicalize` before being written so that dictionary keys are sorted. The output
+    /// always uses tab indentation and `\n` line endings.
+    pub fn new_canonical(writer: W) -> CanonicalWriter<XmlWriter<W>> {
+        CanonicalWriter::new(XmlWriter::new(writer))
+    }
+
     fn write_element_and_value(&mut self, name: &str, value: &str) -> Result<(), Error> {
         self.start_element(name)?;
         self.write_value(value)?;
@@ -88,14 +89,18 @@ impl<W: Write> XmlWriter<W> {
         Ok(())
     }
 
+    /// Consumes the `XmlWriter`, returning the underlying writer.
     pub fn into_inner(self) -> W {
         self.xml_writer.into_inner()
     }
 
     fn write_event<F: FnOnce(&mut Self) -> Result<(), Error>>(
         &mut self,
+        event_kind: EventKind,
         f: F,
     ) -> Result<(), Error> {
+        self.validator.validate(event_kind)?;
+
         if !self.written_prologue {
             self.xml_writer
                 .inner_mut()
@@ -108,7 +113,7 @@ impl<W: Write> XmlWriter<W> {
         f(self)?;
 
         // If there are no more open tags then write the </plist> element
-        if self.stack.is_empty() {
+        if self.validator.depth() == 0 {
             // We didn't tell the xml_writer about the <plist> tag so we'll skip telling it
             // about the </plist> tag as well.
             self.xml_writer
@@ -123,68 +128,30 @@ impl<W: Write> XmlWriter<W> {
 
         Ok(())
     }
-
-    fn write_value_event<F: FnOnce(&mut Self) -> Result<(), Error>>(
-        &mut self,
-        event_kind: EventKind,
-        f: F,
-    ) -> Result<(), Error> {
-        self.write_event(|this| {
-            if this.expecting_key {
-                return Err(ErrorKind::UnexpectedEventType {
-                    expected: EventKind::DictionaryKeyOrEndCollection,
-                    found: event_kind,
-                }
-                .without_position());
-            }
-            f(this)?;
-            this.expecting_key = this.stack.last() == Some(&Element::Dictionary);
-            Ok(())
-        })
-    }
 }
 
 impl<W: Write> Writer for XmlWriter<W> {
     fn write_start_array(&mut self, _len: Option<u64>) -> Result<(), Error> {
-        self.write_value_event(EventKind::StartArray, |this| {
-            this.start_element("array")?;
-            this.stack.push(Element::Array);
-            Ok(())
-        })
+        self.write_event(EventKind::StartArray, |this| this.start_element("array"))
     }
 
     fn write_start_dictionary(&mut self, _len: Option<u64>) -> Result<(), Error> {
-        self.write_value_event(EventKind::StartDictionary, |this| {
-            this.start_element("dict")?;
-            this.stack.push(Element::Dictionary);
-            Ok(())
+        self.write_event(EventKind::StartDictionary, |this| {
+            this.start_element("dict")
         })
     }
 
     fn write_end_collection(&mut self) -> Result<(), Error> {
-        self.write_event(|this| {
-            match (this.stack.pop(), this.expecting_key) {
-                (Some(Element::Dictionary), true) => {
-                    this.end_element("dict")?;
-                }
-                (Some(Element::Array), _) => {
-                    this.end_element("array")?;
-                }
-                (Some(Element::Dictionary), false) | (None, _) => {
-                    return Err(ErrorKind::UnexpectedEventType {
-                        expected: EventKind::ValueOrStartCollection,
-                        found: EventKind::EndCollection,
-                    }
-                    .without_position());
-                }
-            }
-            this.expecting_key = this.stack.last() == Some(&Element::Dictionary);
-            Ok(())
-        })
+        let name = if self.validator.in_dictionary() {
+            "dict"
+        } else {
+            "array"
+        };
+        self.write_event(EventKind::EndCollection, |this| this.end_element(name))
     }
 
     fn write_boolean(&mut self, value: bool) -> Result<(), Error> {
-        self.write_value_event(EventKind::Boolean, |this| {
+        self.write_event(EventKind::Boolean, |this| {
             let value_str = if value { "true" } else { "false" };
             this.start_element(value_str)?;
             this.end_element(value_str)
@@ -192,40 +159,38 @@ impl<W: Write> Writer for XmlWriter<W> {
     }
 
     fn write_data(&mut self, value: &[u8]) -> Result<(), Error> {
-        self.write_value_event(EventKind::Data, |this| {
-            let base64_data = base64_encode_plist(&value, this.stack.len());
+        self.write_event(EventKind::Data, |this| {
+            let base64_data = base64_encode_plist(value, this.validator.depth());
             this.write_element_and_value("data", &base64_data)
         })
     }
 
     fn write_date(&mut self, value: Date) -> Result<(), Error> {
-        self.write_value_event(EventKind::Date, |this| {
+        self.write_event(EventKind::Date, |this| {
             this.write_element_and_value("date", &value.to_rfc3339())
         })
     }
 
     fn write_integer(&mut self, value: Integer) -> Result<(), Error> {
-        self.write_value_event(EventKind::Integer, |this| {
+        self.write_event(EventKind::Integer, |this| {
             this.write_element_and_value("integer", &value.to_string())
         })
     }
 
     fn write_real(&mut self, value: f64) -> Result<(), Error> {
-        self.write_value_event(EventKind::Real, |this| {
+        self.write_event(EventKind::Real, |this| {
             this.write_element_and_value("real", &value.to_string())
         })
     }
 
     fn write_string(&mut self, value: &str) -> Result<(), Error> {
-        self.write_event(|this| {
-            if this.expecting_key {
-                this.write_element_and_value("key", &*value)?;
-                this.expecting_key = false;
-            } else {
-                this.write_element_and_value("string", &*value)?;
-                this.expecting_key = this.stack.last() == Some(&Element::Dictionary);
-            }
-            Ok(())
+        let name = if self.validator.expecting_key() {
+            "key"
+        } else {
+            "string"
+        };
+        self.write_event(EventKind::String, |this| {
+            this.write_element_and_value(name, value)
         })
     }
 
@@ -300,27 +265,27 @@ mod tests {
     fn streaming_parser() {
         let plist = &[
             Event::StartDictionary(None),
-            Event::String("Author".to_owned()),
-            Event::String("William Shakespeare".to_owned()),
-            Event::String("Lines".to_owned()),
+            Event::String("Author".into()),
+            Event::String("William Shakespeare".into()),
+            Event::String("Lines".into()),
             Event::StartArray(None),
-            Event::String("It is a tale told by an idiot,".to_owned()),
-            Event::String("Full of sound and fury, signifying nothing.".to_owned()),
-            Event::Data((0..128).collect::<Vec<_>>()),
+            Event::String("It is a tale told by an idiot,".into()),
+            Event::String("Full of sound and fury, signifying nothing.".into()),
+            Event::Data((0..128).collect::<Vec<_>>().into()),
             Event::EndCollection,
-            Event::String("Death".to_owned()),
+            Event::String("Death".into()),
             Event::Integer(1564.into()),
-            Event::String("Height".to_owned()),
+            Event::String("Height".into()),
             Event::Real(1.60),
-            Event::String("Data".to_owned()),
-            Event::Data(vec![0, 0, 0, 190, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0]),
-            Event::String("Birthdate".to_owned()),
+            Event::String("Data".into()),
+            Event::Data(vec![0, 0, 0, 190, 0, 0, 0, 3, 0, 0, 0, 30, 0, 0, 0].into()),
+            Event::String("Birthdate".into()),
             Event::Date(super::Date::from_rfc3339("1981-05-16T11:32:06Z").unwrap()),
-            Event::String("Comment".to_owned()),
-            Event::String("2 < 3".to_owned()), // make sure characters are escaped
-            Event::String("BiggestNumber".to_owned()),
+            Event::String("Comment".into()),
+            Event::String("2 < 3".into()), // make sure characters are escaped
+            Event::String("BiggestNumber".into()),
             Event::Integer(18446744073709551615u64.into()),
-            Event::String("SmallestNumber".to_owned()),
+            Event::String("SmallestNumber".into()),
             Event::Integer((-9223372036854775808i64).into()),
             Event::String("IsTrue".into()),
             Event::Boolean(true),
@@ -382,4 +347,51 @@ mod tests {
 
         assert_eq!(s, comparison);
     }
+
+    #[test]
+    fn canonical_sorts_keys() {
+        let plist = &[
+            Event::StartDictionary(None),
+            Event::String("b".into()),
+            Event::Real(-0.0),
+            Event::String("a".into()),
+            Event::StartDictionary(None),
+            Event::String("z".into()),
+            Event::Boolean(true),
+            Event::String("y".into()),
+            Event::Boolean(false),
+            Event::EndCollection,
+            Event::EndCollection,
+        ];
+
+        let mut cursor = Cursor::new(Vec::new());
+
+        {
+            let mut plist_w = XmlWriter::new_canonical(&mut cursor);
+
+            for item in plist {
+                plist_w.write(item).unwrap();
+            }
+        }
+
+        let comparison = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">
+<plist version=\"1.0\">
+<dict>
+\t<key>a</key>
+\t<dict>
+\t\t<key>y</key>
+\t\t<false />
+\t\t<key>z</key>
+\t\t<true />
+\t</dict>
+\t<key>b</key>
+\t<real>0</real>
+</dict>
+</plist>";
+
+        let s = String::from_utf8(cursor.into_inner()).unwrap();
+
+        assert_eq!(s, comparison);
+    }
 }
@@ -0,0 +1,179 @@
+use std::iter;
+
+use crate::{
+    error::{Error, ErrorKind, EventKind},
+    stream::{Event, EventValidator, Writer},
+    Value,
+};
+
+/// Options for `transcode`.
+///
+/// ```
+/// use std::io::Cursor;
+/// use plist::{
+///     stream::{Reader, XmlWriter},
+///     TranscodeOptions,
+/// };
+///
+/// let plist = b"<plist><dict><key>b</key><true/><key>a</key><false/></dict></plist>";
+/// let mut writer = XmlWriter::new(Vec::new());
+///
+/// TranscodeOptions::new()
+///     .sort_keys(true)
+///     .transcode(Reader::new(Cursor::new(&plist[..])), &mut writer)
+///     .unwrap();
+///
+/// let xml = String::from_utf8(writer.into_inner()).unwrap();
+/// assert!(xml.find("<key>a</key>") < xml.find("<key>b</key>"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TranscodeOptions {
+    sort_keys: bool,
+}
+
+impl TranscodeOptions {
+    /// Returns the default options.
+    pub fn new() -> TranscodeOptions {
+        TranscodeOptions { sort_keys: false }
+    }
+
+    /// Sets whether dictionary keys are sorted in ascending order.
+    ///
+    /// Each dictionary is buffered in memory until it is complete so that its keys can be sorted.
+    /// Arrays outside of a dictionary are still streamed.
+    pub fn sort_keys(mut self, sort_keys: bool) -> Self {
+        self.sort_keys = sort_keys;
+        self
+    }
+
+    /// Writes the plists in an event stream to `writer` without building a `Value`.
+    ///
+    /// The event stream must contain at least one plist and end on a plist boundary.
+    pub fn transcode<'a, I, W>(&self, events: I, writer: &mut W) -> Result<(), Error>
+    where
+        I: IntoIterator<Item = Result<Event<'a>, Error>>,
+        W: Writer + ?Sized,
+    {
+        let mut events = events.into_iter();
+        let mut validator = EventValidator::new();
+        let mut written = false;
+
+        while let Some(event) = events.next() {
+            let event = event?;
+            validator.validate(EventKind::of_event(&event))?;
+            written = true;
+
+            if let (Event::StartDictionary(_), true) = (&event, self.sort_keys) {
+                let mut dict = Value::from_events(iter::once(Ok(event)).chain(&mut events))?;
+                sort_keys(&mut dict);
+                for event in dict.events() {
+                    writer.write(&event)?;
+                }
+                validator.validate(EventKind::EndCollection)?;
+            } else {
+                writer.write(&event)?;
+            }
+        }
+
+        if !written || validator.depth() != 0 {
+            return Err(ErrorKind::UnexpectedEndOfEventStream.without_position());
+        }
+        Ok(())
+    }
+}
+
+/// Writes the plists in an event stream, e.g. from a `stream::Reader`, to `writer` without
+/// building a `Value`.
+///
+/// Only the events currently being transcoded are held in memory, so any pairing of reader and
+/// writer can convert large plists between encodings.
+///
+/// ```
+/// use std::io::Cursor;
+/// use plist::stream::{BinaryWriter, Reader};
+///
+/// let plist = b"<plist><array><string>a</string><integer>1</integer></array></plist>";
+/// let mut writer = BinaryWriter::new(Vec::new());
+/// plist::transcode(Reader::new(Cursor::new(&plist[..])), &mut writer).unwrap();
+///
+/// let binary = writer.into_inner();
+/// assert!(binary.starts_with(b"bplist00"));
+/// ```
+pub fn transcode<'a, I, W>(events: I, writer: &mut W) -> Result<(), Error>
+where
+    I: IntoIterator<Item = Result<Event<'a>, Error>>,
+    W: Writer + ?Sized,
+{
+    TranscodeOptions::new().transcode(events, writer)
+}
+
+fn sort_keys(value: &mut Value) {
+    match value {
+        Value::Array(array) => array.iter_mut().for_each(sort_keys),
+        Value::Dictionary(dict) => {
+            dict.sort_keys();
+            dict.values_mut().for_each(sort_keys);
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::Cursor};
+
+    use super::*;
+    use crate::stream::{BinaryReader, BinaryWriter, Reader, XmlReader, XmlWriter};
+
+    #[test]
+    fn xml_to_binary_and_back() {
+        let expected = Value::from_file("./tests/data/xml.plist").unwrap();
+
+        let mut binary = BinaryWriter::new(Vec::new());
+        let reader = Reader::new(File::open("./tests/data/xml.plist").unwrap());
+        transcode(reader, &mut binary).unwrap();
+        let binary = binary.into_inner();
+        assert_eq!(Value::from_reader(Cursor::new(&binary)).unwrap(), expected);
+
+        let mut xml = XmlWriter::new(Vec::new());
+        transcode(BinaryReader::from_slice(&binary), &mut xml).unwrap();
+        let xml = xml.into_inner();
+        assert_eq!(Value::from_reader_xml(&xml[..]).unwrap(), expected);
+    }
+
+    #[test]
+    fn sorts_keys() {
+        let plist = b"<plist><array><dict>
+            <key>b</key><dict><key>d</key><true/><key>c</key><false/></dict>
+            <key>a</key><array><dict><key>f</key><true/><key>e</key><false/></dict></array>
+        </dict></array></plist>";
+
+        let mut writer = XmlWriter::new(Vec::new());
+        TranscodeOptions::new()
+            .sort_keys(true)
+            .transcode(XmlReader::new(&plist[..]), &mut writer)
+            .unwrap();
+        let value = Value::from_reader_xml(&writer.into_inner()[..]).unwrap();
+
+        let dict = value.as_array().unwrap()[0].as_dictionary().unwrap();
+        assert_eq!(dict.keys().collect::<Vec<_>>(), ["a", "b"]);
+        let inner = dict["b"].as_dictionary().unwrap();
+        assert_eq!(inner.keys().collect::<Vec<_>>(), ["c", "d"]);
+        let nested = dict["a"].as_array().unwrap()[0].as_dictionary().unwrap();
+        assert_eq!(nested.keys().collect::<Vec<_>>(), ["e", "f"]);
+    }
+
+    #[test]
+    fn invalid_event_streams() {
+        let events: [&[Event]; 3] = [
+            &[],
+            &[Event::StartArray(None)],
+            &[Event::StartDictionary(None), Event::Boolean(true)],
+        ];
+        for events in &events {
+            let mut writer = XmlWriter::new(Vec::new());
+            let events = events.iter().cloned().map(Ok);
+            assert!(transcode(events, &mut writer).is_err());
+        }
+    }
+}
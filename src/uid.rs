@@ -1,7 +1,7 @@
 use std::fmt;
 
 /// A plist `uid` value. These are found exclusively in plists created by `NSKeyedArchiver`.
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Uid {
     value: u64,
 }
@@ -27,7 +27,7 @@ impl fmt::Debug for Uid {
 #[cfg(feature = "serde")]
 pub mod serde_impls {
     use serde::{
-        de::{Deserialize, Deserializer, Error, Visitor},
+        de::{Deserialize, Deserializer, Error, MapAccess, Unexpected, Visitor},
         ser::{Serialize, Serializer},
     };
     use std::fmt;
@@ -35,6 +35,9 @@ pub mod serde_impls {
     use crate::Uid;
 
     pub const UID_NEWTYPE_STRUCT_NAME: &str = "PLIST-UID";
+    /// `Deserializer::deserialize_any` passes uids as a single entry map with this key so they
+    /// remain distinguishable from integers when buffered by serde.
+    pub const UID_MAP_KEY: &str = "$__plist_private_uid";
 
     impl Serialize for Uid {
         fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -58,13 +61,13 @@ pub mod serde_impls {
         where
             D: Deserializer<'de>,
         {
-            deserializer.deserialize_str(UidU64Visitor)
+            deserializer.deserialize_any(UidVisitor)
         }
     }
 
-    struct UidU64Visitor;
+    struct UidVisitor;
 
-    impl<'de> Visitor<'de> for UidU64Visitor {
+    impl<'de> Visitor<'de> for UidVisitor {
         type Value = Uid;
 
         fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -77,6 +80,16 @@ pub mod serde_impls {
         {
             Ok(Uid::new(v))
         }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            match map.next_key::<String>()? {
+                Some(ref key) if key == UID_MAP_KEY => Ok(Uid::new(map.next_value()?)),
+                _ => Err(A::Error::invalid_type(Unexpected::Map, &self)),
+            }
+        }
     }
 
     impl<'de> Deserialize<'de> for Uid {
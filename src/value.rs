@@ -1,17 +1,27 @@
 use std::{
+    cmp::Ordering,
+    collections::hash_map::DefaultHasher,
+    fmt,
     fs::File,
+    hash::{Hash, Hasher},
     io::{BufReader, BufWriter, Read, Seek, Write},
     path::Path,
 };
 
 use crate::{
     error::{self, Error, ErrorKind, EventKind},
-    stream::{BinaryWriter, Event, IntoEvents, Reader, Writer, XmlReader, XmlWriter},
+    stream::{
+        BinaryWriter, Event, Events, IntoEvents, PrettyOptions, PrettyWriter, Reader,
+        ReaderOptions, Writer, XmlReader, XmlWriter,
+    },
     u64_to_usize, Date, Dictionary, Integer, Uid,
 };
 
 /// Represents any plist value.
+///
+/// New variants may be added in minor releases.
 #[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Value {
     Array(Vec<Value>),
     Dictionary(Dictionary),
@@ -22,8 +32,6 @@ pub enum Value {
     Integer(Integer),
     String(String),
     Uid(Uid),
-    #[doc(hidden)]
-    __Nonexhaustive,
 }
 
 impl Value {
@@ -39,12 +47,49 @@ impl Value {
         Value::from_events(reader)
     }
 
+    /// Reads a `Value` from a seekable byte stream containing a plist of any encoding, stopping
+    /// with an error if the plist exceeds the limits in `options`.
+    pub fn from_reader_with_options<R: Read + Seek>(
+        reader: R,
+        options: ReaderOptions,
+    ) -> Result<Value, Error> {
+        let max_depth = options.depth_limit();
+        let reader = Reader::new_with_options(reader, options);
+        Builder::new(reader).max_depth(max_depth).build()
+    }
+
     /// Reads a `Value` from a seekable byte stream containing an XML encoded plist.
     pub fn from_reader_xml<R: Read>(reader: R) -> Result<Value, Error> {
         let reader = XmlReader::new(reader);
         Value::from_events(reader)
     }
 
+    /// Reads a `Value` from a seekable byte stream containing a plist of any encoding, reporting
+    /// every problem found rather than stopping at the first one.
+    ///
+    /// Returns a best-effort value, with invalid values and their dictionary keys omitted, or
+    /// `None` if no value could be read. Problems are returned in the order they were found and
+    /// include their position in the input where it is known.
+    ///
+    /// Invalid values, unknown XML elements, unexpected XML characters and invalid binary objects
+    /// are skipped. Malformed XML and corrupt binary trailers end reading early, in which case any
+    /// open arrays and dictionaries are closed.
+    pub fn from_reader_with_diagnostics<R: Read + Seek>(reader: R) -> (Option<Value>, Vec<Error>) {
+        let mut reader = Reader::new(reader);
+        reader.set_recover_errors(true);
+        Builder::new_diagnostic(reader).build_diagnostic()
+    }
+
+    /// Reads a `Value` from a byte stream containing an XML encoded plist, reporting every problem
+    /// found rather than stopping at the first one.
+    ///
+    /// See `from_reader_with_diagnostics` for details.
+    pub fn from_reader_xml_with_diagnostics<R: Read>(reader: R) -> (Option<Value>, Vec<Error>) {
+        let mut reader = XmlReader::new(reader);
+        reader.set_recover_errors(true);
+        Builder::new_diagnostic(reader).build_diagnostic()
+    }
+
     /// Serializes a `Value` to a file as a binary encoded plist.
     pub fn to_file_binary<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
         let mut file = File::create(path).map_err(error::from_io_without_position)?;
@@ -74,8 +119,7 @@ impl Value {
     }
 
     fn to_writer_inner(&self, writer: &mut dyn Writer) -> Result<(), Error> {
-        let events = self.clone().into_events();
-        for event in events {
+        for event in self.events() {
             writer.write(&event)?;
         }
         Ok(())
@@ -83,34 +127,62 @@ impl Value {
 
     /// Builds a single `Value` from an `Event` iterator.
     /// On success any excess `Event`s will remain in the iterator.
-    #[cfg(feature = "enable_unstable_features_that_may_break_with_minor_version_bumps")]
-    pub fn from_events<T>(events: T) -> Result<Value, Error>
-    where
-        T: IntoIterator<Item = Result<Event, Error>>,
-    {
-        Builder::new(events.into_iter()).build()
-    }
-
-    /// Builds a single `Value` from an `Event` iterator.
-    /// On success any excess `Event`s will remain in the iterator.
-    #[cfg(not(feature = "enable_unstable_features_that_may_break_with_minor_version_bumps"))]
-    pub(crate) fn from_events<T>(events: T) -> Result<Value, Error>
+    ///
+    /// Arrays and dictionaries may be nested up to `ReaderOptions::DEFAULT_MAX_DEPTH` deep.
+    pub fn from_events<'a, T>(events: T) -> Result<Value, Error>
     where
-        T: IntoIterator<Item = Result<Event, Error>>,
+        T: IntoIterator<Item = Result<Event<'a>, Error>>,
     {
         Builder::new(events.into_iter()).build()
     }
 
     /// Converts a `Value` into an `Event` iterator.
-    #[cfg(feature = "enable_unstable_features_that_may_break_with_minor_version_bumps")]
     pub fn into_events(self) -> IntoEvents {
         IntoEvents::new(self)
     }
 
-    /// Converts a `Value` into an `Event` iterator.
-    #[cfg(not(feature = "enable_unstable_features_that_may_break_with_minor_version_bumps"))]
-    pub(crate) fn into_events(self) -> IntoEvents {
-        IntoEvents::new(self)
+    /// Returns an `Event` iterator which borrows strings and data from the `Value`.
+    pub fn events(&self) -> Events<'_> {
+        Events::new(self)
+    }
+
+    /// Returns a wrapper which displays the `Value` as human readable text in the style of
+    /// `plutil -p`.
+    ///
+    /// ```
+    /// use plist::{stream::PrettyOptions, Value};
+    ///
+    /// let value = Value::Array(vec![1.into(), "a".repeat(50).into()]);
+    /// let options = PrettyOptions::new().max_string_len(3);
+    /// assert_eq!(
+    ///     value.pretty().options(options).to_string(),
+    ///     "[\n  0 => 1\n  1 => \"aaa…\"\n]"
+    /// );
+    /// ```
+    pub fn pretty(&self) -> Pretty<'_> {
+        Pretty {
+            value: self,
+            options: PrettyOptions::new(),
+        }
+    }
+
+    /// Normalizes the `Value` so that values which compare equal are always serialized to
+    /// identical bytes.
+    ///
+    /// Dictionary keys are sorted in ascending order, `-0.0` is replaced with `0.0` and all `NaN`s
+    /// are replaced with a single canonical `NaN`. Arrays and dictionaries are canonicalized
+    /// recursively.
+    pub fn canonicalize(&mut self) {
+        match self {
+            Value::Array(array) => array.iter_mut().for_each(Value::canonicalize),
+            Value::Dictionary(dict) => {
+                dict.sort_keys();
+                dict.values_mut().for_each(Value::canonicalize);
+            }
+            Value::Real(v) if v.is_nan() => *v = f64::NAN,
+            Value::Real(v) if *v == 0.0 => *v = 0.0,
+            _ => (),
+        }
     }
 
     /// If the `Value` is a Array, returns the underlying `Vec`.
@@ -444,55 +516,478 @@ impl<'a> From<&'a str> for Value {
     }
 }
 
-struct Builder<T> {
+#[cfg(feature = "serde")]
+impl serde::ser::Serialize for Value {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::ser::Serializer,
+    {
+        match self {
+            Value::Array(v) => v.serialize(serializer),
+            Value::Dictionary(v) => v.serialize(serializer),
+            Value::Boolean(v) => serializer.serialize_bool(*v),
+            Value::Data(v) => serializer.serialize_bytes(v),
+            Value::Date(v) => v.serialize(serializer),
+            Value::Real(v) => serializer.serialize_f64(*v),
+            Value::Integer(v) => v.serialize(serializer),
+            Value::String(v) => serializer.serialize_str(v),
+            Value::Uid(v) => v.serialize(serializer),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Deserialize<'de> for Value {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::de::Deserializer<'de>,
+    {
+        use crate::{date::serde_impls::DATE_MAP_KEY, uid::serde_impls::UID_MAP_KEY};
+        use serde::de::{self, Deserialize, Unexpected};
+        use std::fmt;
+
+        struct ValueVisitor;
+
+        impl<'de> de::Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a plist value")
+            }
+
+            fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+                Ok(Value::Boolean(v))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+                Ok(Value::Integer(v.into()))
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+                Ok(Value::Integer(v.into()))
+            }
+
+            fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+                Ok(Value::Real(v))
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Value, E> {
+                Ok(Value::String(v.to_owned()))
+            }
+
+            fn visit_string<E>(self, v: String) -> Result<Value, E> {
+                Ok(Value::String(v))
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Value, E> {
+                Ok(Value::Data(v.to_owned()))
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Value, E> {
+                Ok(Value::Data(v))
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Value, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                Value::deserialize(deserializer)
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Value, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                Value::deserialize(deserializer)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Value, A::Error>
+            where
+                A: de::SeqAccess<'de>,
+            {
+                let mut array = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(value) = seq.next_element()? {
+                    array.push(value);
+                }
+                Ok(Value::Array(array))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Value, A::Error>
+            where
+                A: de::MapAccess<'de>,
+            {
+                // Dates and uids are passed by the plist deserializer as single entry maps with a
+                // private key.
+                let key: String = match map.next_key()? {
+                    Some(key) => key,
+                    None => return Ok(Value::Dictionary(Dictionary::new())),
+                };
+                if key == DATE_MAP_KEY {
+                    let date_str: String = map.next_value()?;
+                    let date = Date::from_rfc3339(&date_str).map_err(|()| {
+                        de::Error::invalid_value(Unexpected::Str(&date_str), &"a plist date")
+                    })?;
+                    return Ok(Value::Date(date));
+                }
+                if key == UID_MAP_KEY {
+                    return Ok(Value::Uid(Uid::new(map.next_value()?)));
+                }
+
+                let mut dict = Dictionary::new();
+                dict.insert(key, map.next_value()?);
+                while let Some((key, value)) = map.next_entry()? {
+                    dict.insert(key, value);
+                }
+                Ok(Value::Dictionary(dict))
+            }
+        }
+
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+/// Displays a `Value` in the style of `plutil -p`. Returned by `Value::pretty`.
+pub struct Pretty<'a> {
+    value: &'a Value,
+    options: PrettyOptions,
+}
+
+impl Pretty<'_> {
+    /// Sets the options used to truncate long strings, data and deeply nested collections.
+    pub fn options(mut self, options: PrettyOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl fmt::Display for Pretty<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut writer = PrettyWriter::new_with_options(Vec::new(), self.options.clone());
+        for event in self.value.events() {
+            writer.write(&event).map_err(|_| fmt::Error)?;
+        }
+        let text = String::from_utf8(writer.into_inner()).map_err(|_| fmt::Error)?;
+        // Unlike `PrettyWriter`, omit the line ending after the value.
+        f.write_str(text.trim_end_matches('\n'))
+    }
+}
+
+/// A wrapper around a `Value` which implements `Eq`, `Hash` and `Ord`.
+///
+/// `Value` itself only implements `PartialEq` as reals follow IEEE 754 equality. `OrdValue`
+/// instead compares and hashes reals by their bit pattern, so `NaN` is equal to itself and `0.0`
+/// and `-0.0` are distinct. Dictionaries are compared and hashed independently of the order of
+/// their keys.
+///
+/// Values of different types are ordered as `Array < Dictionary < Boolean < Data < Date < Real <
+/// Integer < String < Uid`. Dictionaries are ordered by comparing their entries sorted by key.
+///
+/// ```
+/// use std::collections::HashSet;
+/// use plist::{OrdValue, Value};
+///
+/// let mut set = HashSet::new();
+/// set.insert(OrdValue(Value::Real(std::f64::NAN)));
+/// assert!(set.contains(&OrdValue(Value::Real(std::f64::NAN))));
+/// ```
+#[derive(Clone, Debug)]
+pub struct OrdValue(pub Value);
+
+impl OrdValue {
+    /// Returns the wrapped `Value`.
+    pub fn into_inner(self) -> Value {
+        self.0
+    }
+}
+
+impl From<Value> for OrdValue {
+    fn from(from: Value) -> OrdValue {
+        OrdValue(from)
+    }
+}
+
+impl PartialEq for OrdValue {
+    fn eq(&self, other: &OrdValue) -> bool {
+        cmp_values(&self.0, &other.0) == Ordering::Equal
+    }
+}
+
+impl Eq for OrdValue {}
+
+impl PartialOrd for OrdValue {
+    fn partial_cmp(&self, other: &OrdValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OrdValue {
+    fn cmp(&self, other: &OrdValue) -> Ordering {
+        cmp_values(&self.0, &other.0)
+    }
+}
+
+impl Hash for OrdValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        hash_value(&self.0, state)
+    }
+}
+
+fn type_rank(value: &Value) -> u8 {
+    match value {
+        Value::Array(_) => 0,
+        Value::Dictionary(_) => 1,
+        Value::Boolean(_) => 2,
+        Value::Data(_) => 3,
+        Value::Date(_) => 4,
+        Value::Real(_) => 5,
+        Value::Integer(_) => 6,
+        Value::String(_) => 7,
+        Value::Uid(_) => 8,
+    }
+}
+
+fn sorted_entries(dict: &Dictionary) -> Vec<(&String, &Value)> {
+    let mut entries: Vec<_> = dict.iter().collect();
+    entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+    entries
+}
+
+fn cmp_values(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Array(a), Value::Array(b)) => {
+            for (a, b) in a.iter().zip(b) {
+                match cmp_values(a, b) {
+                    Ordering::Equal => (),
+                    ordering => return ordering,
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        (Value::Dictionary(a), Value::Dictionary(b)) => {
+            let (a, b) = (sorted_entries(a), sorted_entries(b));
+            for ((a_key, a_value), (b_key, b_value)) in a.iter().zip(&b) {
+                match a_key.cmp(b_key).then_with(|| cmp_values(a_value, b_value)) {
+                    Ordering::Equal => (),
+                    ordering => return ordering,
+                }
+            }
+            a.len().cmp(&b.len())
+        }
+        (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+        (Value::Data(a), Value::Data(b)) => a.cmp(b),
+        (Value::Date(a), Value::Date(b)) => a.cmp(b),
+        (Value::Real(a), Value::Real(b)) => real_order_key(*a).cmp(&real_order_key(*b)),
+        (Value::Integer(a), Value::Integer(b)) => a.cmp(b),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::Uid(a), Value::Uid(b)) => a.cmp(b),
+        (a, b) => type_rank(a).cmp(&type_rank(b)),
+    }
+}
+
+/// Maps the bit pattern of `v` to an integer which orders reals numerically, with `-0.0` before
+/// `0.0` and `NaN`s before or after everything else depending on their sign. Two reals have the
+/// same key only if they have the same bit pattern.
+fn real_order_key(v: f64) -> i64 {
+    let bits = v.to_bits() as i64;
+    // Flipping all but the sign bit of negative reals reverses their order.
+    bits ^ (((bits >> 63) as u64) >> 1) as i64
+}
+
+fn hash_value<H: Hasher>(value: &Value, state: &mut H) {
+    type_rank(value).hash(state);
+    match value {
+        Value::Array(array) => {
+            array.len().hash(state);
+            for value in array {
+                hash_value(value, state);
+            }
+        }
+        Value::Dictionary(dict) => {
+            // Entries are hashed individually and combined with a commutative operation so the
+            // resulting hash does not depend on the order of the keys.
+            let entries_hash = dict.iter().fold(0u64, |acc, (key, value)| {
+                let mut entry_state = DefaultHasher::new();
+                key.hash(&mut entry_state);
+                hash_value(value, &mut entry_state);
+                acc.wrapping_add(entry_state.finish())
+            });
+            dict.len().hash(state);
+            entries_hash.hash(state);
+        }
+        Value::Boolean(v) => v.hash(state),
+        Value::Data(v) => v.hash(state),
+        Value::Date(v) => v.hash(state),
+        Value::Real(v) => v.to_bits().hash(state),
+        Value::Integer(v) => v.hash(state),
+        Value::String(v) => v.hash(state),
+        Value::Uid(v) => v.hash(state),
+    }
+}
+
+struct Builder<'a, T> {
     stream: T,
-    token: Option<Event>,
+    token: Option<Event<'a>>,
+    /// Set when the current token is an invalid value which has been reported as a diagnostic.
+    invalid_token: bool,
+    /// Problems found while building in diagnostic mode. `None` in strict mode.
+    diagnostics: Option<Vec<Error>>,
+    /// The number of arrays and dictionaries being built.
+    depth: usize,
+    max_depth: usize,
 }
 
-impl<T: Iterator<Item = Result<Event, Error>>> Builder<T> {
-    fn new(stream: T) -> Builder<T> {
+impl<'a, T: Iterator<Item = Result<Event<'a>, Error>>> Builder<'a, T> {
+    fn new(stream: T) -> Builder<'a, T> {
         Builder {
             stream,
             token: None,
+            invalid_token: false,
+            diagnostics: None,
+            depth: 0,
+            max_depth: ReaderOptions::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    fn max_depth(mut self, max_depth: usize) -> Builder<'a, T> {
+        self.max_depth = max_depth;
+        self
+    }
+
+    fn new_diagnostic(stream: T) -> Builder<'a, T> {
+        Builder {
+            diagnostics: Some(Vec::new()),
+            ..Builder::new(stream)
         }
     }
 
     fn build(mut self) -> Result<Value, Error> {
         self.bump()?;
-        self.build_value()
+        Ok(self
+            .build_value()?
+            .expect("values are only omitted in diagnostic mode"))
     }
 
-    fn bump(&mut self) -> Result<(), Error> {
-        self.token = match self.stream.next() {
-            Some(Ok(token)) => Some(token),
-            Some(Err(err)) => return Err(err),
-            None => None,
-        };
+    fn build_diagnostic(mut self) -> (Option<Value>, Vec<Error>) {
+        let value = self
+            .bump()
+            .and_then(|()| self.build_value())
+            .expect("errors are reported as diagnostics in diagnostic mode");
+        (value, self.diagnostics.unwrap_or_default())
+    }
+
+    /// Returns the error in strict mode or records it in diagnostic mode.
+    fn report(&mut self, err: Error) -> Result<(), Error> {
+        match &mut self.diagnostics {
+            Some(diagnostics) => {
+                diagnostics.push(err);
+                Ok(())
+            }
+            None => Err(err),
+        }
+    }
+
+    fn report_end_of_stream(&mut self) -> Result<(), Error> {
+        // In diagnostic mode the stream usually ends early because the reader has already
+        // reported an error it could not recover from.
+        if !matches!(&self.diagnostics, Some(diagnostics) if !diagnostics.is_empty()) {
+            self.report(ErrorKind::UnexpectedEndOfEventStream.without_position())?;
+        }
         Ok(())
     }
 
-    fn build_value(&mut self) -> Result<Value, Error> {
-        match self.token.take() {
-            Some(Event::StartArray(len)) => Ok(Value::Array(self.build_array(len)?)),
-            Some(Event::StartDictionary(len)) => Ok(Value::Dictionary(self.build_dict(len)?)),
+    fn bump(&mut self) -> Result<(), Error> {
+        self.invalid_token = false;
+        loop {
+            self.token = match self.stream.next() {
+                Some(Ok(token)) => Some(token),
+                Some(Err(err)) => {
+                    // Unexpected characters between elements do not take the place of a value.
+                    let is_value = !matches!(
+                        err.kind(),
+                        ErrorKind::UnexpectedXmlCharactersExpectedElement
+                    );
+                    self.report(err)?;
+                    if !is_value {
+                        continue;
+                    }
+                    self.invalid_token = true;
+                    None
+                }
+                None => None,
+            };
+            return Ok(());
+        }
+    }
+
+    /// Builds the value starting at the current token. Returns `None` in diagnostic mode if the
+    /// value was invalid.
+    fn build_value(&mut self) -> Result<Option<Value>, Error> {
+        if self.invalid_token {
+            return Ok(None);
+        }
 
-            Some(Event::Boolean(b)) => Ok(Value::Boolean(b)),
-            Some(Event::Data(d)) => Ok(Value::Data(d)),
-            Some(Event::Date(d)) => Ok(Value::Date(d)),
-            Some(Event::Integer(i)) => Ok(Value::Integer(i)),
-            Some(Event::Real(f)) => Ok(Value::Real(f)),
-            Some(Event::String(s)) => Ok(Value::String(s)),
-            Some(Event::Uid(u)) => Ok(Value::Uid(u)),
+        let value = match self.token.take() {
+            Some(Event::StartArray(_)) | Some(Event::StartDictionary(_))
+                if self.depth == self.max_depth =>
+            {
+                self.report(ErrorKind::DepthLimitExceeded.without_position())?;
+                self.skip_collection()?;
+                return Ok(None);
+            }
+            Some(Event::StartArray(len)) => {
+                self.depth += 1;
+                let array = self.build_array(len);
+                self.depth -= 1;
+                Value::Array(array?)
+            }
+            Some(Event::StartDictionary(len)) => {
+                self.depth += 1;
+                let dict = self.build_dict(len);
+                self.depth -= 1;
+                Value::Dictionary(dict?)
+            }
 
-            Some(event @ Event::EndCollection) => Err(error::unexpected_event_type(
-                EventKind::ValueOrStartCollection,
-                &event,
-            )),
+            Some(Event::Boolean(b)) => Value::Boolean(b),
+            Some(Event::Data(d)) => Value::Data(d.into_owned()),
+            Some(Event::Date(d)) => Value::Date(d),
+            Some(Event::Integer(i)) => Value::Integer(i),
+            Some(Event::Real(f)) => Value::Real(f),
+            Some(Event::String(s)) => Value::String(s.into_owned()),
+            Some(Event::Uid(u)) => Value::Uid(u),
+
+            Some(event @ Event::EndCollection) => {
+                self.report(error::unexpected_event_type(
+                    EventKind::ValueOrStartCollection,
+                    &event,
+                ))?;
+                // Leave the token in place so the enclosing collection is closed.
+                self.token = Some(event);
+                return Ok(None);
+            }
 
-            Some(Event::__Nonexhaustive) => unreachable!(),
+            None => {
+                self.report_end_of_stream()?;
+                return Ok(None);
+            }
+        };
+        Ok(Some(value))
+    }
 
-            None => Err(ErrorKind::UnexpectedEndOfEventStream.without_position()),
+    /// Skips the remainder of a collection which has been started, without recursing into nested
+    /// collections.
+    fn skip_collection(&mut self) -> Result<(), Error> {
+        let mut depth = 1;
+        while depth > 0 {
+            self.bump()?;
+            match self.token.take() {
+                Some(Event::StartArray(_)) | Some(Event::StartDictionary(_)) => depth += 1,
+                Some(Event::EndCollection) => depth -= 1,
+                Some(_) => (),
+                None if self.invalid_token => (),
+                None => return self.report_end_of_stream(),
+            }
         }
+        Ok(())
     }
 
     fn build_array(&mut self, len: Option<u64>) -> Result<Vec<Value>, Error> {
@@ -503,11 +998,20 @@ impl<T: Iterator<Item = Result<Event, Error>>> Builder<T> {
 
         loop {
             self.bump()?;
-            if let Some(Event::EndCollection) = self.token {
-                self.token.take();
-                return Ok(values);
+            match self.token {
+                Some(Event::EndCollection) => {
+                    self.token.take();
+                    return Ok(values);
+                }
+                None if !self.invalid_token => {
+                    self.report_end_of_stream()?;
+                    return Ok(values);
+                }
+                _ => (),
+            }
+            if let Some(value) = self.build_value()? {
+                values.push(value);
             }
-            values.push(self.build_value()?);
         }
     }
 
@@ -516,19 +1020,36 @@ impl<T: Iterator<Item = Result<Event, Error>>> Builder<T> {
 
         loop {
             self.bump()?;
-            match self.token.take() {
+            let key = match self.token.take() {
                 Some(Event::EndCollection) => return Ok(dict),
-                Some(Event::String(s)) => {
-                    self.bump()?;
-                    dict.insert(s, self.build_value()?);
+                Some(Event::String(s)) => Some(s.into_owned()),
+                None if self.invalid_token => None,
+                None => {
+                    self.report_end_of_stream()?;
+                    return Ok(dict);
                 }
                 Some(event) => {
-                    return Err(error::unexpected_event_type(
+                    self.report(error::unexpected_event_type(
                         EventKind::DictionaryKeyOrEndCollection,
                         &event,
-                    ))
+                    ))?;
+                    // Skip the whole of the invalid key.
+                    self.token = Some(event);
+                    self.build_value()?;
+                    None
                 }
-                None => return Err(ErrorKind::UnexpectedEndOfEventStream.without_position()),
+            };
+
+            self.bump()?;
+            let value = self.build_value()?;
+            if let (Some(key), Some(value)) = (key, value) {
+                dict.insert(key, value);
+            }
+
+            if let Some(Event::EndCollection) = self.token {
+                // The dictionary ended where a value was expected.
+                self.token = None;
+                return Ok(dict);
             }
         }
     }
@@ -536,11 +1057,38 @@ impl<T: Iterator<Item = Result<Event, Error>>> Builder<T> {
 
 #[cfg(test)]
 mod tests {
-    use std::time::SystemTime;
+    use std::{borrow::Cow, io::Cursor, time::SystemTime};
 
     use super::*;
     use crate::{stream::Event::*, Date, Dictionary, Value};
 
+    #[test]
+    fn deeply_nested_arrays() {
+        let depth = 100_000;
+        let xml = format!(
+            "<plist>{}{}</plist>",
+            "<array>".repeat(depth),
+            "</array>".repeat(depth)
+        );
+        let err = Value::from_reader(Cursor::new(xml.as_bytes())).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DepthLimitExceeded));
+
+        let events = (0..depth)
+            .map(|_| StartArray(None))
+            .chain((0..depth).map(|_| EndCollection))
+            .map(Ok);
+        let err = Value::from_events(events).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DepthLimitExceeded));
+
+        let depth = ReaderOptions::DEFAULT_MAX_DEPTH;
+        let xml = format!(
+            "<plist>{}{}</plist>",
+            "<array>".repeat(depth),
+            "</array>".repeat(depth)
+        );
+        assert!(Value::from_reader(Cursor::new(xml.as_bytes())).is_ok());
+    }
+
     #[test]
     fn value_accessors() {
         let vec = vec![Value::Real(0.0)];
@@ -564,14 +1112,14 @@ mod tests {
         );
 
         let date: Date = SystemTime::now().into();
-        assert_eq!(Value::Date(date.clone()).as_date(), Some(date));
+        assert_eq!(Value::Date(date).as_date(), Some(date));
 
         assert_eq!(Value::Real(0.0).as_real(), Some(0.0));
         assert_eq!(Value::Integer(1.into()).as_signed_integer(), Some(1));
         assert_eq!(Value::Integer(1.into()).as_unsigned_integer(), Some(1));
         assert_eq!(Value::Integer((-1).into()).as_unsigned_integer(), None);
         assert_eq!(
-            Value::Integer((i64::max_value() as u64 + 1).into()).as_signed_integer(),
+            Value::Integer((i64::MAX as u64 + 1).into()).as_signed_integer(),
             None
         );
         assert_eq!(Value::String("2".to_owned()).as_string(), Some("2"));
@@ -586,29 +1134,28 @@ mod tests {
         // Input
         let events = vec![
             StartDictionary(None),
-            String("Author".to_owned()),
-            String("William Shakespeare".to_owned()),
-            String("Lines".to_owned()),
+            String("Author".into()),
+            String("William Shakespeare".into()),
+            String("Lines".into()),
             StartArray(None),
-            String("It is a tale told by an idiot,".to_owned()),
-            String("Full of sound and fury, signifying nothing.".to_owned()),
+            String("It is a tale told by an idiot,".into()),
+            String("Full of sound and fury, signifying nothing.".into()),
             EndCollection,
-            String("Birthdate".to_owned()),
+            String("Birthdate".into()),
             Integer(1564.into()),
-            String("Height".to_owned()),
+            String("Height".into()),
             Real(1.60),
             EndCollection,
         ];
 
-        let builder = Builder::new(events.into_iter().map(|e| Ok(e)));
+        let builder = Builder::new(events.into_iter().map(Ok));
         let plist = builder.build();
 
         // Expected output
-        let mut lines = Vec::new();
-        lines.push(Value::String("It is a tale told by an idiot,".to_owned()));
-        lines.push(Value::String(
-            "Full of sound and fury, signifying nothing.".to_owned(),
-        ));
+        let lines = vec![
+            Value::String("It is a tale told by an idiot,".to_owned()),
+            Value::String("Full of sound and fury, signifying nothing.".to_owned()),
+        ];
 
         let mut dict = Dictionary::new();
         dict.insert(
@@ -621,4 +1168,192 @@ mod tests {
 
         assert_eq!(plist.unwrap(), Value::Dictionary(dict));
     }
+
+    #[test]
+    fn borrowed_events() {
+        let mut dict = Dictionary::new();
+        dict.insert("Data".to_owned(), Value::Data(vec![1, 2, 3]));
+        dict.insert(
+            "Lines".to_owned(),
+            Value::Array(vec!["a".into(), Value::Dictionary(Dictionary::new())]),
+        );
+        dict.insert("Height".to_owned(), Value::Real(1.60));
+        let value = Value::Dictionary(dict);
+
+        let events: Vec<_> = value.events().collect();
+        assert_eq!(events, value.clone().into_events().collect::<Vec<_>>());
+        assert!(matches!(&events[1], String(Cow::Borrowed("Data"))));
+        assert!(matches!(&events[2], Data(Cow::Borrowed([1, 2, 3]))));
+
+        assert_eq!(
+            Value::from_events(events.into_iter().map(Ok)).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn canonicalize() {
+        let mut inner = Dictionary::new();
+        inner.insert("b".to_owned(), Value::Real(-0.0));
+        inner.insert("a".to_owned(), Value::Real(-f64::NAN));
+        let mut dict = Dictionary::new();
+        dict.insert("z".to_owned(), Value::Array(vec![Value::Dictionary(inner)]));
+        dict.insert("y".to_owned(), true.into());
+        let mut value = Value::Dictionary(dict);
+
+        value.canonicalize();
+
+        let dict = value.as_dictionary().unwrap();
+        assert_eq!(dict.keys().collect::<Vec<_>>(), ["y", "z"]);
+        let inner = dict["z"].as_array().unwrap()[0].as_dictionary().unwrap();
+        assert_eq!(inner.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(inner["a"].as_real().unwrap().to_bits(), f64::NAN.to_bits());
+        assert_eq!(inner["b"].as_real().unwrap().to_bits(), 0.0f64.to_bits());
+    }
+
+    fn hash_of(value: &OrdValue) -> u64 {
+        let mut state = DefaultHasher::new();
+        value.hash(&mut state);
+        state.finish()
+    }
+
+    #[test]
+    fn ord_value_reals() {
+        let nan = OrdValue(Value::Real(f64::NAN));
+        assert_eq!(nan, nan.clone());
+        assert_eq!(hash_of(&nan), hash_of(&nan.clone()));
+
+        let zero = OrdValue(Value::Real(0.0));
+        let negative_zero = OrdValue(Value::Real(-0.0));
+        assert_ne!(zero, negative_zero);
+        assert!(negative_zero < zero);
+        assert!(OrdValue(Value::Real(-2.0)) < OrdValue(Value::Real(-1.0)));
+    }
+
+    #[test]
+    fn ord_value_dictionary_order_independent() {
+        let mut a = Dictionary::new();
+        a.insert("a".to_owned(), 1.into());
+        a.insert("b".to_owned(), Value::Real(2.0));
+
+        let mut b = Dictionary::new();
+        b.insert("b".to_owned(), Value::Real(2.0));
+        b.insert("a".to_owned(), 1.into());
+
+        let a = OrdValue(Value::Dictionary(a));
+        let b = OrdValue(Value::Dictionary(b));
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(hash_of(&a), hash_of(&b));
+    }
+
+    #[test]
+    fn ord_value_ordering() {
+        let mut values = vec![
+            OrdValue(Value::String("b".to_owned())),
+            OrdValue(Value::Integer(2.into())),
+            OrdValue(Value::String("a".to_owned())),
+            OrdValue(Value::Array(vec![1.into()])),
+            OrdValue(Value::Integer((-1).into())),
+            OrdValue(Value::Array(vec![])),
+        ];
+        values.sort();
+
+        let sorted: Vec<Value> = values.into_iter().map(OrdValue::into_inner).collect();
+        assert_eq!(
+            sorted,
+            vec![
+                Value::Array(vec![]),
+                Value::Array(vec![1.into()]),
+                Value::Integer((-1).into()),
+                Value::Integer(2.into()),
+                Value::String("a".to_owned()),
+                Value::String("b".to_owned()),
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_diagnostics() {
+        let plist = r#"<plist version="1.0">
+<dict>
+    <key>good</key>
+    <integer>1</integer>
+    <key>date</key>
+    <date>yesterday</date>
+    <key>unknown</key>
+    <thing><nested/></thing>
+    <key>array</key>
+    <array>
+        <data>!!!</data>
+        stray
+        <string>kept</string>
+    </array>
+</dict>
+</plist>"#;
+
+        let (value, diagnostics) = Value::from_reader_xml_with_diagnostics(plist.as_bytes());
+
+        let mut expected = Dictionary::new();
+        expected.insert("good".to_owned(), 1.into());
+        expected.insert("array".to_owned(), Value::Array(vec!["kept".into()]));
+        assert_eq!(value, Some(Value::Dictionary(expected)));
+
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|err| {
+                (
+                    format!("{:?}", err.kind()),
+                    err.line_column().map(|(l, _)| l),
+                )
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("InvalidDateString".to_owned(), Some(6)),
+                ("UnknownXmlElement".to_owned(), Some(8)),
+                ("InvalidDataString".to_owned(), Some(11)),
+                (
+                    "UnexpectedXmlCharactersExpectedElement".to_owned(),
+                    Some(11)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn xml_diagnostics_malformed() {
+        let plist = "<plist><array><string>a</string><integer>x</integer><string>b</strin";
+
+        let (value, diagnostics) = Value::from_reader_xml_with_diagnostics(plist.as_bytes());
+
+        assert_eq!(value, Some(Value::Array(vec!["a".into(), "b".into()])));
+        assert_eq!(diagnostics.len(), 2);
+        assert!(matches!(
+            diagnostics[0].kind(),
+            ErrorKind::InvalidIntegerString
+        ));
+    }
+
+    #[test]
+    fn binary_diagnostics() {
+        let mut buf = Vec::new();
+        Value::Array(vec![1.into(), 2.into(), 3.into()])
+            .to_writer_binary(&mut buf)
+            .unwrap();
+
+        // Point the second array element at an object which does not exist.
+        let array_start = buf.iter().position(|&b| b == 0xa3).unwrap();
+        buf[array_start + 2] = 0x7f;
+
+        let (value, diagnostics) = Value::from_reader_with_diagnostics(Cursor::new(buf));
+
+        assert_eq!(value, Some(Value::Array(vec![1.into(), 3.into()])));
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            diagnostics[0].kind(),
+            ErrorKind::ObjectReferenceTooLarge
+        ));
+    }
 }
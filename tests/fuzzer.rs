@@ -64,10 +64,20 @@ fn test_fuzzer_data(data: &[u8]) -> Result<Value, Error> {
     Value::from_reader(cursor)
 }
 
+fn test_fuzzer_data_with_diagnostics(data: &[u8]) -> (Option<Value>, Vec<Error>) {
+    let cursor = Cursor::new(data);
+    Value::from_reader_with_diagnostics(cursor)
+}
+
 fn test_fuzzer_data_ok(data: &[u8]) {
-    test_fuzzer_data(data).unwrap();
+    let value = test_fuzzer_data(data).unwrap();
+    let (diagnostic_value, diagnostics) = test_fuzzer_data_with_diagnostics(data);
+    assert_eq!(diagnostic_value, Some(value));
+    assert!(diagnostics.is_empty());
 }
 
 fn test_fuzzer_data_err(data: &[u8]) {
     assert!(test_fuzzer_data(data).is_err());
+    let (_, diagnostics) = test_fuzzer_data_with_diagnostics(data);
+    assert!(!diagnostics.is_empty());
 }